@@ -0,0 +1,47 @@
+#![no_std]
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use naming::shared_types::{OpenOptions, STDIN};
+#[allow(unused_imports)]
+use runtime::*;
+use terminal::{print, println};
+
+/// Print everything readable from `handle` until end of file, returns `false` if reading fails
+fn print_handle(handle: usize) -> bool {
+    let mut buf: [u8; 512] = [0; 512];
+    loop {
+        match naming::read(handle, &mut buf) {
+            Ok(0) => return true,
+            Ok(len) => print!("{}", String::from_utf8_lossy(&buf[..len])),
+            Err(_) => return false,
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+pub fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    // Without arguments, copy the standard input (e.g. the read end of a pipe)
+    if args.len() == 1 {
+        if !print_handle(STDIN) {
+            println!("usage: cat file ...");
+        }
+        return;
+    }
+
+    for path in &args[1..] {
+        match naming::open(path, OpenOptions::READONLY) {
+            Ok(fh) => {
+                if !print_handle(fh) {
+                    println!("cat: {}: read failed", path);
+                }
+                let _ = naming::close(fh);
+            }
+            Err(error) => println!("cat: {}: {:?}", path, error),
+        }
+    }
+}
@@ -2,9 +2,11 @@
 
 extern crate alloc;
 
+use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
-use naming::cwd;
+use chrono::DateTime;
+use naming::{cwd, readlink, stat};
 
 use naming::shared_types::{DirEntry, FileType, OpenOptions};
 #[allow(unused_imports)]
@@ -12,18 +14,69 @@ use runtime::*;
 use terminal::{print, println};
 
 fn print_usage() {
-    println!("usage: ls [directory_name");
+    println!("usage: ls [-l] [directory_name]");
 }
 
-fn print_dir_entry(dentry: DirEntry) {
-    if dentry.file_type == FileType::Directory {
-        println!("d {}", dentry.name);
-    } else {
-        println!("- {}", dentry.name);
+fn type_char(file_type: FileType) -> char {
+    match file_type {
+        FileType::Directory => 'd',
+        FileType::Link => 'l',
+        FileType::CharDevice => 'c',
+        FileType::BlockDevice => 'b',
+        FileType::Fifo => 'p',
+        _ => '-',
+    }
+}
+
+/// Format permission bits like "rwxr-xr-x"
+fn permissions_string(permissions: u32) -> String {
+    let mut string = String::new();
+    for shift in [6, 3, 0] {
+        let bits = permissions >> shift;
+        string.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        string.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        string.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+    }
+    string
+}
+
+/// Format a timestamp (ms since 1.1.1970) like "2025-03-28 14:05"
+fn time_string(time_ms: u64) -> String {
+    match DateTime::from_timestamp_millis(time_ms as i64) {
+        Some(time) => format!("{}", time.format("%Y-%m-%d %H:%M")),
+        None => String::from("?"),
     }
 }
 
-fn process_ls(path: &String) {
+fn print_dir_entry(path: &String, dentry: DirEntry, long: bool) {
+    if !long {
+        println!("{} {}", type_char(dentry.file_type), dentry.name);
+        return;
+    }
+
+    let entry_path = format!("{}/{}", path.trim_end_matches('/'), dentry.name);
+    if dentry.file_type == FileType::Link {
+        let target = readlink(&entry_path).unwrap_or(String::from("?"));
+        println!("lrwxrwxrwx {:>5} {:>5} {:>10} {:>16} {} -> {}", "", "", target.len(), "", dentry.name, target);
+        return;
+    }
+
+    match stat(&entry_path) {
+        Ok(file_stat) => println!(
+            "{}{} {:>5} {:>5} {:>10} {:>16} {}",
+            type_char(file_stat.file_type),
+            permissions_string(file_stat.permissions),
+            file_stat.uid,
+            file_stat.gid,
+            file_stat.size,
+            time_string(file_stat.modified_time),
+            dentry.name
+        ),
+        Err(_) => println!("{}????????? {:>5} {:>5} {:>10} {:>16} {}", type_char(dentry.file_type), "?", "?", "?", "?", dentry.name),
+    }
+}
+
+fn process_ls(path: &String, long: bool) {
     // open directory
     let res = naming::open(&path, OpenOptions::DIRECTORY);
     if res.is_err() {
@@ -32,18 +85,9 @@ fn process_ls(path: &String) {
     }
     let fd = res.unwrap();
 
-    // dump content of directory
-    loop {
-        let res = naming::readdir(fd);
-        match res {
-            Ok(data) => {
-                match data {
-                    Some(content) => print_dir_entry(content),
-                    None => break,
-                }
-            },
-            Err(_) => break
-        }
+    // dump content of directory (stops at the first error)
+    for content in naming::readdir(fd).map_while(Result::ok) {
+        print_dir_entry(path, content, long);
     }
 
     // close directory
@@ -62,17 +106,17 @@ pub fn args_to_vec() -> Vec<String> {
 #[unsafe(no_mangle)]
 pub fn main() {
     let args_vec = args_to_vec();
-    let args_count = args_vec.len();
-
+    let long = args_vec.iter().skip(1).any(|arg| arg == "-l");
+    let paths: Vec<&String> = args_vec.iter().skip(1).filter(|arg| *arg != "-l").collect();
 
-    if args_count == 1 {
+    if paths.is_empty() {
         let res = cwd();
         match res {
-            Ok(path) =>  process_ls(&path),
+            Ok(path) =>  process_ls(&path, long),
             Err(_) => print_usage(),
         }
-    } else if args_count == 2 {
-        process_ls(&args_vec[1]);
+    } else if paths.len() == 1 {
+        process_ls(paths[0], long);
     } else {
         print_usage();
     }
@@ -0,0 +1,49 @@
+#![no_std]
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use naming::rename;
+use naming::shared_types::OpenOptions;
+#[allow(unused_imports)]
+use runtime::*;
+use terminal::{print, println};
+
+fn print_usage() {
+    println!("usage: mv source target");
+}
+
+/// Check if `path` is an existing directory
+fn is_dir(path: &str) -> bool {
+    match naming::open(path, OpenOptions::DIRECTORY) {
+        Ok(fd) => {
+            let _ = naming::close(fd);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+#[unsafe(no_mangle)]
+pub fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        print_usage();
+        return;
+    }
+
+    let source = &args[1];
+    let mut target = args[2].clone();
+
+    // Moving into an existing directory keeps the name of the source
+    if is_dir(&target) {
+        let name = source.trim_end_matches('/').rsplit('/').next().unwrap_or(source);
+        target = format!("{}/{}", target.trim_end_matches('/'), name);
+    }
+
+    if let Err(e) = rename(source, &target) {
+        println!("mv: cannot move '{}' to '{}': {:?}", source, target, e);
+    }
+}
@@ -78,15 +78,9 @@ pub fn main() {
     let fd = res.unwrap();
     println!("open dir '/test'");
 
-    loop {
-        let res = naming::readdir(fd);
+    for res in naming::readdir(fd) {
         match res {
-            Ok(data) => {
-                match data {
-                    Some(content) => println!("   readdir data = {:?}", content),
-                    None => break,
-                }
-            },
+            Ok(content) => println!("   readdir data = {:?}", content),
             Err(_) => { 
                 println!("   readdir failed");
                 break;
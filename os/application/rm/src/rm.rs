@@ -0,0 +1,29 @@
+#![no_std]
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use naming::unlink;
+#[allow(unused_imports)]
+use runtime::*;
+use terminal::{print, println};
+
+fn print_usage() {
+    println!("usage: rm file_name ...");
+}
+
+#[unsafe(no_mangle)]
+pub fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        print_usage();
+        return;
+    }
+
+    for path in &args[1..] {
+        if let Err(e) = unlink(path) {
+            println!("rm: cannot remove '{}': {:?}", path, e);
+        }
+    }
+}
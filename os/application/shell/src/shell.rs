@@ -2,10 +2,13 @@
 
 extern crate alloc;
 
+use alloc::format;
+use alloc::vec;
 use alloc::string::String;
 use alloc::vec::Vec;
-use concurrent::thread;
-use naming::{mkdir, touch, cwd, cd};
+use concurrent::{process, thread};
+use naming::{mkdir, mkfifo, touch, cwd, cd, mount, umount, open, pipe, close, dup, dup2, sync, chmod, chown, stat, utimes};
+use naming::shared_types::{OpenOptions, STDERR, STDIN, STDOUT, UTIME_NOW};
 #[allow(unused_imports)]
 use runtime::*;
 use terminal::read::read;
@@ -35,6 +38,29 @@ fn process_mkdir(split: &Vec<&str>) {
     }
 }
 
+fn process_mkfifo(split: &Vec<&str>) {
+    if split.len() != 2 {
+        println!("usage: mkfifo fifo_name");
+        return ;
+    }
+    let res = mkfifo(&split[1]);
+    if res.is_err() {
+        println!("mkfifo failed: {:?}", res.unwrap_err());
+    }
+}
+
+fn process_touch(split: &Vec<&str>) {
+    if split.len() != 2 {
+        println!("usage: touch file_name");
+        return ;
+    }
+    // like Unix: create the file or update the timestamps of an existing one
+    let res = if stat(&split[1]).is_ok() { utimes(&split[1], UTIME_NOW, UTIME_NOW) } else { touch(&split[1]) };
+    if res.is_err() {
+        println!("{:?}", res);
+    }
+}
+
 fn process_cd(split: &Vec<&str>) {
     if split.len() != 2 {
         println!("usage: cd directory_name");
@@ -46,6 +72,58 @@ fn process_cd(split: &Vec<&str>) {
     }
 }
 
+fn process_mount(split: &Vec<&str>) {
+    if split.len() != 4 {
+        println!("usage: mount device directory_name type");
+        return ;
+    }
+    let res = mount(&split[1], &split[2], &split[3]);
+    if res.is_err() {
+        println!("mount failed: {:?}", res.unwrap_err());
+    }
+}
+
+fn process_umount(split: &Vec<&str>) {
+    if split.len() != 2 {
+        println!("usage: umount directory_name");
+        return ;
+    }
+    let res = umount(&split[1]);
+    if res.is_err() {
+        println!("umount failed: {:?}", res.unwrap_err());
+    }
+}
+
+fn process_chmod(split: &Vec<&str>) {
+    let permissions = if split.len() == 3 { u32::from_str_radix(split[1], 8).ok() } else { None };
+    match permissions {
+        Some(permissions) => {
+            let res = chmod(&split[2], permissions);
+            if res.is_err() {
+                println!("chmod failed: {:?}", res.unwrap_err());
+            }
+        }
+        None => println!("usage: chmod octal_permissions path"),
+    }
+}
+
+fn process_chown(split: &Vec<&str>) {
+    // owner is given as uid:gid
+    let ids = if split.len() == 3 { split[1].split_once(':') } else { None };
+    match ids.and_then(|(uid, gid)| Some((uid.parse::<u32>().ok()?, gid.parse::<u32>().ok()?))) {
+        Some((uid, gid)) => {
+            let res = chown(&split[2], uid, gid);
+            if res.is_err() {
+                println!("chown failed: {:?}", res.unwrap_err());
+            }
+        }
+        None => println!("usage: chown uid:gid path"),
+    }
+}
+
+/// Commands executed by the shell itself (see `process_internal_command`)
+const INTERNAL_COMMANDS: [&str; 11] = ["pwd", "cd", "mkdir", "mkfifo", "mount", "umount", "chmod", "chown", "id", "sync", "touch"];
+
 fn process_internal_command(split: &Vec<&str>) -> bool {
     if split[0] == "pwd" {
         process_pwd(split);
@@ -56,27 +134,238 @@ fn process_internal_command(split: &Vec<&str>) -> bool {
     } else if split[0] == "mkdir" {
         process_mkdir(split);
         return true;
-    } else if split[0] == "touch" {
-        let res = touch(&split[1]);
+    } else if split[0] == "mkfifo" {
+        process_mkfifo(split);
+        return true;
+    } else if split[0] == "mount" {
+        process_mount(split);
+        return true;
+    } else if split[0] == "umount" {
+        process_umount(split);
+        return true;
+    } else if split[0] == "chmod" {
+        process_chmod(split);
+        return true;
+    } else if split[0] == "chown" {
+        process_chown(split);
+        return true;
+    } else if split[0] == "id" {
+        println!("uid={} gid={}", process::uid(), process::gid());
+        return true;
+    } else if split[0] == "sync" {
+        let res = sync();
         if res.is_err() {
             println!("{:?}", res);
         }
         return true;
+    } else if split[0] == "touch" {
+        process_touch(split);
+        return true;
     }
     return false;
 }
 
+/// I/O redirections of a command: `< file`, `> file`, `>> file` and `2>&1`
+struct Redirections<'a> {
+    input: Option<&'a str>,
+    output: Option<(&'a str, bool)>, // (file, append)
+    error_to_output: bool,
+}
+
+/// Split `command` into the arguments and the redirections (the file name may be attached, e.g. `>file`). \
+/// Returns `None`, if a file name is missing.
+fn parse_redirections<'a>(command: &Vec<&'a str>) -> Option<(Vec<&'a str>, Redirections<'a>)> {
+    let mut args = Vec::new();
+    let mut redirections = Redirections { input: None, output: None, error_to_output: false };
+
+    let mut tokens = command.iter();
+    while let Some(&token) = tokens.next() {
+        if token == "2>&1" {
+            redirections.error_to_output = true;
+            continue;
+        }
+
+        let (operator, file) = if let Some(file) = token.strip_prefix(">>") {
+            (">>", file)
+        } else if let Some(file) = token.strip_prefix('>') {
+            (">", file)
+        } else if let Some(file) = token.strip_prefix('<') {
+            ("<", file)
+        } else {
+            args.push(token);
+            continue;
+        };
+
+        let file = if file.is_empty() { *tokens.next()? } else { file };
+        match operator {
+            "<" => redirections.input = Some(file),
+            ">>" => redirections.output = Some((file, true)),
+            _ => redirections.output = Some((file, false)),
+        }
+    }
+    Some((args, redirections))
+}
+
+/// Open the files of `redirections` and add the resulting handles for the child to `handles`
+/// (pairs `(handle in child, handle in shell)`). The opened handles are added to `files` and must
+/// be closed by the caller after starting the child.
+fn open_redirections(redirections: &Redirections, handles: &mut Vec<(usize, usize)>, files: &mut Vec<usize>) -> Result<(), String> {
+    if let Some(file) = redirections.input {
+        let fh = open(file, OpenOptions::READONLY).map_err(|error| format!("{}: {:?}", file, error))?;
+        files.push(fh);
+        handles.push((STDIN, fh));
+    }
+
+    if let Some((file, append)) = redirections.output {
+        let mode = if append { OpenOptions::APPEND } else { OpenOptions::TRUNCATE };
+        let fh = open(file, OpenOptions::WRITEONLY | OpenOptions::CREATE | mode).map_err(|error| format!("{}: {:?}", file, error))?;
+        files.push(fh);
+        handles.push((STDOUT, fh));
+    }
+
+    if redirections.error_to_output {
+        // the standard error output of the child gets the same object as its standard output
+        let output = handles.iter().rev().find(|(child, _)| *child == STDOUT).map(|(_, parent)| *parent).unwrap_or(STDOUT);
+        handles.push((STDERR, output));
+    }
+    Ok(())
+}
+
+/// Run an internal command with its standard input and output redirected (see `Redirections`). \
+/// The redirected handles of the shell are saved and restored afterwards using `dup` and `dup2`.
+fn process_redirected_internal_command(args: &Vec<&str>, redirections: &Redirections) {
+    let mut handles = Vec::new();
+    let mut files = Vec::new();
+    if let Err(error) = open_redirections(redirections, &mut handles, &mut files) {
+        for fh in files {
+            let _ = close(fh);
+        }
+        println!("{}", error);
+        return;
+    }
+
+    let mut saved_handles = Vec::new(); // pairs (handle, saved copy)
+    let mut result = Ok(());
+    for &(handle, fh) in handles.iter() {
+        result = dup(handle).and_then(|saved| {
+            saved_handles.push((handle, saved));
+            dup2(fh, handle)
+        }).map(|_| ());
+        if result.is_err() {
+            break;
+        }
+    }
+    for fh in files {
+        let _ = close(fh);
+    }
+
+    if result.is_ok() {
+        process_internal_command(args);
+    }
+    for (handle, saved) in saved_handles.into_iter().rev() {
+        let _ = dup2(saved, handle);
+        let _ = close(saved);
+    }
+    if let Err(error) = result {
+        println!("redirection failed: {:?}", error);
+    }
+}
+
+/// Run the applications of `commands` concurrently, connecting the standard output
+/// of each application with the standard input of the next one by a pipe. \
+/// Each command may redirect its input and output (see `Redirections`), taking precedence over the pipes.
+fn process_pipeline(commands: &Vec<Vec<&str>>) {
+    if commands.iter().any(|command| command.is_empty()) {
+        println!("usage: command | command ...");
+        return;
+    }
+
+    let mut apps = Vec::new();
+    let mut errors = Vec::new();
+    let mut input: Option<usize> = None; // read end of the previous pipe
+    for (i, command) in commands.iter().enumerate() {
+        let (args, redirections) = match parse_redirections(command) {
+            Some((args, redirections)) if !args.is_empty() => (args, redirections),
+            _ => {
+                errors.push(String::from("usage: command [< file] [> file | >> file] [2>&1]"));
+                break;
+            }
+        };
+
+        let mut handles = Vec::new();
+        if let Some(read_handle) = input {
+            handles.push((STDIN, read_handle));
+        }
+
+        let mut output = None;
+        if i < commands.len() - 1 {
+            match pipe() {
+                Ok((read_handle, write_handle)) => {
+                    handles.push((STDOUT, write_handle));
+                    output = Some((read_handle, write_handle));
+                }
+                Err(error) => {
+                    errors.push(format!("pipe failed: {:?}", error));
+                    break;
+                }
+            }
+        }
+
+        let mut files = Vec::new();
+        match open_redirections(&redirections, &mut handles, &mut files) {
+            Ok(()) => match thread::start_application_with_handles(args[0], args[1..].to_vec(), &handles) {
+                Some(app) => apps.push(app),
+                None => errors.push(format!("{}: Command not found!", args[0])),
+            },
+            Err(error) => errors.push(error),
+        }
+
+        // The applications own the pipe ends and files now, the reader gets end of file when the writer terminates
+        for fh in files {
+            let _ = close(fh);
+        }
+        if let Some(read_handle) = input {
+            let _ = close(read_handle);
+        }
+        input = output.map(|(read_handle, write_handle)| {
+            let _ = close(write_handle);
+            read_handle
+        });
+    }
+    if let Some(read_handle) = input {
+        let _ = close(read_handle);
+    }
+
+    // Print errors not before all pipe ends are closed, the shell must not write into a pipe
+    for error in errors {
+        println!("{}", error);
+    }
+    for app in apps {
+        app.join();
+    }
+}
+
+/// Run a single command, either internal or an application (with optional redirections)
+fn process_command(command: &Vec<&str>) {
+    match parse_redirections(command) {
+        Some((args, redirections)) if !args.is_empty() && INTERNAL_COMMANDS.contains(&args[0]) => {
+            process_redirected_internal_command(&args, &redirections);
+        }
+        _ => process_pipeline(&vec![command.clone()]),
+    }
+}
+
 fn process_next_char(line: &mut String, ch: char) {
     match ch {
         '\n' => {
-            let split = line.split_whitespace().collect::<Vec<&str>>();
-            if !split.is_empty() {
-                if !process_internal_command(&split) {
-                    match thread::start_application(split[0], split[1..].iter().map(|&s| s).collect()) {
-                        Some(app) => app.join(),
-                        None => println!("Command not found!"),
-                    }
-                }
+            let commands = line
+                .split('|')
+                .map(|command| command.split_whitespace().collect::<Vec<&str>>())
+                .collect::<Vec<Vec<&str>>>();
+            if commands.len() > 1 {
+                process_pipeline(&commands);
+            } else if !commands[0].is_empty() {
+                process_command(&commands[0]);
             }
 
             line.clear();
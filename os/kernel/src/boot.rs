@@ -21,7 +21,7 @@ use crate::process::thread::Thread;
 use crate::syscall::syscall_dispatcher;
 use crate::{
     acpi_tables, allocator, apic, built_info, gdt, init_acpi_tables, init_apic, init_initrd,
-    init_pci, init_serial_port, init_terminal, initrd, keyboard, logger, memory, network,
+    init_pci, init_serial_port, init_terminal, keyboard, logger, memory, network,
     process_manager, scheduler, serial_port, terminal, timer, tss,
 };
 use crate::{efi_services_available, naming, storage};
@@ -316,8 +316,47 @@ pub extern "C" fn start(multiboot2_magic: u32, multiboot2_addr: *const BootInfor
         }
     }
 
-    // Init naming service
-    naming::api::init();
+    // Init naming service (the kernel command line may select an ext2 root file system, e.g. "root=ata0p0")
+    let root_device = multiboot
+        .command_line_tag()
+        .and_then(|tag| tag.cmdline().ok())
+        .and_then(|cmdline| cmdline.split_whitespace().find_map(|arg| arg.strip_prefix("root=")));
+    naming::api::init(root_device);
+
+    // Make kernel devices available at /dev (the directory may already exist on a persistent root file system)
+    let dev_dir = "/dev".to_string();
+    let _ = naming::api::mkdir(&dev_dir);
+    naming::api::mount(&"devfs".to_string(), &dev_dir, &"devfs".to_string()).expect("Failed to mount devfs");
+
+    // Named shared memory objects live at /dev/shm (mount point provided by devfs)
+    naming::api::mount(&"shmfs".to_string(), &"/dev/shm".to_string(), &"shmfs".to_string()).expect("Failed to mount shmfs");
+
+    // Make process and kernel information available at /proc
+    let proc_dir = "/proc".to_string();
+    let _ = naming::api::mkdir(&proc_dir);
+    naming::api::mount(&"procfs".to_string(), &proc_dir, &"procfs".to_string()).expect("Failed to mount procfs");
+
+    // Mount first partition of the hard disk (if available and not used as root file system)
+    if storage::block_device("ata0p0").is_some() && root_device != Some("ata0p0") {
+        let mount_point = "/mnt/hdd".to_string();
+        let _ = naming::api::mkdir(&"/mnt".to_string());
+        let _ = naming::api::mkdir(&mount_point);
+        let device = "ata0p0".to_string();
+        let mounted = naming::api::mount(&device, &mount_point, &"fat32".to_string())
+            .or_else(|_| naming::api::mount(&device, &mount_point, &"ext2".to_string()));
+        if let Err(error) = mounted {
+            warn!("Failed to mount [ata0p0] at [{}]: {:?}", mount_point, error);
+        }
+    }
+
+    // Mount the file system in non-volatile memory (if available), keeping small files across reboots and power loss
+    if nvmem::region("pmem0").is_some() {
+        let mount_point = "/pmem".to_string();
+        let _ = naming::api::mkdir(&mount_point);
+        if let Err(error) = naming::api::mount(&"pmem0".to_string(), &mount_point, &"pmemfs".to_string()) {
+            warn!("Failed to mount [pmem0] at [{}]: {:?}", mount_point, error);
+        }
+    }
 
     // Load initial ramdisk
     let initrd_tag = multiboot
@@ -326,6 +365,11 @@ pub extern "C" fn start(multiboot2_magic: u32, multiboot2_addr: *const BootInfor
         .expect("Initrd not found!");
     init_initrd(initrd_tag);
 
+    // Make the applications of the initial ramdisk available at /bin
+    let bin_dir = "/bin".to_string();
+    let _ = naming::api::mkdir(&bin_dir);
+    naming::api::mount(&"initrd".to_string(), &bin_dir, &"tarfs".to_string()).expect("Failed to mount initial ramdisk");
+
     // Create and register the cleanup thread in the scheduler
     // (If the last thread of a process terminates, it cannot delete its own address space)
     scheduler().ready(Thread::new_kernel_thread(
@@ -338,16 +382,25 @@ pub extern "C" fn start(multiboot2_magic: u32, multiboot2_addr: *const BootInfor
         "cleanup",
     ));
 
+    // Create and register the write-back thread of the block caches
+    scheduler().ready(Thread::new_kernel_thread(
+        || {
+            loop {
+                scheduler().sleep(storage::WRITE_BACK_INTERVAL_MS);
+                if storage::sync().is_err() {
+                    warn!("Failed to write back cached sectors");
+                }
+            }
+        },
+        "bcache",
+    ));
+
     // Create and register the 'shell' thread (from app image in ramdisk) in the scheduler
     scheduler().ready(Thread::load_application(
-        initrd()
-            .entries()
-            .find(|entry| entry.filename().as_str().unwrap() == "shell")
-            .expect("Shell application not available!")
-            .data(),
+        &naming::api::load_file(&"/bin/shell".to_string()).expect("Shell application not available!"),
         "shell",
         &Vec::new(),
-    ));
+    ).expect("Shell application not executable!"));
 
     // Disable terminal logging (remove terminal output stream)
     logger().remove(terminal().as_ref());
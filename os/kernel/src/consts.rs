@@ -11,5 +11,10 @@ pub const USER_SPACE_ARG_START: usize = USER_SPACE_ENV_START;
 // User space stacks (Max size per stack: 1 GiB)
 pub const MAX_USER_STACK_SIZE: usize = 0x40000000;  // 1 GiB
 pub const MAIN_USER_STACK_START: usize = USER_SPACE_ENV_START + 0x40000000;  // 1 GiB
+
+// Memory-mapped files (above all user stacks)
+pub const USER_SPACE_MMAP_START: usize = USER_SPACE_START + 0x3f0000000000;  // 64 TiB
+pub const USER_SPACE_MMAP_END: usize = 0x7ffffffff000;  // 128 TiB (lower half without its last page)
+
 pub const KERNEL_STACK_PAGES: usize = 64;
 pub const STACK_ENTRY_SIZE: usize = 8;  
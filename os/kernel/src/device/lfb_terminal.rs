@@ -51,6 +51,7 @@ pub struct LFBTerminal {
     color: Mutex<ColorState>,
     parser: Mutex<RefCell<Parser>>,
     decoder: Mutex<Keyboard<AnyLayout, ScancodeSet1>>,
+    pending: Mutex<Option<char>>, // character decoded by 'has_input', but not read yet
 }
 
 pub struct CursorThread {
@@ -165,6 +166,11 @@ impl OutputStream for LFBTerminal {
 
 impl InputStream for LFBTerminal {
     fn read_byte(&self) -> i16 {
+        if let Some(c) = self.pending.lock().take() {
+            self.write_byte(c as u8);
+            return c as i16;
+        }
+
         if let Some(keyboard) = keyboard() {
             let read_byte;
 
@@ -205,6 +211,27 @@ impl Terminal for LFBTerminal {
         LFBTerminal::clear_screen(&mut display, &mut color);
         LFBTerminal::position(&mut display, &mut cursor, &mut color, (0, 0));
     }
+
+    /// Decode the scancodes received so far, until a character is complete (kept for the next 'read_byte')
+    fn has_input(&self) -> bool {
+        let mut pending = self.pending.lock();
+        if pending.is_some() {
+            return true;
+        }
+
+        if let Some(keyboard) = keyboard() {
+            let mut decoder = self.decoder.lock();
+            while let Some(scancode) = keyboard.try_read_byte() {
+                if let Ok(Some(event)) = decoder.add_byte(scancode) {
+                    if let Some(DecodedKey::Unicode(c)) = decoder.process_keyevent(event) {
+                        *pending = Some(c);
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
 }
 
 impl LFBTerminal {
@@ -214,7 +241,8 @@ impl LFBTerminal {
             cursor: Mutex::new(CursorState::new()),
             color: Mutex::new(ColorState::new()),
             parser: Mutex::new(RefCell::new(Parser::<Utf8Parser>::new())),
-            decoder: Mutex::new(Keyboard::new(ScancodeSet1::new(), AnyLayout::De105Key(De105Key), HandleControl::Ignore))
+            decoder: Mutex::new(Keyboard::new(ScancodeSet1::new(), AnyLayout::De105Key(De105Key), HandleControl::Ignore)),
+            pending: Mutex::new(None),
         }
     }
 
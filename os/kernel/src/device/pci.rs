@@ -94,6 +94,10 @@ impl PciBus {
         &self.config_space
     }
 
+    pub fn devices(&self) -> &Vec<RwLock<EndpointHeader>> {
+        &self.devices
+    }
+
     pub fn search_by_ids(&self, vendor_id: u16, device_id: u16) -> Vec<&RwLock<EndpointHeader>> {
         self.devices.iter()
             .filter(|device| device.read().header().id(self.config_space()) == (vendor_id, device_id))
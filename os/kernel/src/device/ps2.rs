@@ -10,10 +10,14 @@ use ps2::{Controller, KeyboardType};
 use ps2::error::{ControllerError, KeyboardError};
 use spin::Mutex;
 use spin::once::Once;
-use crate::{apic, interrupt_dispatcher};
+use crate::process::scheduler::WaitQueue;
+use crate::{apic, interrupt_dispatcher, scheduler};
 
 const KEYBOARD_BUFFER_CAPACITY: usize = 128;
 
+/// Threads waiting for keyboard input, woken up by the interrupt handler
+pub static INPUT_QUEUE: WaitQueue = WaitQueue::new();
+
 pub struct PS2 {
     controller: Arc<Mutex<Controller>>,
     keyboard: Once<Arc<Keyboard>>,
@@ -34,9 +38,15 @@ impl Keyboard {
     }
 
     pub fn plugin(keyboard: Arc<Keyboard>) {
+        scheduler().register_interrupt_queue(&INPUT_QUEUE);
         interrupt_dispatcher().assign(InterruptVector::Keyboard, Box::new(KeyboardInterruptHandler::new(Arc::clone(&keyboard))));
         apic().allow(InterruptVector::Keyboard);
     }
+
+    /// Return the next scancode from the buffer without waiting
+    pub fn try_read_byte(&self) -> Option<u8> {
+        self.buffer.0.try_dequeue().ok()
+    }
 }
 
 impl InputStream for Keyboard {
@@ -66,6 +76,7 @@ impl InterruptHandler for KeyboardInterruptHandler {
                         panic!("Keyboard: Failed to store received byte in buffer!");
                     }
                 }
+                scheduler().wake_up_from_interrupt(&INPUT_QUEUE);
             }
         } else {
             panic!("Keyboard: Controller is locked during interrupt!");
@@ -333,6 +333,10 @@ impl SerialPort {
         }
     }
 
+    pub fn port(&self) -> ComPort {
+        self.port
+    }
+
     pub fn plugin(serial_port: Arc<SerialPort>) {
         let vector = match serial_port.port {
             Com1 | Com3 => InterruptVector::Com1,
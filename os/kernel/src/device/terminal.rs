@@ -6,6 +6,9 @@ use crate::terminal;
 
 pub trait Terminal: OutputStream + InputStream {
     fn clear(&self);
+
+    /// Check without blocking, if `read_byte` can return a character immediately
+    fn has_input(&self) -> bool;
 }
 
 // Implementation of the 'core::fmt::Write' trait for our Terminal
@@ -6,9 +6,9 @@ use core::ptr;
 use spin::Mutex;
 use x86_64::registers::control::Cr2;
 use x86_64::set_general_handler;
-use x86_64::structures::idt::InterruptStackFrame;
+use x86_64::structures::idt::{InterruptStackFrame, PageFaultErrorCode};
 use crate::{apic, idt, interrupt_dispatcher, scheduler};
-use crate::memory::PAGE_SIZE;
+use crate::memory::{mmap, PAGE_SIZE};
 
 #[repr(u8)]
 #[derive(PartialEq, PartialOrd, Copy, Clone, Debug)]
@@ -209,8 +209,11 @@ fn handle_page_fault(frame: InterruptStackFrame, _index: u8, error: Option<u64>)
     let thread = scheduler().current_thread();
 
     // Check if page fault occurred right below the user stack
+    let not_present = !PageFaultErrorCode::from_bits_truncate(error.unwrap_or(0)).contains(PageFaultErrorCode::PROTECTION_VIOLATION);
     if !thread.is_kernel_thread() && !thread.stacks_locked() && fault_addr > (thread.user_stack_start() - PAGE_SIZE as u64) && fault_addr < thread.user_stack_start() {
         thread.grow_user_stack(); // Grow stack by one page
+    } else if not_present && mmap::handle_page_fault(&thread.process().virtual_address_space, fault_addr) {
+        // Page of a memory-mapped file has been read from the file
     } else {
         panic!("Page Fault!\nError code: [{:?}]\nAddress: [0x{:0>16x}]\n{:?}", error, fault_addr, frame);
     }
@@ -6,6 +6,7 @@
    ║   - allocator_locked   check if allocator is locked                     ║
    ║   - dump               get a dump of the current free list              ║ 
    ║   - free               free a range of frames                           ║
+   ║   - free_frames        get the number of currently free frames          ║
    ║   - insert             insert free frame region detected during boot    ║
    ║   - phys_limit         get the highest phys. addr. managed by the alloc.║
    ║   - reserve            permanently reserve a range of frames            ║
@@ -60,6 +61,11 @@ pub fn alloc(frame_count: usize) -> PhysFrameRange {
     PAGE_FRAME_ALLOCATOR.lock().alloc_block(frame_count)
 }
 
+/// Allocate `frame_count` contiguous page frames, returning `None` instead of panicking if there is not enough memory.
+pub fn try_alloc(frame_count: usize) -> Option<PhysFrameRange> {
+    PAGE_FRAME_ALLOCATOR.lock().try_alloc_block(frame_count)
+}
+
 /// Free a contiguous range of page `frames`.
 /// Unsafe because invalid parameters may break the list allocator.
 pub unsafe fn free(frames: PhysFrameRange) {
@@ -76,6 +82,11 @@ pub fn phys_limit() -> PhysFrame {
     return PHYS_LIMIT.get().unwrap().lock().get();
 }
 
+/// Get the number of currently free page frames.
+pub fn free_frames() -> usize {
+    PAGE_FRAME_ALLOCATOR.lock().free_frames()
+}
+
 /// Get a dump of the current free list.
 pub fn dump() -> String {
     format!("{:?}", PAGE_FRAME_ALLOCATOR.lock())
@@ -130,6 +141,19 @@ impl PageFrameListAllocator {
         Self { head: PageFrameNode::new(0) }
     }
 
+    /// Count the frames of all blocks in the free list.
+    fn free_frames(&self) -> usize {
+        let mut available: usize = 0;
+
+        let mut current = &self.head;
+        while let Some(block) = &current.next {
+            available += block.frame_count;
+            current = current.next.as_ref().unwrap();
+        }
+
+        available
+    }
+
     /// Insert a new range of `frames`, sorted ascending by its memory address.
     unsafe fn insert(&mut self, frames: PhysFrameRange) {
         let mut new_block = PageFrameNode::new((frames.end - frames.start) as usize);
@@ -191,19 +215,22 @@ impl PageFrameListAllocator {
 
     /// Allocate a block with `frame_count` contiguous page frames.
     fn alloc_block(&mut self, frame_count: usize) -> PhysFrameRange {
-        match self.find_free_block(frame_count) {
-            Some(block) => {
-                let remaining = PhysFrameRange { start: block.start() + frame_count as u64, end: block.end() };
-                if (remaining.end - remaining.start) > 0 {
-                    unsafe { self.insert(remaining); }
-                }
-                
-                return PhysFrameRange { start: block.start(), end: remaining.start };
-            },
+        match self.try_alloc_block(frame_count) {
+            Some(block) => block,
             None => panic!("PageFrameAllocator: Out of memory!")
         }
     }
 
+    fn try_alloc_block(&mut self, frame_count: usize) -> Option<PhysFrameRange> {
+        let block = self.find_free_block(frame_count)?;
+        let remaining = PhysFrameRange { start: block.start() + frame_count as u64, end: block.end() };
+        if (remaining.end - remaining.start) > 0 {
+            unsafe { self.insert(remaining); }
+        }
+
+        Some(PhysFrameRange { start: block.start(), end: remaining.start })
+    }
+
     /// Free a region of `frames` consisting of at least one page frame.
     /// The block is inserted ascending by address and fused with its neighbours, if possible.
     unsafe fn free_block(&mut self, frames: PhysFrameRange) {
@@ -33,6 +33,16 @@ impl KernelAllocator {
     pub fn is_locked(&self) -> bool {
         self.heap.is_locked()
     }
+
+    /// Return the size of the heap in bytes
+    pub fn size(&self) -> usize {
+        self.heap.lock().size()
+    }
+
+    /// Return the number of bytes currently allocated on the heap
+    pub fn used(&self) -> usize {
+        self.heap.lock().used()
+    }
 }
 
 unsafe impl Allocator for KernelAllocator {
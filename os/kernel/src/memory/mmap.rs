@@ -0,0 +1,312 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: mmap                                                            ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Memory-mapped files. A file is mapped into a VMA of type 'Mmap' of the  ║
+   ║ user address space. No page is populated at mapping time, instead each  ║
+   ║ page is read from the file on its first access (page fault).            ║
+   ║   - private mappings get their own page frames, changes are never       ║
+   ║     written back to the file                                            ║
+   ║   - shared mappings of the same file object share their page frames     ║
+   ║     (also across processes). Dirty pages are written back to the file   ║
+   ║     by 'sync' and when the mapping is removed (also on process exit)    ║
+   ║   - shared mappings of shared memory objects (see 'shm') use the frames ║
+   ║     of the object directly and get a VMA of type 'SharedMemory'         ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::slice;
+use spin::Mutex;
+use x86_64::VirtAddr;
+use x86_64::instructions::tlb;
+use x86_64::structures::paging::frame::PhysFrameRange;
+use x86_64::structures::paging::page::PageRange;
+use x86_64::structures::paging::{Page, PageTableFlags, PhysFrame};
+
+use crate::consts::{USER_SPACE_MMAP_END, USER_SPACE_MMAP_START};
+use crate::memory::vmm::{VirtualAddressSpace, VirtualMemoryArea, VmaType};
+use crate::memory::shm::{SharedFrame, SharedMemory};
+use crate::memory::{frames, MemorySpace, PAGE_SIZE};
+use crate::naming::traits::FileObject;
+use naming::shared_types::{FileType, MapFlags, OpenOptions};
+use syscall::return_vals::Errno;
+
+/// Page frames of shared mappings: (file object, page number in file) -> (frame, number of mapped pages using it)
+static SHARED_FRAMES: Mutex<BTreeMap<(usize, usize), (PhysFrame, usize)>> = Mutex::new(BTreeMap::new());
+
+/// A file mapped into the pages `range` of an address space, starting at `offset` (page aligned) in the file
+pub struct FileMapping {
+    range: PageRange,
+    file: Arc<dyn FileObject>,
+    offset: usize,
+    flags: MapFlags,
+    /// Frames of a shared memory object used by the populated pages (keeping them alive while mapped)
+    memory_frames: BTreeMap<Page, Arc<SharedFrame>>,
+}
+
+impl FileMapping {
+    /// Key of the page `page` of this mapping in `SHARED_FRAMES`
+    fn shared_key(&self, page: Page) -> (usize, usize) {
+        let file_id = Arc::as_ptr(&self.file) as *const () as usize;
+        (file_id, self.offset / PAGE_SIZE + (page - self.range.start) as usize)
+    }
+
+    /// Return the memory of the shared memory object, if this is a shared mapping of one
+    fn shared_memory(&self) -> Option<&SharedMemory> {
+        if self.flags.contains(MapFlags::SHARED) { self.file.shared_memory() } else { None }
+    }
+
+    fn vma(&self) -> VirtualMemoryArea {
+        vma(self.range, self.shared_memory().is_some())
+    }
+
+    /// Offset in the file of `page` of this mapping
+    fn file_offset(&self, page: Page) -> usize {
+        self.offset + (page - self.range.start) as usize * PAGE_SIZE
+    }
+
+    fn page_flags(&self) -> PageTableFlags {
+        let mut flags = PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE;
+        if self.flags.contains(MapFlags::WRITE) {
+            flags |= PageTableFlags::WRITABLE;
+        }
+        flags
+    }
+
+    /// Allocate a frame for `page` and fill it with the file content (zeros beyond the end of the file). \
+    /// Fails with `ENOMEM` if the memory is exhausted, which fails the page fault.
+    fn load_frame(&self, page: Page) -> Result<PhysFrame, Errno> {
+        let frame = frames::try_alloc(1).ok_or(Errno::ENOMEM)?.start;
+        let data = frame_data(frame);
+        data.fill(0);
+
+        let mut read = 0;
+        while read < PAGE_SIZE {
+            match self.file.read(&mut data[read..], self.file_offset(page) + read, OpenOptions::READONLY) {
+                Ok(0) => break,
+                Ok(count) => read += count,
+                Err(error) => {
+                    unsafe { frames::free(PhysFrameRange { start: frame, end: frame + 1 }); }
+                    return Err(error);
+                }
+            }
+        }
+        Ok(frame)
+    }
+
+    /// Write `frame` holding `page` back to the file (without extending the file)
+    fn write_back(&self, page: Page, frame: PhysFrame) -> Result<(), Errno> {
+        let offset = self.file_offset(page);
+        let size = self.file.stat()?.size;
+        if offset >= size {
+            return Ok(());
+        }
+
+        let len = PAGE_SIZE.min(size - offset);
+        self.file.write(&frame_data(frame)[..len], offset, OpenOptions::READWRITE).map(|_| ())
+    }
+
+    /// Write all dirty pages of a shared mapping in `space` back to the file and mark them as clean
+    fn sync(&self, space: &VirtualAddressSpace) -> Result<(), Errno> {
+        if !self.flags.contains(MapFlags::SHARED) || self.shared_memory().is_some() {
+            return Ok(());
+        }
+
+        let page_tables = space.page_tables();
+        for page in self.range {
+            let dirty_entry = page_tables.entry(page).filter(|(_, flags)| flags.contains(PageTableFlags::DIRTY));
+            if let Some((frame, flags)) = dirty_entry {
+                self.write_back(page, frame)?;
+                page_tables.set_flags(PageRange { start: page, end: page + 1 }, flags - PageTableFlags::DIRTY);
+                tlb::flush(page.start_address());
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove all pages of this mapping from the page tables of `space` (dirty shared pages are written back before).
+    /// Frames of private pages are freed, frames of shared pages are freed when their last user is gone.
+    /// Frames of shared memory objects are released, when the mapping is dropped.
+    fn release(&self, space: &VirtualAddressSpace) {
+        let page_tables = space.page_tables();
+        if self.shared_memory().is_some() {
+            page_tables.unmap(self.range, false);
+        } else if self.flags.contains(MapFlags::SHARED) {
+            let mut shared_frames = SHARED_FRAMES.lock();
+            for page in self.range {
+                if let Some((frame, flags)) = page_tables.entry(page) {
+                    if flags.contains(PageTableFlags::DIRTY) {
+                        let _ = self.write_back(page, frame);
+                    }
+
+                    let key = self.shared_key(page);
+                    if let Some((_, users)) = shared_frames.get_mut(&key) {
+                        *users -= 1;
+                        if *users == 0 {
+                            shared_frames.remove(&key);
+                            unsafe { frames::free(PhysFrameRange { start: frame, end: frame + 1 }); }
+                        }
+                    }
+                }
+            }
+            page_tables.unmap(self.range, false);
+        } else {
+            page_tables.unmap(self.range, true);
+        }
+    }
+}
+
+/// Return the content of `frame` (all page frames are identity mapped in kernel space)
+pub(super) fn frame_data(frame: PhysFrame) -> &'static mut [u8] {
+    unsafe { slice::from_raw_parts_mut(frame.start_address().as_u64() as *mut u8, PAGE_SIZE) }
+}
+
+/// VMA for a mapping of the pages `range` (of a shared memory object, if `shared_memory` is true)
+fn vma(range: PageRange, shared_memory: bool) -> VirtualMemoryArea {
+    if shared_memory {
+        VirtualMemoryArea::new_with_tag(range, VmaType::SharedMemory, "shm")
+    } else {
+        VirtualMemoryArea::new_with_tag(range, VmaType::Mmap, "mmap")
+    }
+}
+
+/// Map `length` bytes of the regular file `file` (opened with `options`) starting at `offset` (page aligned) into `space`. \
+/// Returns `Ok(start address of the mapping)` or `Err(errno)`
+pub fn map_file(space: &VirtualAddressSpace, file: Arc<dyn FileObject>, options: OpenOptions, offset: usize, length: usize, flags: MapFlags) -> Result<VirtAddr, Errno> {
+    if length == 0 || offset % PAGE_SIZE != 0 || offset.checked_add(length).is_none() || flags.contains(MapFlags::SHARED) == flags.contains(MapFlags::PRIVATE) {
+        return Err(Errno::EINVAL);
+    }
+    if file.stat()?.mode.file_type() != FileType::Regular {
+        return Err(Errno::ENODEV);
+    }
+
+    // the file must be readable, changes of a shared mapping require write access as well
+    if !options.is_readable() || (flags.contains(MapFlags::SHARED | MapFlags::WRITE) && !options.is_writable()) {
+        return Err(Errno::EACCES);
+    }
+
+    let page_count = length.div_ceil(PAGE_SIZE) as u64;
+    let mut mappings = space.file_mappings().lock();
+
+    // first fit in the mmap area of the address space (used by file and shared memory mappings)
+    let mut start = Page::containing_address(VirtAddr::new(USER_SPACE_MMAP_START as u64));
+    let end = Page::containing_address(VirtAddr::new(USER_SPACE_MMAP_END as u64));
+    let mut vmas: Vec<VirtualMemoryArea> = space.find_vmas(VmaType::Mmap);
+    vmas.extend(space.find_vmas(VmaType::SharedMemory));
+    vmas.sort_by_key(|vma| vma.range().start);
+    for vma in vmas {
+        if end - start < page_count || vma.range().start >= start + page_count {
+            break;
+        }
+        start = start.max(vma.range().end);
+    }
+
+    // the mapping must not leave the mmap area
+    if start > end || end - start < page_count {
+        return Err(Errno::EINVAL);
+    }
+
+    let mapping = FileMapping { range: PageRange { start, end: start + page_count }, file, offset, flags, memory_frames: BTreeMap::new() };
+    space.add_vma(mapping.vma());
+    mappings.push(mapping);
+    Ok(start.start_address())
+}
+
+/// Remove the mapping starting at `addr` with `length` bytes from `space`. \
+/// Returns `Ok(0)` or `Err(Errno::EINVAL)`, if there is no such mapping (only complete mappings can be removed).
+pub fn unmap_file(space: &VirtualAddressSpace, addr: VirtAddr, length: usize) -> Result<usize, Errno> {
+    let mut mappings = space.file_mappings().lock();
+    let index = mappings.iter()
+        .position(|mapping| mapping.range.start.start_address() == addr && (mapping.range.end - mapping.range.start) as usize == length.div_ceil(PAGE_SIZE))
+        .ok_or(Errno::EINVAL)?;
+
+    let mapping = mappings.swap_remove(index);
+    mapping.release(space);
+    tlb::flush_all();
+    space.remove_vma(mapping.vma());
+    Ok(0)
+}
+
+/// Write the dirty pages of all shared mappings overlapping `length` bytes at `addr` in `space` back to their files. \
+/// Returns `Ok(0)` or `Err(errno)`
+pub fn sync_file(space: &VirtualAddressSpace, addr: VirtAddr, length: usize) -> Result<usize, Errno> {
+    // all mappings lie below the end of the mmap area
+    let last = addr.as_u64().checked_add(length.max(1) as u64 - 1).ok_or(Errno::EINVAL)?;
+    if last >= USER_SPACE_MMAP_END as u64 {
+        return Err(Errno::EINVAL);
+    }
+    let last = VirtAddr::new(last);
+    let pages = PageRange {
+        start: Page::containing_address(addr),
+        end: Page::containing_address(last) + 1,
+    };
+
+    let mappings = space.file_mappings().lock();
+    let mut found = false;
+    for mapping in mappings.iter().filter(|mapping| mapping.range.start < pages.end && pages.start < mapping.range.end) {
+        mapping.sync(space)?;
+        found = true;
+    }
+
+    if found { Ok(0) } else { Err(Errno::EINVAL) }
+}
+
+/// Populate the page containing `addr` in `space`, if it belongs to a file mapping (called by the page fault handler). \
+/// Returns false, if `addr` is not mapped or the file could not be read.
+pub fn handle_page_fault(space: &VirtualAddressSpace, addr: VirtAddr) -> bool {
+    let page = Page::containing_address(addr);
+    let mut mappings = space.file_mappings().lock();
+    let mapping = match mappings.iter_mut().find(|mapping| mapping.range.start <= page && page < mapping.range.end) {
+        Some(mapping) => mapping,
+        None => return false,
+    };
+
+    let frame = if mapping.shared_memory().is_some() {
+        // pages behind the end of the shared memory object cannot be accessed
+        let index = mapping.file_offset(page) / PAGE_SIZE;
+        match mapping.shared_memory().and_then(|memory| memory.frame(index)) {
+            Some(shared_frame) => {
+                let frame = shared_frame.frame();
+                mapping.memory_frames.insert(page, shared_frame);
+                frame
+            }
+            None => return false,
+        }
+    } else if mapping.flags.contains(MapFlags::SHARED) {
+        let mut shared_frames = SHARED_FRAMES.lock();
+        match shared_frames.get_mut(&mapping.shared_key(page)) {
+            Some((frame, users)) => {
+                *users += 1;
+                *frame
+            }
+            None => match mapping.load_frame(page) {
+                Ok(frame) => {
+                    shared_frames.insert(mapping.shared_key(page), (frame, 1));
+                    frame
+                }
+                Err(_) => return false,
+            },
+        }
+    } else {
+        match mapping.load_frame(page) {
+            Ok(frame) => frame,
+            Err(_) => return false,
+        }
+    };
+
+    space.page_tables().map_physical(
+        PhysFrameRange { start: frame, end: frame + 1 },
+        PageRange { start: page, end: page + 1 },
+        MemorySpace::User,
+        mapping.page_flags(),
+    );
+    true
+}
+
+/// Remove all file mappings of `space` (called when the address space is dropped)
+pub(super) fn release_all(space: &VirtualAddressSpace) {
+    for mapping in space.file_mappings().lock().drain(..) {
+        mapping.release(space);
+    }
+}
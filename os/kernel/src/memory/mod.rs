@@ -1,6 +1,8 @@
 pub mod vmm;
 pub mod pages;
 pub mod frames;
+pub mod mmap;
+pub mod shm;
 
 pub mod nvmem;
 
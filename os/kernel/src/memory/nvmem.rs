@@ -5,12 +5,14 @@ use acpi::AcpiTable;
 use acpi::sdt::{SdtHeader, Signature};
 use alloc::vec::Vec;
 use bitflags::bitflags;
+use core::arch::asm;
 use core::cmp::PartialEq;
 use core::ptr;
 use log::info;
+use spin::Once;
 use x86_64::structures::paging::frame::PhysFrameRange;
 use x86_64::structures::paging::page::PageRange;
-use x86_64::structures::paging::{Page, PageTableFlags, PhysFrame};
+use x86_64::structures::paging::{Page, PageTableFlags, PhysFrame, Size4KiB};
 use x86_64::{PhysAddr, VirtAddr};
 
 #[allow(dead_code)]
@@ -129,8 +131,19 @@ pub struct FlushHintAddressStructure {
     header: NfitStructureHeader,
     device_handle: u32,
     hint_count: u16,
+    reserved: [u8; 6],
 }
 
+/// Size of a cache line (granularity of `flush`)
+const CACHE_LINE_SIZE: usize = 64;
+
+/// Non-volatile memory ranges found in the NFIT, named "pmem0", "pmem1", ... (identity mapped by `init`)
+static REGIONS: Once<Vec<PhysFrameRange>> = Once::new();
+
+/// One flush hint address per NVDIMM. Writing to it drains the write buffers of the memory controller
+/// for this NVDIMM (not needed on platforms, which flush these buffers on power loss).
+static FLUSH_HINTS: Once<Vec<u64>> = Once::new();
+
 unsafe impl AcpiTable for Nfit {
     const SIGNATURE: Signature = Signature::NFIT;
 
@@ -172,6 +185,19 @@ impl Nfit {
 
         return ranges;
     }
+
+    pub fn get_flush_hint_structures(&self) -> Vec<&FlushHintAddressStructure> {
+        let mut hints = Vec::<&FlushHintAddressStructure>::new();
+
+        self.get_structures().iter().for_each(|structure| {
+            let structure_type = unsafe { ptr::from_ref(structure).read_unaligned().typ };
+            if structure_type == NfitStructureType::FlushHintAddress {
+                hints.push(structure.as_structure::<FlushHintAddressStructure>());
+            }
+        });
+
+        return hints;
+    }
 }
 
 impl NfitStructureHeader {
@@ -215,8 +241,10 @@ impl FlushHintAddressStructure {
 pub fn init() {
     if let Ok(nfit) = acpi_tables().lock().find_table::<Nfit>() {
         info!("Found NFIT table");
+        let kernel_process = process_manager().read().kernel_process().expect("Failed to get kernel process");
 
         // Search NFIT table for non-volatile memory ranges
+        let mut regions = Vec::new();
         for spa in nfit.get_phys_addr_ranges() {
             // Copy values to avoid unaligned access of packed struct fields
             let address = spa.base;
@@ -229,21 +257,67 @@ pub fn init() {
 
             // Map non-volatile memory range to kernel address space
             let start_page = Page::from_start_address(VirtAddr::new(address)).unwrap();
-            process_manager()
-                .read()
-                .kernel_process()
-                .expect("Failed to get kernel process")
-                .virtual_address_space
-                .map(
-                    PageRange {
-                        start: start_page,
-                        end: start_page + (length / PAGE_SIZE as u64),
-                    },
-                    MemorySpace::Kernel,
-                    PageTableFlags::PRESENT | PageTableFlags::WRITABLE,
-                    VmaType::DeviceMemory,
-                    "nfit",
-                );
+            kernel_process.virtual_address_space.map(
+                PageRange {
+                    start: start_page,
+                    end: start_page + (length / PAGE_SIZE as u64),
+                },
+                MemorySpace::Kernel,
+                PageTableFlags::PRESENT | PageTableFlags::WRITABLE,
+                VmaType::DeviceMemory,
+                "nfit",
+            );
+            regions.push(spa.as_phys_frame_range());
+        }
+        REGIONS.call_once(|| regions);
+
+        // Map the first flush hint address of each NVDIMM (uncached, since writing to it triggers the flush)
+        let mut hints = Vec::new();
+        for structure in nfit.get_flush_hint_structures() {
+            if let Some(&hint) = structure.get_flush_hint_addresses().first() {
+                let page = Page::<Size4KiB>::containing_address(VirtAddr::new(hint));
+                if !hints.iter().any(|&other| Page::<Size4KiB>::containing_address(VirtAddr::new(other)) == page) {
+                    kernel_process.virtual_address_space.map(
+                        PageRange { start: page, end: page + 1 },
+                        MemorySpace::Kernel,
+                        PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::NO_CACHE,
+                        VmaType::DeviceMemory,
+                        "nfit flush hint",
+                    );
+                }
+                hints.push(hint);
+            }
+        }
+        info!("Found [{}] flush hint addresses", hints.len());
+        FLUSH_HINTS.call_once(|| hints);
+    }
+}
+
+/// Return the non-volatile memory range with the given `name` ("pmem0" for the first range found in the NFIT)
+pub fn region(name: &str) -> Option<PhysFrameRange> {
+    let index: usize = name.strip_prefix("pmem")?.parse().ok()?;
+    REGIONS.get()?.get(index).copied()
+}
+
+/// Write the cache lines covering `length` bytes at `address` back to memory. \
+/// The write back is only guaranteed to be complete after calling `drain`.
+pub fn flush(address: usize, length: usize) {
+    let start = address & !(CACHE_LINE_SIZE - 1);
+    for line in (start..address + length).step_by(CACHE_LINE_SIZE) {
+        unsafe { asm!("clflush [{}]", in(reg) line, options(nostack, preserves_flags)) };
+    }
+}
+
+/// Wait until all flushed cache lines have reached the persistence domain (using the flush hint addresses, if any). \
+/// Also acts as store fence, so stores after `drain` become persistent after the flushed ones.
+pub fn drain() {
+    unsafe { asm!("sfence", options(nostack, preserves_flags)) };
+
+    let hints = FLUSH_HINTS.get().map(Vec::as_slice).unwrap_or(&[]);
+    if !hints.is_empty() {
+        for &hint in hints {
+            unsafe { (hint as *mut u64).write_volatile(0) };
         }
+        unsafe { asm!("sfence", options(nostack, preserves_flags)) };
     }
 }
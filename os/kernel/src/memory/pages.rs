@@ -7,6 +7,7 @@
    ║                   in the given memory space                             ║
    ║   - set_flags     set flags of page table entries for a range of pages  ║
    ║   - translate     translate a virtual address to a physical address     ║
+   ║   - entry         get frame and flags of the page table entry of a page ║
    ║   - unmap         unmap a range of pages                                ║
    ╟─────────────────────────────────────────────────────────────────────────╢
    ║ Author: Fabian Ruhland, Univ. Duesseldorf, 20.2.2025                    ║
@@ -133,6 +134,15 @@ impl Paging {
         Paging::translate_in_table(root_table, addr, depth)
     }
 
+    /// Return the frame and the flags of the page table entry for `page` or None, if the page is not mapped
+    pub(super) fn entry(&self, page: Page) -> Option<(PhysFrame, PageTableFlags)> {
+        let depth = self.depth;
+        let root_table_guard = self.root_table.read();
+        let root_table = unsafe { root_table_guard.as_mut().unwrap() };
+
+        Paging::entry_in_table(root_table, page.start_address(), depth)
+    }
+
     /// Unmap a range of `pages` from the address space. 
     /// `free_physical` indicates if the physical frames should be freed.
     pub(super) fn unmap(&self, pages: PageRange, free_physical: bool) {
@@ -351,6 +361,21 @@ impl Paging {
         }
     }
 
+    /// Internal recursive function returning frame and flags of the page table entry for the page containing `addr` or None.
+    fn entry_in_table(table: &mut PageTable, addr: VirtAddr, level: usize) -> Option<(PhysFrame, PageTableFlags)> {
+        let entry = &table[page_table_index(addr, level)];
+        if entry.is_unused() {
+            return None;
+        }
+
+        if level > 1 { // Calculate next level page table until level == 1
+            let next_level_table = unsafe { (entry.addr().as_u64() as *mut PageTable).as_mut().unwrap() };
+            Paging::entry_in_table(next_level_table, addr, level - 1)
+        } else { // Reached level 1 page table
+            Some((PhysFrame::containing_address(entry.addr()), entry.flags()))
+        }
+    }
+
     /// Create 1:1 mapping entries in the given page `table` for `pages` with the given `flags` for the kernel space.
     fn identity_map_kernel(table: &mut PageTable, pages: PageRange, flags: PageTableFlags) -> usize {
         let start_index = usize::from(page_table_index(pages.start.start_address(), 1));
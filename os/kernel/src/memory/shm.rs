@@ -0,0 +1,114 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: shm                                                             ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Memory of shared memory objects (named in the naming service, see       ║
+   ║ 'naming::shmfs'). The content lives in reference-counted page frames,   ║
+   ║ which are mapped directly by all shared mappings of the object (VMAs    ║
+   ║ of type 'SharedMemory', see 'mmap'). A frame is freed, when the object  ║
+   ║ and all mappings using it are gone, e.g. the name has been removed and  ║
+   ║ the last process using the object has exited.                           ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use spin::Mutex;
+use syscall::return_vals::Errno;
+use x86_64::structures::paging::PhysFrame;
+use x86_64::structures::paging::frame::PhysFrameRange;
+
+use crate::memory::mmap::frame_data;
+use crate::memory::{frames, PAGE_SIZE};
+
+/// Max. size of a shared memory object
+pub const MAX_SIZE: usize = 0x10000000; // 256 MiB
+
+/// A page frame of a shared memory object, freed when the last reference is dropped
+pub struct SharedFrame(PhysFrame);
+
+impl SharedFrame {
+    /// Allocate a page frame filled with zeros (`None` if the memory is exhausted)
+    fn alloc() -> Option<Arc<SharedFrame>> {
+        let frame = frames::try_alloc(1)?.start;
+        frame_data(frame).fill(0);
+        Some(Arc::new(SharedFrame(frame)))
+    }
+
+    pub fn frame(&self) -> PhysFrame {
+        self.0
+    }
+}
+
+impl Drop for SharedFrame {
+    fn drop(&mut self) {
+        unsafe { frames::free(PhysFrameRange { start: self.0, end: self.0 + 1 }); }
+    }
+}
+
+/// Page frames holding the content of a shared memory object
+pub struct SharedMemory {
+    frames: Mutex<Vec<Arc<SharedFrame>>>,
+}
+
+impl SharedMemory {
+    pub fn new() -> SharedMemory {
+        SharedMemory { frames: Mutex::new(Vec::new()) }
+    }
+
+    /// Return the frame holding page `index` (`None` behind the last page)
+    pub fn frame(&self, index: usize) -> Option<Arc<SharedFrame>> {
+        self.frames.lock().get(index).cloned()
+    }
+
+    /// Set the size to `size` bytes (rounded up to pages). New pages are filled with zeros, the bytes behind `size`
+    /// in the last page are cleared. Removed frames stay valid for mappings still using them. \
+    /// Returns `Err(Errno::ENOSPC)` if `size` exceeds `MAX_SIZE` or the memory is exhausted (the content is unchanged then).
+    pub fn resize(&self, size: usize) -> Result<(), Errno> {
+        if size > MAX_SIZE {
+            return Err(Errno::ENOSPC);
+        }
+
+        let pages = size.div_ceil(PAGE_SIZE);
+        let mut frames = self.frames.lock();
+
+        // allocate all new frames first (dropping them again on failure)
+        let mut new_frames = Vec::new();
+        while frames.len() + new_frames.len() < pages {
+            new_frames.push(SharedFrame::alloc().ok_or(Errno::ENOSPC)?);
+        }
+
+        frames.truncate(pages);
+        if let Some(last) = frames.last().filter(|_| frames.len() == pages && size % PAGE_SIZE != 0) {
+            frame_data(last.frame())[size % PAGE_SIZE..].fill(0);
+        }
+        frames.extend(new_frames);
+        Ok(())
+    }
+
+    /// Copy the content at `offset` into `buf` (the caller checks the size of the object)
+    pub fn read(&self, buf: &mut [u8], offset: usize) {
+        let frames = self.frames.lock();
+        let mut done = 0;
+        while done < buf.len() {
+            let pos = offset + done;
+            let page_offset = pos % PAGE_SIZE;
+            let len = (PAGE_SIZE - page_offset).min(buf.len() - done);
+            let data = frame_data(frames[pos / PAGE_SIZE].frame());
+            buf[done..done + len].copy_from_slice(&data[page_offset..page_offset + len]);
+            done += len;
+        }
+    }
+
+    /// Copy `buf` to `offset` (the caller resizes the object before, if necessary)
+    pub fn write(&self, buf: &[u8], offset: usize) {
+        let frames = self.frames.lock();
+        let mut done = 0;
+        while done < buf.len() {
+            let pos = offset + done;
+            let page_offset = pos % PAGE_SIZE;
+            let len = (PAGE_SIZE - page_offset).min(buf.len() - done);
+            let data = frame_data(frames[pos / PAGE_SIZE].frame());
+            data[page_offset..page_offset + len].copy_from_slice(&buf[done..done + len]);
+            done += len;
+        }
+    }
+}
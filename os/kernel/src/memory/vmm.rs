@@ -17,7 +17,7 @@ use alloc::vec::Vec;
 use core::cmp::Ordering;
 use core::fmt;
 use log::info;
-use spin::RwLock;
+use spin::{Mutex, RwLock};
 
 use x86_64::PhysAddr;
 use x86_64::VirtAddr;
@@ -26,6 +26,7 @@ use x86_64::structures::paging::page::PageRange;
 use x86_64::structures::paging::{Page, PageTableFlags};
 
 use crate::memory::frames::phys_limit;
+use crate::memory::mmap::{self, FileMapping};
 use crate::memory::pages::Paging;
 use crate::memory::{MemorySpace, PAGE_SIZE};
 use crate::process_manager;
@@ -54,6 +55,7 @@ pub fn create_kernel_address_space() -> Arc<Paging> {
 
 pub struct VirtualAddressSpace {
     virtual_memory_areas: RwLock<Vec<VirtualMemoryArea>>,
+    file_mappings: Mutex<Vec<FileMapping>>, // files mapped into VMAs of type 'Mmap' or 'SharedMemory'
     page_tables: Arc<Paging>,
 }
 
@@ -62,6 +64,7 @@ impl VirtualAddressSpace {
         Self {
             page_tables,
             virtual_memory_areas: RwLock::new(Vec::new()),
+            file_mappings: Mutex::new(Vec::new()),
         }
     }
 
@@ -93,7 +96,7 @@ impl VirtualAddressSpace {
         found.sort_by(|first, second| {
             return if first.start().as_u64() < second.start().as_u64() {
                 Ordering::Less
-            } else if first.start().as_u64() < second.start().as_u64() {
+            } else if first.start().as_u64() > second.start().as_u64() {
                 Ordering::Greater
             } else {
                 Ordering::Equal
@@ -103,6 +106,14 @@ impl VirtualAddressSpace {
         found
     }
 
+    pub fn remove_vma(&self, vma: VirtualMemoryArea) {
+        let mut areas = self.virtual_memory_areas.write();
+        match areas.iter().position(|area| *area == vma) {
+            Some(index) => { areas.swap_remove(index); }
+            None => panic!("Trying to remove a non-existent VMA!"),
+        }
+    }
+
     pub fn update_vma(&self, vma: VirtualMemoryArea, update: impl Fn(&mut VirtualMemoryArea)) {
         let mut areas = self.virtual_memory_areas.write();
         match areas.iter_mut().find(|area| **area == vma) {
@@ -154,10 +165,19 @@ impl VirtualAddressSpace {
         self.page_tables.set_flags(pages, flags);
     }
 
+    pub(super) fn file_mappings(&self) -> &Mutex<Vec<FileMapping>> {
+        &self.file_mappings
+    }
+
     pub fn page_table_address(&self) -> PhysAddr {
         self.page_tables.page_table_address()
     }
 
+    /// Return a copy of all VMAs of the address space
+    pub fn vmas(&self) -> Vec<VirtualMemoryArea> {
+        self.virtual_memory_areas.read().clone()
+    }
+
     pub fn dump(&self, pid: usize) {
         info!("VMAs of process [{}]", pid);
         let areas = self.virtual_memory_areas.read();
@@ -169,7 +189,10 @@ impl VirtualAddressSpace {
 
 impl Drop for VirtualAddressSpace {
     fn drop(&mut self) {
-        for vma in self.virtual_memory_areas.read().iter() {
+        // frames of file mappings and shared memory objects may be shared and dirty pages must be written back
+        mmap::release_all(self);
+
+        for vma in self.virtual_memory_areas.read().iter().filter(|vma| vma.typ() != VmaType::Mmap && vma.typ() != VmaType::SharedMemory) {
             self.page_tables.unmap(vma.range(), true);
         }
     }
@@ -183,6 +206,8 @@ pub enum VmaType {
     DeviceMemory,
     UserStack,
     KernelStack,
+    Mmap,
+    SharedMemory,
 }
 
 ///
@@ -268,6 +293,10 @@ impl VirtualMemoryArea {
         self.typ
     }
 
+    pub fn tag(&self) -> &str {
+        core::str::from_utf8(&self.tag).unwrap_or("<invalid>")
+    }
+
     pub fn overlaps_with(&self, other: &VirtualMemoryArea) -> bool {
         self.range.end > other.range.start && self.range.start < other.range.end
     }
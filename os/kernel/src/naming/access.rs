@@ -0,0 +1,64 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: access                                                          ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Permission checks of the naming service. The permission bits of an      ║
+   ║ object (see 'Mode') are checked against the user and group id of the    ║
+   ║ current process: the owner bits apply if the process owns the object,   ║
+   ║ the group bits apply if it is in the group of the object, otherwise the ║
+   ║ bits for others. Root (uid 0) passes all checks, except executing a     ║
+   ║ file without any execute bit.                                           ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use core::result::Result;
+
+use super::stat::{Stat, ROOT_UID};
+use crate::process_manager;
+use naming::shared_types::FileType;
+use syscall::return_vals::Errno;
+
+/// Access rights (same bit positions as in each group of the permission bits)
+pub(super) const READ: u32 = 0o4;
+pub(super) const WRITE: u32 = 0o2;
+pub(super) const EXECUTE: u32 = 0o1; // execute a file or search a directory
+
+/// Return the user and group id of the current process
+pub(super) fn credentials() -> (u32, u32) {
+    let process = process_manager().read().current_process();
+    (process.uid(), process.gid())
+}
+
+/// Check if the current process has all rights in `access` for the object with the meta data `stat`. \
+/// Returns `Ok(())` or `Err(Errno::EACCES)`
+pub(super) fn check(stat: &Stat, access: u32) -> Result<(), Errno> {
+    let (uid, gid) = credentials();
+    let permissions = stat.mode.permissions();
+
+    let allowed = if uid == ROOT_UID {
+        access & EXECUTE == 0 || stat.mode.file_type() == FileType::Directory || permissions & 0o111 != 0
+    } else {
+        let rights = if uid == stat.uid {
+            permissions >> 6
+        } else if gid == stat.gid {
+            permissions >> 3
+        } else {
+            permissions
+        };
+        rights & access == access
+    };
+
+    if allowed { Ok(()) } else { Err(Errno::EACCES) }
+}
+
+/// Check if the current process runs as root (required e.g. for mounting file systems). \
+/// Returns `Ok(())` or `Err(Errno::EPERM)`
+pub(super) fn check_root() -> Result<(), Errno> {
+    let (uid, _) = credentials();
+    if uid == ROOT_UID { Ok(()) } else { Err(Errno::EPERM) }
+}
+
+/// Check if the current process may change the meta data (e.g. permissions) of the object with `stat` (owner or root). \
+/// Returns `Ok(())` or `Err(Errno::EACCES)`
+pub(super) fn check_owner(stat: &Stat) -> Result<(), Errno> {
+    let (uid, _) = credentials();
+    if uid == ROOT_UID || uid == stat.uid { Ok(()) } else { Err(Errno::EACCES) }
+}
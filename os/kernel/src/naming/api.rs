@@ -7,44 +7,62 @@
    ║   - read   read bytes from an open object                               ║
    ║   - write  write bytes into an open object                              ║
    ║   - seek   set file pointer (for files)                                 ║
+   ║   - truncate set the size of a file                                     ║
+   ║   - file   get the file object of a handle (e.g. for mmap)              ║
+   ║   - pipe   create an anonymous pipe (read and write handle)             ║
+   ║   - mkfifo create a named pipe                                          ║
+   ║   - watch  get notified about changes of a directory or file            ║
    ║   - mkdi : create a directory                                           ║
    ║   - touch  create a file                                                ║
+   ║   - unlink remove a file                                                ║
+   ║   - rmdir  remove an empty directory                                    ║
+   ║   - rename move/rename a file or directory                              ║
+   ║   - stat   get meta data of a named object (by path or handle: fstat)   ║
+   ║   - symlink create a symbolic link, readlink: get its target            ║
+   ║   - chmod  set permissions, chown: set owner and group                  ║
+   ║   - mount  mount a file system at a directory                           ║
+   ║   - umount unmount a file system                                        ║
+   ║   - load_file read a complete file into memory (e.g. for exec)          ║
    ╟─────────────────────────────────────────────────────────────────────────╢
    ║ Author: Michael Schoettner, Univ. Duesseldorf, 23.2.2025                ║
    ╚═════════════════════════════════════════════════════════════════════════╝
 */
 
-use alloc::string::{String, ToString};
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec;
 use alloc::vec::Vec;
-use log::info;
-use spin::{Mutex, Once};
+use log::{info, warn};
 
-use super::traits::FileSystem;
+use super::traits::{DirectoryObject, FileObject, FileSystem, NamedObject};
+use super::access;
 use super::lookup;
+use super::mount;
 use super::open_objects;
-use super::stat::Mode;
-use super::tmpfs;
+use super::stat::{self, Mode, DIR_PERMISSIONS, FILE_PERMISSIONS, MODE_DIR, MODE_FILE, ROOT_UID};
+use super::{devfs, ext2, fat32, pipe, pmemfs, procfs, shmfs, tarfs, tmpfs, watch};
+use crate::memory::nvmem;
+use crate::{initrd, process_manager, scheduler, storage, timer};
 
-use naming::shared_types::{OpenOptions, RawDirent, SeekOrigin};
+use naming::shared_types::{FileStat, OpenOptions, PollFlags, PollHandle, RawDirent, SeekOrigin, WatchMask, POLL_INFINITE, UTIME_NOW, UTIME_OMIT};
 use syscall::return_vals::Errno;
 
-// root of naming service
-pub(super) static ROOT: Once<Arc<dyn FileSystem>> = Once::new();
-
-// current working directory
-static CWD: Mutex<String> = Mutex::new(String::new());
+/// Initilize the naming service (must be called once before using it). \
+/// The ext2 file system on the block device `root_device` (e.g. "ata0p0") becomes the root file system.
+/// If no device is given or it does not contain an ext2 file system, an empty TmpFs is used instead.
+pub fn init(root_device: Option<&str>) {
+    let mut root_fs: Arc<dyn FileSystem> = Arc::new(tmpfs::TmpFs::new(tmpfs::default_size_limit()));
+    if let Some(device) = root_device {
+        match storage::block_device(device).ok_or(Errno::ENOENT).and_then(ext2::Ext2::new) {
+            Ok(fs) => {
+                info!("Using [{}] (ext2) as root file system", device);
+                root_fs = Arc::new(fs);
+            }
+            Err(error) => warn!("Failed to use [{}] as root file system: {:?}", device, error),
+        }
+    }
 
-/// Initilize the naming service (must be called once before using it).
-pub fn init() {
-    // Initialize ROOT with TmpFs
-    ROOT.call_once(|| {
-        let tmpfs = tmpfs::TmpFs::new();
-        Arc::new(tmpfs)
-    });
-    open_objects::open_object_table_init();
-    let mut cwd = CWD.lock();
-    *cwd = "/".to_string();
+    mount::mount("/", root_fs, None).expect("Failed to mount root file system");
     info!("naming service initialized");
     //    test::running_tests();
 }
@@ -52,13 +70,28 @@ pub fn init() {
 /// Open/create a named object referenced by `path` using the given `flags`. \
 /// Returns `Ok(object_handle)` or `Err`.
 pub fn open(path: &String, flags: OpenOptions) -> Result<usize, Errno> {
-    open_objects::open(path, flags).or_else(|e| {
-        if flags.contains(OpenOptions::CREATE) {
-            touch(path).and_then(|_| open_objects::open(path, flags))
-        } else {
-            Err(e)
+    if flags.contains(OpenOptions::CREATE) {
+        // only a missing file is created (existing objects, e.g. in devfs, may be opened in read-only file systems)
+        match lookup::lookup_named_object(path) {
+            Ok(_) if flags.contains(OpenOptions::EXCLUSIVE) => return Err(Errno::EEXIST),
+            Ok(_) => {}
+            Err(Errno::ENOENT) => {
+                // creating requires write access to the parent directory (opening an existing file does not)
+                let result = lookup_parent_writable(path)
+                    .and_then(|(dir, name)| dir.create_file(&name, Mode::new(MODE_FILE).with_permissions(FILE_PERMISSIONS)));
+                match result {
+                    // created concurrently by another process
+                    Err(Errno::EEXIST) if !flags.contains(OpenOptions::EXCLUSIVE) => {}
+                    result => {
+                        result?;
+                    }
+                }
+            }
+            Err(e) => return Err(e),
         }
-    })
+    }
+
+    open_objects::open(path, flags)
 }
 
 /// Write all bytes from the given `buffer` into the named object referenced by `object_handle`. \
@@ -85,76 +118,253 @@ pub fn close(object_handle: usize) -> Result<usize, Errno> {
     open_objects::close(object_handle)
 }
 
-/// Create a directory for the given `path`. \
+/// Duplicate `object_handle`, both handles share the opened object (including the position). \
+/// Returns `Ok(new handle)` or `Err(errno)`
+pub fn dup(object_handle: usize) -> Result<usize, Errno> {
+    open_objects::dup(object_handle)
+}
+
+/// Let `new_handle` reference the same opened object as `object_handle` (an object opened with `new_handle` is closed before). \
+/// Returns `Ok(new_handle)` or `Err(errno)`
+pub fn dup2(object_handle: usize, new_handle: usize) -> Result<usize, Errno> {
+    open_objects::dup2(object_handle, new_handle)
+}
+
+/// Set the size of the file referenced by `object_handle` (opened for writing) to `size` bytes. \
 /// Returns `Ok(0)` or `Err(errno)`
-pub fn mkdir(path: &String) -> Result<usize, Errno> {
-    // Split the path into components
-    let mut components: Vec<&str> = path.split("/").collect();
+pub fn truncate(object_handle: usize, size: usize) -> Result<usize, Errno> {
+    open_objects::truncate(object_handle, size)
+}
 
-    // Remove the last component (the name of the new directory)
-    let new_dir_name = components.pop();
+/// Return the file referenced by `object_handle` and the options used for opening it (e.g. for mapping it into memory). \
+/// Returns `Ok((file, options))` or `Err(errno)`
+pub fn file(object_handle: usize) -> Result<(Arc<dyn FileObject>, OpenOptions), Errno> {
+    open_objects::file(object_handle)
+}
 
-    // We need parent directory to create the new directory
-    let parent_dir;
-    if components.len() == 1 {
-        parent_dir = "/".to_string();
-    } else {
-        parent_dir = components.join("/"); // Joins the remaining components
-    }
-
-    // Safely lookup the parent directory and create the new file
-    let result = lookup::lookup_dir(&parent_dir)
-        .and_then(|dir| {
-            new_dir_name
-                .ok_or(Errno::EINVAL) // Handle missing file name
-                .and_then(|name| dir.create_dir(name, Mode::new(0))) // Create the file
-        })
-        .map(|_| 0); // Convert the success result to 0
-
-    match result {
-        Ok(_) => Ok(0), // Successfully created the file
-        Err(_) => {
-            // Handle the error here (e.g., logging or returning the error code)
-            Err(Errno::ENOTDIR)
+/// Create the named pipe (FIFO) `path`. \
+/// Returns `Ok(0)` or `Err(errno)`
+pub fn mkfifo(path: &String) -> Result<usize, Errno> {
+    let (dir, name) = lookup_parent_writable(path)?;
+    dir.create_fifo(&name).map(|_| 0)
+}
+
+/// Wait until one of the handles in `handles` is ready for the requested events or `timeout_ms` has elapsed
+/// (`POLL_INFINITE` waits forever, 0 only checks the handles). The ready events are returned in `revents`. \
+/// Returns `Ok(number of ready handles)` (0 after a timeout)
+pub fn poll(handles: &mut [PollHandle], timeout_ms: usize) -> Result<usize, Errno> {
+    let deadline = if timeout_ms == POLL_INFINITE { None } else { Some(timer().systime_ms().saturating_add(timeout_ms)) };
+    let mut ready = 0;
+
+    // Every object wakes up its waiting threads, when its state changes -> check all handles again
+    scheduler().wait_any(deadline, || {
+        ready = 0;
+        for entry in handles.iter_mut() {
+            let requested = PollFlags::from_bits_truncate(entry.events) | PollFlags::HANGUP | PollFlags::INVALID;
+            let events = open_objects::poll(entry.handle) & requested;
+            entry.revents = events.bits();
+            if !events.is_empty() {
+                ready += 1;
+            }
+        }
+        ready > 0
+    });
+
+    Ok(ready)
+}
+
+/// Allocate a handle for `file` (not part of a file system, e.g. a network socket) opened with `options`. \
+/// Returns `Ok(handle)` or `Err(errno)`
+pub fn open_file_object(file: Arc<dyn FileObject>, options: OpenOptions) -> Result<usize, Errno> {
+    open_objects::open_object(file.into(), options)
+}
+
+/// Create an anonymous pipe. \
+/// Returns `Ok((read handle, write handle))` or `Err(errno)`
+pub fn pipe() -> Result<(usize, usize), Errno> {
+    let (reader, writer) = pipe::create();
+    let read_handle = open_objects::open_object(reader.into(), OpenOptions::READONLY)?;
+    match open_objects::open_object(writer.into(), OpenOptions::WRITEONLY) {
+        Ok(write_handle) => Ok((read_handle, write_handle)),
+        Err(error) => {
+            let _ = open_objects::close(read_handle);
+            Err(error)
         }
     }
 }
 
+/// Register a watch for the events `mask` of the directory or file referenced by `path` (read permission required). \
+/// Returns `Ok(handle)` for reading the events (see `watch`) or `Err(errno)`
+pub fn watch(path: &String, mask: WatchMask) -> Result<usize, Errno> {
+    let named_object = lookup::lookup_named_object(path)?;
+    access::check(&named_object.stat()?, access::READ)?;
+    open_objects::open_object(watch::create(named_object, mask).into(), OpenOptions::READONLY)
+}
+
+/// Create a directory for the given `path`. \
+/// Returns `Ok(0)` or `Err(errno)`
+pub fn mkdir(path: &String) -> Result<usize, Errno> {
+    // We need parent directory to create the new directory
+    let (dir, new_dir_name) = lookup_parent_writable(path)?;
+    dir.create_dir(&new_dir_name, Mode::new(MODE_DIR).with_permissions(DIR_PERMISSIONS))?;
+    Ok(0)
+}
+
 /// Create an empty file defined by `path`. \
 /// Returns `Ok(0)` or `Err(errno)`
 pub fn touch(path: &String) -> Result<usize, Errno> {
-    // Split the path into components
-    let mut components: Vec<&str> = path.split("/").collect();
+    // We need parent directory to create the new file
+    let (dir, new_file_name) = lookup_parent_writable(path)?;
+    dir.create_file(&new_file_name, Mode::new(MODE_FILE).with_permissions(FILE_PERMISSIONS))?;
+    Ok(0)
+}
 
-    // Remove the last component (the name of the new file)
-    let new_file_name = components.pop();
+/// Remove the file referenced by `path` (must not be a directory). \
+/// Returns `Ok(0)` or `Err(errno)`
+pub fn unlink(path: &String) -> Result<usize, Errno> {
+    let (dir, name) = lookup_parent_writable(path)?;
+    dir.unlink(&name).map(|_| 0)
+}
 
-    // We need parent directory to create the new file
-    let parent_dir;
-    if components.len() == 1 {
-        parent_dir = "/".to_string();
-    } else {
-        parent_dir = components.join("/"); // Joins the remaining components
-    }
-
-    // Safely lookup the parent directory and create the new file
-    let result = lookup::lookup_dir(&parent_dir)
-        .and_then(|dir| {
-            new_file_name
-                .ok_or(Errno::EINVAL) // Handle missing file name
-                .and_then(|name| dir.create_file(name, Mode::new(0))) // Create the file
-        })
-        .map(|_| 0); // Convert the success result to 0
-
-    match result {
-        Ok(_) => Ok(0), // Successfully created the file
-        Err(_) => {
-            // Handle the error here (e.g., logging or returning the error code)
-            Err(Errno::ENOTDIR)
-        }
+/// Remove the empty directory referenced by `path`. \
+/// Returns `Ok(0)` or `Err(errno)`
+pub fn rmdir(path: &String) -> Result<usize, Errno> {
+    let path = lookup::absolute_path(path)?;
+    if mount::is_mount_point(&path) {
+        return Err(Errno::EBUSY);
+    }
+
+    let (dir, name) = lookup_parent_writable(&path)?;
+    dir.rmdir(&name).map(|_| 0)
+}
+
+/// Move the file or directory `old_path` to `new_path` (within the same file system). \
+/// An existing file or empty directory `new_path` is replaced. \
+/// Returns `Ok(0)` or `Err(errno)`
+pub fn rename(old_path: &String, new_path: &String) -> Result<usize, Errno> {
+    // Compare the locations of both entries (with symbolic links in their directories resolved)
+    let old_location = entry_location(old_path)?;
+    let new_location = entry_location(new_path)?;
+    if old_location == new_location {
+        return Ok(0);
+    }
+    if mount::is_mount_point(&old_location) || mount::is_mount_point(&new_location) {
+        return Err(Errno::EBUSY);
+    }
+    if !mount::same_mount(&old_location, &new_location) {
+        return Err(Errno::EXDEV);
+    }
+
+    // A directory cannot be moved into itself (one of the '..' parents of the target is the moved directory)
+    // and an ancestor (which is not empty) cannot be replaced
+    if is_below(&new_location, &old_location) {
+        return Err(Errno::EINVAL);
+    }
+    if is_below(&old_location, &new_location) {
+        return Err(Errno::ENOTEMPTY);
+    }
+
+    let (old_dir, old_name) = lookup_parent_writable(&old_location)?;
+    let (new_dir, new_name) = lookup_parent_writable(&new_location)?;
+    old_dir.rename(&old_name, &new_dir, &new_name).map(|_| 0)
+}
+
+/// Helper function returning the location of the entry `path`: the resolved path of its directory
+/// (see `lookup::resolved_dir_path`) followed by its name (not resolved, the entry may be a link or missing)
+fn entry_location(path: &str) -> Result<String, Errno> {
+    let (parent, name) = lookup::split_parent(path)?;
+    let mut location = lookup::resolved_dir_path(&parent)?;
+    if !location.ends_with('/') {
+        location.push('/');
+    }
+    location.push_str(&name);
+    Ok(location)
+}
+
+/// Helper function looking up the parent directory of `path` for adding or removing the entry for `path`
+/// (requires write and search permission for the directory). \
+/// Returns `Ok((parent directory, name of the entry))` or `Err(errno)`
+fn lookup_parent_writable(path: &str) -> Result<(Arc<dyn DirectoryObject>, String), Errno> {
+    let (parent_dir, name) = lookup::split_parent(path)?;
+    let dir = lookup::lookup_dir(&parent_dir)?;
+    access::check(&dir.stat()?, access::WRITE | access::EXECUTE)?;
+    Ok((dir, name))
+}
+
+/// Helper function checking if the absolute `path` lies below the absolute path `dir` (both without links)
+fn is_below(path: &str, dir: &str) -> bool {
+    path.strip_prefix(dir).is_some_and(|rest| rest.starts_with('/') || dir == "/")
+}
+
+/// Create a symbolic link `link_path` pointing to `target` (which is stored as given). \
+/// Returns `Ok(0)` or `Err(errno)`
+pub fn symlink(target: &String, link_path: &String) -> Result<usize, Errno> {
+    if target.is_empty() {
+        return Err(Errno::ENOENT);
+    }
+
+    let (dir, name) = lookup_parent_writable(link_path)?;
+    dir.create_symlink(&name, target).map(|_| 0)
+}
+
+/// Set the permission bits of the named object referenced by `path` (e.g. 0o755). Only allowed for the owner and root. \
+/// Returns `Ok(0)` or `Err(errno)`
+pub fn chmod(path: &String, permissions: u32) -> Result<usize, Errno> {
+    let named_object = lookup::lookup_named_object(path)?;
+    access::check_owner(&named_object.stat()?)?;
+    named_object.chmod(permissions).map(|_| 0)
+}
+
+/// Set the owner `uid` and the group `gid` of the named object referenced by `path`. Only allowed for root. \
+/// Returns `Ok(0)` or `Err(errno)`
+pub fn chown(path: &String, uid: u32, gid: u32) -> Result<usize, Errno> {
+    let named_object = lookup::lookup_named_object(path)?;
+    if access::credentials().0 != ROOT_UID {
+        return Err(Errno::EACCES);
+    }
+    named_object.chown(uid, gid).map(|_| 0)
+}
+
+/// Set the time of the last access `accessed` and the last modification `modified` (in ms) of the named object
+/// referenced by `path`. `UTIME_NOW` sets the current time, `UTIME_OMIT` leaves a timestamp unchanged.
+/// Only allowed for the owner and root. \
+/// Returns `Ok(0)` or `Err(errno)`
+pub fn utimes(path: &String, accessed: u64, modified: u64) -> Result<usize, Errno> {
+    let named_object = lookup::lookup_named_object(path)?;
+    access::check_owner(&named_object.stat()?)?;
+
+    let now = stat::now();
+    let time = |time| match time {
+        UTIME_OMIT => None,
+        UTIME_NOW => Some(now),
+        time => Some(time),
+    };
+    named_object.utimes(time(accessed), time(modified)).map(|_| 0)
+}
+
+/// Get the target of the symbolic link referenced by `path`. \
+/// Returns `Ok(target)` or `Err(Errno::EINVAL)` if `path` is not a symbolic link
+pub fn readlink(path: &String) -> Result<String, Errno> {
+    match lookup::lookup_named_object_nofollow(path)? {
+        NamedObject::FileObject(file) => file.readlink(),
+        NamedObject::DirectoryObject(_) => Err(Errno::EINVAL),
     }
 }
 
+/// Get the meta data of the named object referenced by `path`. \
+/// Returns `Ok(FileStat)` or `Err(errno)`
+pub fn stat(path: &String) -> Result<FileStat, Errno> {
+    lookup::lookup_named_object(path)
+        .and_then(|named_object| named_object.stat())
+        .map(|stat| stat.to_file_stat())
+}
+
+/// Get the meta data of the named object referenced by `object_handle`. \
+/// Returns `Ok(FileStat)` or `Err(errno)`
+pub fn fstat(object_handle: usize) -> Result<FileStat, Errno> {
+    open_objects::fstat(object_handle).map(|stat| stat.to_file_stat())
+}
+
 /// Read next directory entry of directory referenced by `dir_handle` \
 /// Returns: \
 ///   `Ok(1)` next directory entry in `dentry` \
@@ -190,11 +400,21 @@ pub fn readdir(dir_handle: usize, dentry: *mut RawDirent) -> Result<usize, Errno
     }
 }
 
-/// Get the current working directory and return path in `buffer`. \
+/// Fill `buf` with as many entries of the directory referenced by `dir_handle` as fit, each consisting of
+/// a `RawDirentHeader` followed by the name. \
+/// Returns: \
+///   `Ok(bytes written)` or `Ok(0)` if there are no more entries in the directory \
+///   `Err(Errno::EINVAL)` if `buf` is too small for the next entry
+pub fn getdents(dir_handle: usize, buf: &mut [u8]) -> Result<usize, Errno> {
+    open_objects::getdents(dir_handle, buf)
+}
+
+/// Get the current working directory of the calling process and return path in `buffer`. \
 /// Return: `Ok(len of string)` or `Err(errno)`
 pub fn cwd(buffer: &mut [u8]) -> Result<usize, Errno> {
-    // Lock the CWD mutex to access its value
-    let cwd = CWD.lock();
+    // Lock the cwd of the current process to access its value
+    let process = process_manager().read().current_process();
+    let cwd = process.cwd.lock();
     
     // Get the string as bytes
     let cwd_bytes = cwd.as_bytes();
@@ -215,21 +435,96 @@ pub fn cwd(buffer: &mut [u8]) -> Result<usize, Errno> {
 }
 
 ///
-/// Description: Change working directory \
-/// Parameters: `path` absolute path \
+/// Description: Change working directory of the calling process \
+/// Parameters: `path` absolute path or path relative to the current working directory \
 /// Return: `Ok(0)` or `Err(errno)`
 ///
 pub fn cd(path: &String) -> Result<usize, Errno> {
-    let result = lookup::lookup_dir(&path);
-    match result {
-        Ok(_) => {
-            let mut cwd = CWD.lock();
-            *cwd = path.clone();
-            Ok(0)
+    let path = lookup::absolute_path(path)?;
+    let dir = lookup::lookup_dir(&path)?;
+    access::check(&dir.stat()?, access::EXECUTE)?;
+
+    let process = process_manager().read().current_process();
+    *process.cwd.lock() = path;
+    Ok(0)
+}
+
+/// Mount the file system of type `fs_type` stored on the block device `device` at the directory `target`. \
+/// Supported types are `tmpfs` (`device` may give a size limit like `size=16m`, see `tmpfs::size_limit`),
+/// `devfs`, `procfs` and `shmfs` (`device` is ignored), `fat32`, `ext2`, `pmemfs` (`device` is a non-volatile
+/// memory region, e.g. `pmem0`) and `tarfs` (read-only, `device` must be `initrd`). Only root may mount. \
+/// Returns `Ok(0)` or `Err(errno)`
+pub fn mount(device: &String, target: &String, fs_type: &String) -> Result<usize, Errno> {
+    access::check_root()?;
+
+    // mount point must be an existing directory
+    let target = lookup::absolute_path(target)?;
+    lookup::lookup_dir(&target)?;
+
+    // a device must not be used by two file systems (checked before opening, which may already write to it)
+    let used_device = matches!(fs_type.as_str(), "fat32" | "ext2" | "pmemfs").then_some(device.as_str());
+    if used_device.is_some_and(mount::is_device_mounted) {
+        return Err(Errno::EBUSY);
+    }
+
+    let fs: Arc<dyn FileSystem> = match fs_type.as_str() {
+        "tmpfs" => Arc::new(tmpfs::TmpFs::new(tmpfs::size_limit(device)?)),
+        "devfs" => Arc::new(devfs::DevFs::new()),
+        "procfs" => Arc::new(procfs::ProcFs::new()),
+        "shmfs" => Arc::new(shmfs::ShmFs::new()),
+        "fat32" => {
+            let block_device = storage::block_device(device).ok_or(Errno::ENOENT)?;
+            Arc::new(fat32::Fat32::new(block_device)?)
+        }
+        "ext2" => {
+            let block_device = storage::block_device(device).ok_or(Errno::ENOENT)?;
+            Arc::new(ext2::Ext2::new(block_device)?)
+        }
+        "pmemfs" => {
+            let region = nvmem::region(device).ok_or(Errno::ENOENT)?;
+            Arc::new(pmemfs::PmemFs::new(region)?)
+        }
+        "tarfs" => match device.as_str() {
+            "initrd" => Arc::new(tarfs::TarFs::new(initrd())),
+            _ => return Err(Errno::ENOENT),
         },
-        Err(_) => {
-            // Handle the error here (e.g., logging or returning the error code)
-            Err(Errno::ENOTDIR)
+        _ => return Err(Errno::EINVAL),
+    };
+
+    mount::mount(&target, fs, used_device).inspect(|_| info!("Mounted [{}] ({}) at [{}]", device, fs_type, target))
+}
+
+/// Unmount the file system mounted at `target` (only root may unmount) and write back the cached sectors
+/// of its block device. \
+/// Returns `Ok(0)` or `Err(errno)`
+pub fn umount(target: &String) -> Result<usize, Errno> {
+    access::check_root()?;
+    if let Some(device) = mount::umount(&lookup::absolute_path(target)?)? {
+        // write back cached sectors of the file system, before the device may be removed or used otherwise
+        storage::sync_device(&device)?;
+    }
+    Ok(0)
+}
+
+/// Read the complete file referenced by `path` (absolute or relative to the current working directory). \
+/// Used for loading executables, so execute permission is required. \
+/// Returns `Ok(file content)` or `Err(errno)`
+pub fn load_file(path: &String) -> Result<Vec<u8>, Errno> {
+    let named_object = lookup::lookup_named_object(path)?;
+    let file = named_object.as_file()?;
+    let stat = file.stat()?;
+    access::check(&stat, access::EXECUTE)?;
+    let size = stat.size;
+    let mut data = vec![0; size];
+
+    let mut read = 0;
+    while read < size {
+        match file.read(&mut data[read..], read, OpenOptions::READONLY)? {
+            0 => break,
+            count => read += count,
         }
     }
+
+    data.truncate(read);
+    Ok(data)
 }
@@ -0,0 +1,367 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: devfs                                                           ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Device file system (mounted at /dev) providing kernel devices as named  ║
+   ║ objects:                                                                ║
+   ║   - all registered block devices and partitions (e.g. ata0, ata0p0)     ║
+   ║   - terminal: read from keyboard, write to screen                       ║
+   ║   - com1 .. com4: the initialized serial port                           ║
+   ║   - speaker: write "<frequency> [<duration in ms>]", "0" turns it off   ║
+   ║   - shm: empty directory, mount point of shmfs (shared memory objects)  ║
+   ║ The directory content is generated on each access, so block devices     ║
+   ║ registered after mounting show up as well. All nodes belong to root,    ║
+   ║ only root may access block devices.                                     ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt;
+use core::result::Result;
+use core::str::from_utf8;
+
+use super::stat::{Mode, Stat, DIR_PERMISSIONS, MODE_BLOCK_DEVICE, MODE_CHAR_DEVICE, MODE_DIR};
+use super::traits::{DirectoryObject, FileObject, FileSystem, NamedObject};
+use crate::device::ps2::INPUT_QUEUE;
+use crate::device::serial::SerialPort;
+use crate::storage::block::BlockDevice;
+use crate::{scheduler, serial_port, speaker, storage, terminal};
+use naming::shared_types::{DirEntry, FileType, OpenOptions, PollFlags};
+use syscall::return_vals::Errno;
+
+/// Permissions of the device nodes (all owned by root and group 0)
+const BLOCK_DEVICE_PERMISSIONS: u32 = 0o600;
+const TERMINAL_PERMISSIONS: u32 = 0o620;
+const CHAR_DEVICE_PERMISSIONS: u32 = 0o660; // serial ports and speaker
+
+pub struct DevFs {
+    root_dir: Arc<Dir>,
+}
+
+impl DevFs {
+    pub fn new() -> DevFs {
+        DevFs {
+            root_dir: Arc::new(Dir {}),
+        }
+    }
+}
+
+impl FileSystem for DevFs {
+    fn root_dir(&self) -> Arc<dyn DirectoryObject> {
+        self.root_dir.clone()
+    }
+}
+
+/// Return the terminal device (used for the standard handles of processes)
+pub(super) fn terminal_file() -> Arc<dyn FileObject> {
+    Arc::new(TerminalFile {})
+}
+
+/// The only directory of devfs
+pub struct Dir {}
+
+impl Dir {
+    /// Return names and types of all devices currently available
+    fn entries(&self) -> Vec<(String, FileType)> {
+        let mut entries: Vec<(String, FileType)> = storage::block_device_names()
+            .into_iter()
+            .map(|name| (name, FileType::BlockDevice))
+            .collect();
+
+        entries.push(("terminal".to_string(), FileType::CharDevice));
+        if let Some(serial) = serial_port() {
+            entries.push((serial_port_name(&serial), FileType::CharDevice));
+        }
+        entries.push(("speaker".to_string(), FileType::CharDevice));
+        entries.push(("shm".to_string(), FileType::Directory));
+        entries
+    }
+}
+
+impl DirectoryObject for Dir {
+    fn lookup(&self, name: &str) -> Result<NamedObject, Errno> {
+        let device: Arc<dyn FileObject> = match name {
+            "terminal" => Arc::new(TerminalFile {}),
+            "speaker" => Arc::new(SpeakerFile {}),
+            "shm" => return Ok((Arc::new(MountDir {}) as Arc<dyn DirectoryObject>).into()),
+            _ => {
+                if let Some(device) = storage::block_device(name) {
+                    Arc::new(BlockDeviceFile { device })
+                } else {
+                    match serial_port() {
+                        Some(serial) if serial_port_name(&serial) == name => Arc::new(SerialFile { serial }),
+                        _ => return Err(Errno::ENOENT),
+                    }
+                }
+            }
+        };
+        Ok(device.into())
+    }
+
+    fn create_file(&self, _name: &str, _mode: Mode) -> Result<NamedObject, Errno> {
+        Err(Errno::EACCES)
+    }
+
+    fn create_dir(&self, _name: &str, _mode: Mode) -> Result<NamedObject, Errno> {
+        Err(Errno::EACCES)
+    }
+
+    fn stat(&self) -> Result<Stat, Errno> {
+        Ok(Stat::new(Mode::new(MODE_DIR).with_permissions(DIR_PERMISSIONS), 0))
+    }
+
+    fn readdir(&self, index: usize) -> Result<Option<DirEntry>, Errno> {
+        Ok(self
+            .entries()
+            .into_iter()
+            .nth(index)
+            .map(|(name, file_type)| DirEntry { inode: index as u64 + 1, file_type, name }))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Debug for Dir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DevFsDir").finish()
+    }
+}
+
+/// Empty directory, serving as mount point for another file system (e.g. shmfs at /dev/shm)
+struct MountDir {}
+
+impl DirectoryObject for MountDir {
+    fn lookup(&self, _name: &str) -> Result<NamedObject, Errno> {
+        Err(Errno::ENOENT)
+    }
+
+    fn create_file(&self, _name: &str, _mode: Mode) -> Result<NamedObject, Errno> {
+        Err(Errno::EACCES)
+    }
+
+    fn create_dir(&self, _name: &str, _mode: Mode) -> Result<NamedObject, Errno> {
+        Err(Errno::EACCES)
+    }
+
+    fn stat(&self) -> Result<Stat, Errno> {
+        Ok(Stat::new(Mode::new(MODE_DIR).with_permissions(DIR_PERMISSIONS), 0))
+    }
+
+    fn readdir(&self, _index: usize) -> Result<Option<DirEntry>, Errno> {
+        Ok(None)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Debug for MountDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DevFsMountDir").finish()
+    }
+}
+
+/// Name of the serial port in devfs (e.g. "com1")
+fn serial_port_name(serial: &SerialPort) -> String {
+    format!("{:?}", serial.port()).to_lowercase()
+}
+
+/// Raw access to a block device (or partition) at arbitrary byte offsets.
+/// Sectors which are only partially written are read first.
+struct BlockDeviceFile {
+    device: Arc<dyn BlockDevice + Send + Sync>,
+}
+
+impl BlockDeviceFile {
+    fn size(&self) -> usize {
+        self.device.sector_count() as usize * self.device.sector_size() as usize
+    }
+
+    /// Return first sector, number of sectors and offset within the first sector for the given byte range
+    fn sector_range(&self, offset: usize, len: usize) -> (u64, usize, usize) {
+        let sector_size = self.device.sector_size() as usize;
+        let first = offset / sector_size;
+        let last = (offset + len - 1) / sector_size;
+        (first as u64, last - first + 1, offset % sector_size)
+    }
+}
+
+impl FileObject for BlockDeviceFile {
+    fn stat(&self) -> Result<Stat, Errno> {
+        Ok(Stat::new(Mode::new(MODE_BLOCK_DEVICE).with_permissions(BLOCK_DEVICE_PERMISSIONS), self.size()))
+    }
+
+    fn read(&self, buf: &mut [u8], offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        let size = self.size();
+        if offset >= size || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let len = buf.len().min(size - offset);
+        let (sector, count, start) = self.sector_range(offset, len);
+        let mut data = vec![0u8; count * self.device.sector_size() as usize];
+        if self.device.read(sector, count, &mut data) != count {
+            return Err(Errno::EIO);
+        }
+
+        buf[..len].copy_from_slice(&data[start..start + len]);
+        Ok(len)
+    }
+
+    fn write(&self, buf: &[u8], offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        let size = self.size();
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if offset >= size {
+            return Err(Errno::ENOSPC);
+        }
+
+        let len = buf.len().min(size - offset);
+        let sector_size = self.device.sector_size() as usize;
+        let (sector, count, start) = self.sector_range(offset, len);
+        let mut data = vec![0u8; count * sector_size];
+
+        // Keep the content of partially written sectors
+        let partial = start != 0 || len % sector_size != 0;
+        if partial && self.device.read(sector, count, &mut data) != count {
+            return Err(Errno::EIO);
+        }
+
+        data[start..start + len].copy_from_slice(&buf[..len]);
+        if self.device.write(sector, count, &data) != count {
+            return Err(Errno::EIO);
+        }
+        Ok(len)
+    }
+}
+
+impl fmt::Debug for BlockDeviceFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DevFsBlockDevice").finish()
+    }
+}
+
+/// Write bytes to `stream`, valid UTF-8 is written as string, anything else byte by byte
+fn write_bytes(stream: &dyn stream::OutputStream, buf: &[u8]) {
+    match from_utf8(buf) {
+        Ok(string) => stream.write_str(string),
+        Err(_) => buf.iter().for_each(|&byte| stream.write_byte(byte)),
+    }
+}
+
+/// Read bytes from `stream` until `buf` is full or a line is complete. Before each byte `wait` is called,
+/// blocking the calling thread until input is available (instead of spinning in `read_byte`). \
+/// Returns the number of bytes read (0 if the stream is closed).
+fn read_line(stream: &dyn stream::InputStream, buf: &mut [u8], wait: &dyn Fn()) -> usize {
+    let mut count = 0;
+    while count < buf.len() {
+        wait();
+        match stream.read_byte() {
+            -1 => break,
+            byte => {
+                buf[count] = byte as u8;
+                count += 1;
+                if byte == '\n' as i16 {
+                    break;
+                }
+            }
+        }
+    }
+    count
+}
+
+/// Terminal: reading returns keyboard input line by line, writing prints on the screen
+struct TerminalFile {}
+
+impl FileObject for TerminalFile {
+    fn stat(&self) -> Result<Stat, Errno> {
+        Ok(Stat::new(Mode::new(MODE_CHAR_DEVICE).with_permissions(TERMINAL_PERMISSIONS), 0))
+    }
+
+    fn read(&self, buf: &mut [u8], _offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        let terminal = terminal();
+        Ok(read_line(terminal.as_ref(), buf, &|| {
+            scheduler().wait(&INPUT_QUEUE, None, || terminal.has_input());
+        }))
+    }
+
+    fn write(&self, buf: &[u8], _offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        let terminal = terminal();
+        write_bytes(terminal.as_ref(), buf);
+        Ok(buf.len())
+    }
+
+    /// Readable if a key has been pressed, writing never blocks
+    fn poll(&self) -> PollFlags {
+        if terminal().has_input() { PollFlags::READABLE | PollFlags::WRITABLE } else { PollFlags::WRITABLE }
+    }
+}
+
+impl fmt::Debug for TerminalFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DevFsTerminal").finish()
+    }
+}
+
+/// Serial port
+struct SerialFile {
+    serial: Arc<SerialPort>,
+}
+
+impl FileObject for SerialFile {
+    fn stat(&self) -> Result<Stat, Errno> {
+        Ok(Stat::new(Mode::new(MODE_CHAR_DEVICE).with_permissions(CHAR_DEVICE_PERMISSIONS), 0))
+    }
+
+    fn read(&self, buf: &mut [u8], _offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        Ok(read_line(self.serial.as_ref(), buf, &|| {}))
+    }
+
+    fn write(&self, buf: &[u8], _offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        write_bytes(self.serial.as_ref(), buf);
+        Ok(buf.len())
+    }
+}
+
+impl fmt::Debug for SerialFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DevFsSerial").field("port", &self.serial.port()).finish()
+    }
+}
+
+/// PC speaker, controlled by writing lines "<frequency> [<duration in ms>]"
+struct SpeakerFile {}
+
+impl FileObject for SpeakerFile {
+    fn stat(&self) -> Result<Stat, Errno> {
+        Ok(Stat::new(Mode::new(MODE_CHAR_DEVICE).with_permissions(CHAR_DEVICE_PERMISSIONS), 0))
+    }
+
+    fn write(&self, buf: &[u8], _offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        let commands = from_utf8(buf).map_err(|_| Errno::EINVAL)?;
+        for command in commands.lines() {
+            let mut params = command.split_whitespace().map(|param| param.parse::<usize>());
+            match (params.next(), params.next()) {
+                (None, _) => {}
+                (Some(Ok(0)), None) => speaker().off(),
+                (Some(Ok(freq)), None) => speaker().on(freq),
+                (Some(Ok(freq)), Some(Ok(duration))) if freq > 0 => speaker().play(freq, duration),
+                _ => return Err(Errno::EINVAL),
+            }
+        }
+        Ok(buf.len())
+    }
+}
+
+impl fmt::Debug for SpeakerFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DevFsSpeaker").finish()
+    }
+}
@@ -0,0 +1,1696 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: ext2                                                            ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ ext2 file system working on any block device (disk or partition), e.g.  ║
+   ║ images created with 'mke2fs -t ext2'. Supports reading and writing      ║
+   ║ files, directories and symbolic links, including permissions, owners    ║
+   ║ and timestamps. Access times are not updated when reading.              ║
+   ║                                                                         ║
+   ║ Layout of an ext2 volume:                                               ║
+   ║   - superblock at byte 1024 (geometry, free counts and features)        ║
+   ║   - block groups, each with a block bitmap, an inode bitmap and an      ║
+   ║     inode table; the group descriptor table follows the superblock      ║
+   ║ An inode references its data with 12 direct block pointers, followed    ║
+   ║ by a single, a double and a triple indirect block pointer. Directories  ║
+   ║ are files containing variable sized entries (inode number and name).    ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt;
+use core::result::Result;
+use log::{info, warn};
+use spin::Mutex;
+
+use super::access;
+use super::stat::{self, Mode, Stat, MODE_BLOCK_DEVICE, MODE_CHAR_DEVICE, MODE_DIR, MODE_FIFO, MODE_FILE, MODE_LINK};
+use super::traits::{DirectoryObject, FileObject, FileSystem, NamedObject};
+use super::update_lock::{UpdateGuard, UpdateLock};
+use crate::storage::block::BlockDevice;
+use naming::shared_types::{DirEntry, FileType, OpenOptions};
+use syscall::return_vals::Errno;
+
+/// Position of the superblock (independent of the block size)
+const SUPERBLOCK_OFFSET: usize = 1024;
+const SUPERBLOCK_SIZE: usize = 1024;
+const EXT2_MAGIC: u16 = 0xef53;
+/// Block sizes from 1 KiB up to 4 KiB are supported
+const MAX_LOG_BLOCK_SIZE: u32 = 2;
+
+/// Feature flags (only these features are supported, others prevent mounting or writing)
+const INCOMPAT_FILETYPE: u32 = 0x0002;
+const RO_COMPAT_SPARSE_SUPER: u32 = 0x0001;
+const RO_COMPAT_LARGE_FILE: u32 = 0x0002;
+const RO_COMPAT_BTREE_DIR: u32 = 0x0004;
+
+/// Values used by revision 0 file systems
+const GOOD_OLD_INODE_SIZE: usize = 128;
+const GOOD_OLD_FIRST_INODE: u32 = 11;
+
+const ROOT_INODE: u32 = 2;
+const GROUP_DESC_SIZE: usize = 32;
+
+/// Type bits of the mode of an inode
+const S_IFMT: u16 = 0xf000;
+const S_IFSOCK: u16 = 0xc000;
+const S_IFLNK: u16 = 0xa000;
+const S_IFREG: u16 = 0x8000;
+const S_IFBLK: u16 = 0x6000;
+const S_IFDIR: u16 = 0x4000;
+const S_IFCHR: u16 = 0x2000;
+const S_IFIFO: u16 = 0x1000;
+const PERMISSIONS_MASK: u16 = 0o777;
+
+/// Inode flag of directories indexed by a hash tree (cleared when adding entries, since the index is not maintained)
+const INDEX_FL: u32 = 0x1000;
+
+/// Block pointers of an inode: 12 direct ones, followed by a single, a double and a triple indirect one
+const DIRECT_BLOCKS: usize = 12;
+const INDIRECT_LEVELS: usize = 3;
+
+/// File types stored in directory entries (if the file system has the filetype feature)
+const FT_UNKNOWN: u8 = 0;
+const FT_REG_FILE: u8 = 1;
+const FT_DIR: u8 = 2;
+const FT_CHRDEV: u8 = 3;
+const FT_BLKDEV: u8 = 4;
+const FT_FIFO: u8 = 5;
+const FT_SOCK: u8 = 6;
+const FT_SYMLINK: u8 = 7;
+
+const DIR_ENTRY_HEADER: usize = 8;
+const MAX_NAME_LENGTH: usize = 255;
+
+/// Targets of symbolic links shorter than this are stored in the block pointers of the inode ("fast symlinks")
+const FAST_SYMLINK_SIZE: usize = 60;
+
+/// Extended attribute blocks (may be shared between inodes and are reference counted)
+const XATTR_MAGIC: u32 = 0xea02_0000;
+
+/// `i_blocks` of an inode counts 512 byte units
+const I_BLOCKS_UNIT: usize = 512;
+
+pub struct Ext2 {
+    volume: Arc<Volume>,
+    root_generation: u32,
+}
+
+impl Ext2 {
+    /// Open the ext2 file system stored on `device`. \
+    /// Returns `Err(Errno::EINVAL)` if the device does not contain a (supported) ext2 file system.
+    pub fn new(device: Arc<dyn BlockDevice + Send + Sync>) -> Result<Ext2, Errno> {
+        let volume = Volume::open(device)?;
+        info!(
+            "ext2: found volume with [{}] blocks of [{}] bytes and [{}] inodes",
+            volume.blocks_count, volume.block_size, volume.inodes_count
+        );
+        if volume.read_only {
+            warn!("ext2: volume uses unsupported features, mounting it read-only");
+        }
+
+        let root_generation = volume.read_inode(ROOT_INODE)?.generation();
+        Ok(Ext2 {
+            volume: Arc::new(volume),
+            root_generation,
+        })
+    }
+}
+
+impl FileSystem for Ext2 {
+    fn root_dir(&self) -> Arc<dyn DirectoryObject> {
+        Arc::new(Dir::new(self.volume.clone(), ROOT_INODE, self.root_generation))
+    }
+}
+
+/// Location of the bitmaps and the inode table of a block group (never changes)
+struct Group {
+    block_bitmap: u32,
+    inode_bitmap: u32,
+    inode_table: u32,
+}
+
+/// Counters of a block group, mirrored in its group descriptor
+#[derive(Clone, Copy)]
+struct GroupCounts {
+    free_blocks: u16,
+    free_inodes: u16,
+    used_dirs: u16,
+}
+
+/// Free counts of the volume, mirrored in the superblock and the group descriptors. \
+/// They are only locked for updating them, never during disk I/O (see `Volume::update_lock`).
+struct AllocState {
+    free_blocks: u32,
+    free_inodes: u32,
+    groups: Vec<GroupCounts>,
+}
+
+/// Geometry of the volume (parsed from the superblock and the group descriptors) and helper functions
+/// for accessing blocks, inodes, bitmaps and directories.
+struct Volume {
+    device: Arc<dyn BlockDevice + Send + Sync>,
+    block_size: usize,
+    sectors_per_block: usize,
+    blocks_count: u32,
+    inodes_count: u32,
+    first_data_block: u32,
+    blocks_per_group: u32,
+    inodes_per_group: u32,
+    inode_size: usize,
+    first_inode: u32,
+    filetype_feature: bool,
+    large_file_feature: bool,
+    read_only: bool,
+    groups: Vec<Group>,
+    alloc_state: Mutex<AllocState>,
+    update_lock: UpdateLock, // serializes all modifying operations on the volume (e.g. the bitmaps)
+}
+
+impl Volume {
+    fn open(device: Arc<dyn BlockDevice + Send + Sync>) -> Result<Volume, Errno> {
+        let sector_size = device.sector_size() as usize;
+        if sector_size == 0 || SUPERBLOCK_SIZE % sector_size != 0 {
+            return Err(Errno::EINVAL);
+        }
+
+        let mut superblock = vec![0u8; SUPERBLOCK_SIZE];
+        let count = SUPERBLOCK_SIZE / sector_size;
+        if device.read((SUPERBLOCK_OFFSET / sector_size) as u64, count, &mut superblock) != count {
+            return Err(Errno::EIO);
+        }
+
+        if read_u16(&superblock, 56) != EXT2_MAGIC {
+            return Err(Errno::EINVAL);
+        }
+
+        let inodes_count = read_u32(&superblock, 0);
+        let blocks_count = read_u32(&superblock, 4);
+        let free_blocks = read_u32(&superblock, 12);
+        let free_inodes = read_u32(&superblock, 16);
+        let first_data_block = read_u32(&superblock, 20);
+        let log_block_size = read_u32(&superblock, 24);
+        let blocks_per_group = read_u32(&superblock, 32);
+        let inodes_per_group = read_u32(&superblock, 40);
+        let rev_level = read_u32(&superblock, 76);
+
+        // Revision 0 has fixed inode sizes and no feature flags
+        let (first_inode, inode_size, incompat, ro_compat) = if rev_level == 0 {
+            (GOOD_OLD_FIRST_INODE, GOOD_OLD_INODE_SIZE, 0, 0)
+        } else {
+            (
+                read_u32(&superblock, 84),
+                read_u16(&superblock, 88) as usize,
+                read_u32(&superblock, 96),
+                read_u32(&superblock, 100),
+            )
+        };
+
+        // Journal recovery, extents, 64 bit block numbers etc. are not supported
+        if incompat & !INCOMPAT_FILETYPE != 0 || log_block_size > MAX_LOG_BLOCK_SIZE {
+            return Err(Errno::EINVAL);
+        }
+
+        let block_size = 1024usize << log_block_size;
+        let bits_per_block = block_size as u32 * 8;
+        if blocks_per_group == 0 || blocks_per_group > bits_per_block || inodes_per_group == 0 || inodes_per_group > bits_per_block {
+            return Err(Errno::EINVAL);
+        }
+        if inode_size < GOOD_OLD_INODE_SIZE || inode_size > block_size || !inode_size.is_power_of_two() {
+            return Err(Errno::EINVAL);
+        }
+        if blocks_count <= first_data_block || first_inode <= ROOT_INODE {
+            return Err(Errno::EINVAL);
+        }
+
+        let group_count = (blocks_count - first_data_block).div_ceil(blocks_per_group) as usize;
+        if (group_count as u64) * (inodes_per_group as u64) < inodes_count as u64 {
+            return Err(Errno::EINVAL);
+        }
+
+        let mut volume = Volume {
+            device,
+            block_size,
+            sectors_per_block: block_size / sector_size,
+            blocks_count,
+            inodes_count,
+            first_data_block,
+            blocks_per_group,
+            inodes_per_group,
+            inode_size,
+            first_inode,
+            filetype_feature: incompat & INCOMPAT_FILETYPE != 0,
+            large_file_feature: ro_compat & RO_COMPAT_LARGE_FILE != 0,
+            read_only: ro_compat & !(RO_COMPAT_SPARSE_SUPER | RO_COMPAT_LARGE_FILE | RO_COMPAT_BTREE_DIR) != 0,
+            groups: Vec::with_capacity(group_count),
+            alloc_state: Mutex::new(AllocState {
+                free_blocks,
+                free_inodes,
+                groups: Vec::with_capacity(group_count),
+            }),
+            update_lock: UpdateLock::new(),
+        };
+
+        // Read the group descriptor table (starts in the block following the superblock)
+        let table_blocks = (group_count * GROUP_DESC_SIZE).div_ceil(block_size);
+        let mut table = vec![0u8; table_blocks * block_size];
+        for (index, chunk) in table.chunks_exact_mut(block_size).enumerate() {
+            volume.read_block(first_data_block + 1 + index as u32, chunk)?;
+        }
+
+        for desc in table.chunks_exact(GROUP_DESC_SIZE).take(group_count) {
+            let group = Group {
+                block_bitmap: read_u32(desc, 0),
+                inode_bitmap: read_u32(desc, 4),
+                inode_table: read_u32(desc, 8),
+            };
+            if group.block_bitmap >= blocks_count || group.inode_bitmap >= blocks_count || group.inode_table >= blocks_count {
+                return Err(Errno::EINVAL);
+            }
+
+            volume.groups.push(group);
+            volume.alloc_state.get_mut().groups.push(GroupCounts {
+                free_blocks: read_u16(desc, 12),
+                free_inodes: read_u16(desc, 14),
+                used_dirs: read_u16(desc, 16),
+            });
+        }
+
+        if !volume.read_inode(ROOT_INODE)?.is_dir() {
+            return Err(Errno::EINVAL);
+        }
+
+        Ok(volume)
+    }
+
+    fn check_writable(&self) -> Result<(), Errno> {
+        if self.read_only {
+            return Err(Errno::EROFS);
+        }
+        Ok(())
+    }
+
+    /// Number of 512 byte units (as counted in `i_blocks`) per block
+    fn block_units(&self) -> u32 {
+        (self.block_size / I_BLOCKS_UNIT) as u32
+    }
+
+    /// Number of block pointers in an indirect block
+    fn pointers_per_block(&self) -> u64 {
+        (self.block_size / 4) as u64
+    }
+
+    fn read_block(&self, block: u32, buffer: &mut [u8]) -> Result<(), Errno> {
+        if block >= self.blocks_count {
+            return Err(Errno::EIO);
+        }
+        let count = self.sectors_per_block;
+        if self.device.read(block as u64 * count as u64, count, buffer) != count {
+            return Err(Errno::EIO);
+        }
+        Ok(())
+    }
+
+    fn write_block(&self, block: u32, buffer: &[u8]) -> Result<(), Errno> {
+        if block >= self.blocks_count {
+            return Err(Errno::EIO);
+        }
+        let count = self.sectors_per_block;
+        if self.device.write(block as u64 * count as u64, count, buffer) != count {
+            return Err(Errno::EIO);
+        }
+        Ok(())
+    }
+
+    /// Return the block group containing the inode `number`
+    fn inode_group(&self, number: u32) -> usize {
+        ((number - 1) / self.inodes_per_group) as usize
+    }
+
+    /// Return the block and the offset within the block of the inode `number`
+    fn inode_location(&self, number: u32) -> Result<(u32, usize), Errno> {
+        if number == 0 || number > self.inodes_count {
+            return Err(Errno::EIO);
+        }
+
+        let offset = ((number - 1) % self.inodes_per_group) as usize * self.inode_size;
+        let table = self.groups[self.inode_group(number)].inode_table;
+        Ok((table + (offset / self.block_size) as u32, offset % self.block_size))
+    }
+
+    fn read_inode(&self, number: u32) -> Result<Inode, Errno> {
+        let (block, offset) = self.inode_location(number)?;
+        let mut buffer = vec![0u8; self.block_size];
+        self.read_block(block, &mut buffer)?;
+
+        Ok(Inode {
+            number,
+            raw: buffer[offset..offset + self.inode_size].to_vec(),
+        })
+    }
+
+    fn write_inode(&self, inode: &Inode) -> Result<(), Errno> {
+        let (block, offset) = self.inode_location(inode.number)?;
+        let mut buffer = vec![0u8; self.block_size];
+        self.read_block(block, &mut buffer)?;
+        buffer[offset..offset + self.inode_size].copy_from_slice(&inode.raw);
+        self.write_block(block, &buffer)
+    }
+
+    /// Read the inode of a file or directory object, which must still exist. The inode number of a removed
+    /// object may have been reused, `generation` detects this. \
+    /// Returns `Err(Errno::ENOENT)` if the object has been removed.
+    fn object_inode(&self, number: u32, generation: u32) -> Result<Inode, Errno> {
+        let inode = self.read_inode(number)?;
+        if inode.links() == 0 || inode.generation() != generation {
+            return Err(Errno::ENOENT);
+        }
+        Ok(inode)
+    }
+
+    /// Read the inode of the object `number` (see `object_inode`), apply `update` and write it back
+    /// Block until no other modifying operation is running on the volume (see `UpdateLock`)
+    fn lock_update(&self) -> UpdateGuard<'_> {
+        self.update_lock.lock()
+    }
+
+    fn update_inode(&self, number: u32, generation: u32, update: impl FnOnce(&mut Inode)) -> Result<(), Errno> {
+        let mut inode = self.object_inode(number, generation)?;
+        update(&mut inode);
+        self.write_inode(&inode)
+    }
+
+    /// Write the counters of `group` back into its group descriptor and the free counts into the superblock
+    fn write_counts(&self, group: usize) -> Result<(), Errno> {
+        let (counts, free_blocks, free_inodes) = {
+            let state = self.alloc_state.lock();
+            (state.groups[group], state.free_blocks, state.free_inodes)
+        };
+        let mut buffer = vec![0u8; self.block_size];
+
+        let offset = group * GROUP_DESC_SIZE;
+        let block = self.first_data_block + 1 + (offset / self.block_size) as u32;
+        let pos = offset % self.block_size;
+        self.read_block(block, &mut buffer)?;
+        write_u16(&mut buffer, pos + 12, counts.free_blocks);
+        write_u16(&mut buffer, pos + 14, counts.free_inodes);
+        write_u16(&mut buffer, pos + 16, counts.used_dirs);
+        self.write_block(block, &buffer)?;
+
+        let block = (SUPERBLOCK_OFFSET / self.block_size) as u32;
+        let pos = SUPERBLOCK_OFFSET % self.block_size;
+        self.read_block(block, &mut buffer)?;
+        write_u32(&mut buffer, pos + 12, free_blocks);
+        write_u32(&mut buffer, pos + 16, free_inodes);
+        self.write_block(block, &buffer)
+    }
+
+    /// Number of blocks in `group` (the last group may be smaller)
+    fn blocks_in_group(&self, group: usize) -> usize {
+        let remaining = self.blocks_count - self.first_data_block - group as u32 * self.blocks_per_group;
+        remaining.min(self.blocks_per_group) as usize
+    }
+
+    /// Allocate a free block (preferably in `goal_group`) and fill it with zeros. \
+    /// The caller must hold the update lock (see `lock_update`).
+    fn allocate_block(&self, goal_group: usize) -> Result<u32, Errno> {
+        let mut bitmap = vec![0u8; self.block_size];
+        for i in 0..self.groups.len() {
+            let group = (goal_group + i) % self.groups.len();
+            if self.alloc_state.lock().groups[group].free_blocks == 0 {
+                continue;
+            }
+
+            self.read_block(self.groups[group].block_bitmap, &mut bitmap)?;
+            if let Some(bit) = find_zero_bit(&bitmap, 0, self.blocks_in_group(group)) {
+                set_bit(&mut bitmap, bit, true);
+                self.write_block(self.groups[group].block_bitmap, &bitmap)?;
+
+                {
+                    let mut state = self.alloc_state.lock();
+                    state.groups[group].free_blocks -= 1;
+                    state.free_blocks = state.free_blocks.saturating_sub(1);
+                }
+                self.write_counts(group)?;
+
+                let block = self.first_data_block + group as u32 * self.blocks_per_group + bit as u32;
+                self.write_block(block, &vec![0u8; self.block_size])?;
+                return Ok(block);
+            }
+        }
+
+        Err(Errno::ENOSPC)
+    }
+
+    /// Mark `block` as free. \
+    /// The caller must hold the update lock (see `lock_update`).
+    fn free_block(&self, block: u32) -> Result<(), Errno> {
+        if block < self.first_data_block || block >= self.blocks_count {
+            return Err(Errno::EIO);
+        }
+
+        let index = block - self.first_data_block;
+        let group = (index / self.blocks_per_group) as usize;
+        let bit = (index % self.blocks_per_group) as usize;
+
+        let mut bitmap = vec![0u8; self.block_size];
+        self.read_block(self.groups[group].block_bitmap, &mut bitmap)?;
+        if !get_bit(&bitmap, bit) {
+            return Err(Errno::EIO); // block is not in use
+        }
+        set_bit(&mut bitmap, bit, false);
+        self.write_block(self.groups[group].block_bitmap, &bitmap)?;
+
+        {
+            let mut state = self.alloc_state.lock();
+            state.groups[group].free_blocks += 1;
+            state.free_blocks += 1;
+        }
+        self.write_counts(group)
+    }
+
+    /// Allocate a free inode number (preferably in `goal_group`). \
+    /// The caller must hold the update lock (see `lock_update`).
+    fn allocate_inode(&self, goal_group: usize, is_dir: bool) -> Result<u32, Errno> {
+        let mut bitmap = vec![0u8; self.block_size];
+        for i in 0..self.groups.len() {
+            let group = (goal_group + i) % self.groups.len();
+            if self.alloc_state.lock().groups[group].free_inodes == 0 {
+                continue;
+            }
+
+            // Inodes below `first_inode` are reserved (e.g. root directory, journal)
+            let group_start = group as u32 * self.inodes_per_group;
+            let first_bit = (self.first_inode - 1).saturating_sub(group_start) as usize;
+            let bits = (self.inodes_count - group_start).min(self.inodes_per_group) as usize;
+
+            self.read_block(self.groups[group].inode_bitmap, &mut bitmap)?;
+            if let Some(bit) = find_zero_bit(&bitmap, first_bit, bits) {
+                set_bit(&mut bitmap, bit, true);
+                self.write_block(self.groups[group].inode_bitmap, &bitmap)?;
+
+                {
+                    let mut state = self.alloc_state.lock();
+                    let counts = &mut state.groups[group];
+                    counts.free_inodes -= 1;
+                    if is_dir {
+                        counts.used_dirs += 1;
+                    }
+                    state.free_inodes = state.free_inodes.saturating_sub(1);
+                }
+                self.write_counts(group)?;
+
+                return Ok(group_start + bit as u32 + 1);
+            }
+        }
+
+        Err(Errno::ENOSPC)
+    }
+
+    /// Mark the inode `number` as free. \
+    /// The caller must hold the update lock (see `lock_update`).
+    fn free_inode(&self, number: u32, is_dir: bool) -> Result<(), Errno> {
+        let group = self.inode_group(number);
+        let bit = ((number - 1) % self.inodes_per_group) as usize;
+
+        let mut bitmap = vec![0u8; self.block_size];
+        self.read_block(self.groups[group].inode_bitmap, &mut bitmap)?;
+        set_bit(&mut bitmap, bit, false);
+        self.write_block(self.groups[group].inode_bitmap, &bitmap)?;
+
+        {
+            let mut state = self.alloc_state.lock();
+            let counts = &mut state.groups[group];
+            counts.free_inodes += 1;
+            if is_dir {
+                counts.used_dirs = counts.used_dirs.saturating_sub(1);
+            }
+            state.free_inodes += 1;
+        }
+        self.write_counts(group)
+    }
+
+    /// Translate the `index`-th block of a file into the slot in the block pointers of the inode
+    /// and the indices within the indirect blocks (empty for direct blocks). \
+    /// Returns `Err(Errno::EINVAL)` if `index` is beyond the maximum file size.
+    fn block_path(&self, index: u64) -> Result<(usize, Vec<usize>), Errno> {
+        if index < DIRECT_BLOCKS as u64 {
+            return Ok((index as usize, Vec::new()));
+        }
+
+        let per_block = self.pointers_per_block();
+        let mut index = index - DIRECT_BLOCKS as u64;
+        let mut span = 1u64; // number of data blocks reachable via the pointer in the current slot
+        for level in 1..=INDIRECT_LEVELS {
+            span *= per_block;
+            if index < span {
+                let mut path = Vec::with_capacity(level);
+                let mut step = span;
+                for _ in 0..level {
+                    step /= per_block;
+                    path.push((index / step) as usize);
+                    index %= step;
+                }
+                return Ok((DIRECT_BLOCKS + level - 1, path));
+            }
+            index -= span;
+        }
+
+        Err(Errno::EINVAL)
+    }
+
+    /// Return the block holding the `index`-th block of the file `inode` (0 for a hole)
+    fn data_block(&self, inode: &Inode, index: u64) -> Result<u32, Errno> {
+        let (slot, path) = self.block_path(index)?;
+        let mut block = inode.block(slot);
+
+        let mut buffer = vec![0u8; if path.is_empty() { 0 } else { self.block_size }];
+        for offset in path {
+            if block == 0 {
+                break;
+            }
+            self.read_block(block, &mut buffer)?;
+            block = read_u32(&buffer, offset * 4);
+        }
+        Ok(block)
+    }
+
+    /// Return the block holding the `index`-th block of the file `inode`. Missing blocks (including
+    /// indirect blocks) are allocated. The caller must hold the update lock (see `lock_update`).
+    /// Afterwards, `inode` must be written back, since its block pointers and block count may have changed.
+    fn map_block(&self, inode: &mut Inode, index: u64) -> Result<u32, Errno> {
+        let (slot, path) = self.block_path(index)?;
+        let goal_group = self.inode_group(inode.number);
+
+        let mut block = inode.block(slot);
+        if block == 0 {
+            block = self.allocate_block(goal_group)?;
+            inode.set_block(slot, block);
+            inode.set_sectors(inode.sectors() + self.block_units());
+        }
+
+        let mut buffer = vec![0u8; self.block_size];
+        for offset in path {
+            self.read_block(block, &mut buffer)?;
+            let mut next = read_u32(&buffer, offset * 4);
+            if next == 0 {
+                next = self.allocate_block(goal_group)?;
+                write_u32(&mut buffer, offset * 4, next);
+                self.write_block(block, &buffer)?;
+                inode.set_sectors(inode.sectors() + self.block_units());
+            }
+            block = next;
+        }
+
+        Ok(block)
+    }
+
+    /// Free all blocks of the file `inode` with an index >= `first` (including indirect blocks, which are no
+    /// longer needed). The caller must hold the update lock and write `inode` back afterwards.
+    fn free_blocks(&self, inode: &mut Inode, first: u64) -> Result<(), Errno> {
+        let mut freed = 0;
+        for slot in (first.min(DIRECT_BLOCKS as u64) as usize)..DIRECT_BLOCKS {
+            let block = inode.block(slot);
+            if block != 0 {
+                self.free_block(block)?;
+                inode.set_block(slot, 0);
+                freed += 1;
+            }
+        }
+
+        let per_block = self.pointers_per_block();
+        let mut start = DIRECT_BLOCKS as u64; // index of the first block reachable via the current slot
+        let mut span = 1u64;
+        for level in 1..=INDIRECT_LEVELS {
+            span *= per_block;
+            let slot = DIRECT_BLOCKS + level - 1;
+            let block = inode.block(slot);
+            if block != 0 && first < start + span {
+                let tree_first = first.saturating_sub(start);
+                freed += self.free_tree(block, level as u32, tree_first)?;
+                if tree_first == 0 {
+                    inode.set_block(slot, 0);
+                }
+            }
+            start += span;
+        }
+
+        inode.set_sectors(inode.sectors().saturating_sub(freed * self.block_units()));
+        Ok(())
+    }
+
+    /// Free all data blocks with an index >= `first` (relative to the tree) reachable via the indirect `block`
+    /// with the given `level` (1 = single indirect). `block` itself is freed, if `first` is 0. \
+    /// Returns the number of freed blocks.
+    fn free_tree(&self, block: u32, level: u32, first: u64) -> Result<u32, Errno> {
+        let per_block = self.pointers_per_block();
+        let span = per_block.pow(level - 1); // number of data blocks reachable via each pointer
+        let mut buffer = vec![0u8; self.block_size];
+        self.read_block(block, &mut buffer)?;
+
+        let mut freed = 0;
+        for i in (first / span) as usize..per_block as usize {
+            let child = read_u32(&buffer, i * 4);
+            if child == 0 {
+                continue;
+            }
+
+            let child_first = first.saturating_sub(i as u64 * span);
+            if level == 1 {
+                self.free_block(child)?;
+                freed += 1;
+            } else {
+                freed += self.free_tree(child, level - 1, child_first)?;
+            }
+            if child_first == 0 {
+                write_u32(&mut buffer, i * 4, 0);
+            }
+        }
+
+        if first == 0 {
+            self.free_block(block)?;
+            freed += 1;
+        } else {
+            self.write_block(block, &buffer)?;
+        }
+        Ok(freed)
+    }
+
+    /// Fill the file `inode` with zeros from `offset` up to the end of the block containing `offset`
+    /// (new blocks are zeroed anyway, but the last block may contain garbage behind the end of file)
+    fn zero_block_tail(&self, inode: &Inode, offset: u64) -> Result<(), Errno> {
+        let pos = (offset % self.block_size as u64) as usize;
+        if pos == 0 {
+            return Ok(());
+        }
+
+        let block = self.data_block(inode, offset / self.block_size as u64)?;
+        if block != 0 {
+            let mut buffer = vec![0u8; self.block_size];
+            self.read_block(block, &mut buffer)?;
+            buffer[pos..].fill(0);
+            self.write_block(block, &buffer)?;
+        }
+        Ok(())
+    }
+
+    /// Drop a link to `inode` (after removing a directory entry referencing it).
+    /// The inode and its blocks are freed, when the last link is gone (objects still using it fail with ENOENT). \
+    /// The caller must hold the update lock (see `lock_update`).
+    fn release_link(&self, inode: &mut Inode) -> Result<(), Errno> {
+        // A directory is referenced by its entry and its own '.' entry
+        let links = if inode.is_dir() { 0 } else { inode.links().saturating_sub(1) };
+        inode.set_links(links);
+        inode.set_changed_time(now_secs());
+        if links > 0 {
+            return self.write_inode(inode);
+        }
+
+        if inode.has_data_blocks(self.block_units()) {
+            self.free_blocks(inode, 0)?;
+        }
+        self.release_xattr_block(inode)?;
+        inode.set_size(0);
+        inode.set_deleted_time(now_secs());
+        self.write_inode(inode)?;
+        self.free_inode(inode.number, inode.is_dir())
+    }
+
+    /// Drop the reference of `inode` to its extended attribute block (freeing it, if it is not shared)
+    fn release_xattr_block(&self, inode: &mut Inode) -> Result<(), Errno> {
+        let block = inode.file_acl();
+        if block == 0 {
+            return Ok(());
+        }
+
+        let mut buffer = vec![0u8; self.block_size];
+        self.read_block(block, &mut buffer)?;
+        let refcount = read_u32(&buffer, 4);
+        if read_u32(&buffer, 0) == XATTR_MAGIC && refcount > 1 {
+            write_u32(&mut buffer, 4, refcount - 1);
+            self.write_block(block, &buffer)?;
+        } else {
+            self.free_block(block)?;
+        }
+
+        inode.set_file_acl(0);
+        inode.set_sectors(inode.sectors().saturating_sub(self.block_units()));
+        Ok(())
+    }
+
+    /// Read all entries of the directory `dir` (including `.` and `..`)
+    fn dir_records(&self, dir: &Inode) -> Result<Vec<DirRecord>, Errno> {
+        let mut records = Vec::new();
+        let mut buffer = vec![0u8; self.block_size];
+
+        for index in 0..dir.size().div_ceil(self.block_size as u64) {
+            let block = self.data_block(dir, index)?;
+            if block == 0 {
+                continue;
+            }
+            self.read_block(block, &mut buffer)?;
+
+            let mut offset = 0;
+            while offset + DIR_ENTRY_HEADER <= self.block_size {
+                let (inode, rec_len, name_len) = self.entry_header(&buffer, offset)?;
+                if inode != 0 {
+                    let name = &buffer[offset + DIR_ENTRY_HEADER..offset + DIR_ENTRY_HEADER + name_len];
+                    records.push(DirRecord {
+                        inode,
+                        name: String::from_utf8_lossy(name).into_owned(),
+                        file_type: if self.filetype_feature { buffer[offset + 7] } else { FT_UNKNOWN },
+                        block: index,
+                        offset,
+                    });
+                }
+                offset += rec_len;
+            }
+        }
+
+        Ok(records)
+    }
+
+    /// Parse and check the header of the directory entry at `offset` in the directory block `buffer`. \
+    /// Returns inode number, record length and name length.
+    fn entry_header(&self, buffer: &[u8], offset: usize) -> Result<(u32, usize, usize), Errno> {
+        let inode = read_u32(buffer, offset);
+        let rec_len = read_u16(buffer, offset + 4) as usize;
+        let name_len = buffer[offset + 6] as usize;
+
+        if rec_len < DIR_ENTRY_HEADER || rec_len % 4 != 0 || offset + rec_len > self.block_size || DIR_ENTRY_HEADER + name_len > rec_len {
+            return Err(Errno::EIO);
+        }
+        Ok((inode, rec_len, name_len))
+    }
+
+    /// Find the entry `name` in the directory `dir`
+    fn find_entry(&self, dir: &Inode, name: &str) -> Result<Option<DirRecord>, Errno> {
+        Ok(self.dir_records(dir)?.into_iter().find(|record| record.name == name))
+    }
+
+    /// Check if the directory `dir` contains only the entries `.` and `..`
+    fn is_empty_dir(&self, dir: &Inode) -> Result<bool, Errno> {
+        Ok(self.dir_records(dir)?.iter().all(|record| record.is_dot_entry()))
+    }
+
+    /// Add the entry `name` referencing `inode` to the directory `dir`. A new block is appended to the directory,
+    /// if none of its blocks has enough space left. \
+    /// The caller must hold the update lock (see `lock_update`).
+    fn add_dir_entry(&self, dir: &mut Inode, name: &str, inode: u32, file_type: u8) -> Result<(), Errno> {
+        let needed = entry_size(name.len());
+        let mut buffer = vec![0u8; self.block_size];
+
+        if dir.flags() & INDEX_FL != 0 {
+            dir.set_flags(dir.flags() & !INDEX_FL);
+            self.write_inode(dir)?;
+        }
+
+        let block_count = dir.size().div_ceil(self.block_size as u64);
+        for index in 0..block_count {
+            let block = self.data_block(dir, index)?;
+            if block == 0 {
+                continue;
+            }
+            self.read_block(block, &mut buffer)?;
+
+            // Use the space behind an entry (or an unused entry), if it is large enough
+            let mut offset = 0;
+            while offset + DIR_ENTRY_HEADER <= self.block_size {
+                let (entry_inode, rec_len, name_len) = self.entry_header(&buffer, offset)?;
+                let used = if entry_inode == 0 { 0 } else { entry_size(name_len) };
+                if rec_len >= used + needed {
+                    if used > 0 {
+                        write_u16(&mut buffer, offset + 4, used as u16);
+                    }
+                    self.write_entry(&mut buffer, offset + used, inode, rec_len - used, name, file_type);
+                    return self.write_block(block, &buffer);
+                }
+                offset += rec_len;
+            }
+        }
+
+        // No space left -> append a new block containing only the new entry
+        let block = self.map_block(dir, block_count)?;
+        buffer.fill(0);
+        self.write_entry(&mut buffer, 0, inode, self.block_size, name, file_type);
+        self.write_block(block, &buffer)?;
+        dir.set_size((block_count + 1) * self.block_size as u64);
+        self.write_inode(dir)
+    }
+
+    /// Remove the entry described by `record` from the directory `dir`
+    fn remove_dir_entry(&self, dir: &Inode, record: &DirRecord) -> Result<(), Errno> {
+        let block = self.data_block(dir, record.block)?;
+        let mut buffer = vec![0u8; self.block_size];
+        self.read_block(block, &mut buffer)?;
+
+        if record.offset == 0 {
+            // The first entry of a block cannot be merged and is marked as unused instead
+            write_u32(&mut buffer, 0, 0);
+        } else {
+            // Merge the entry into its predecessor
+            let mut offset = 0;
+            loop {
+                let (_, rec_len, _) = self.entry_header(&buffer, offset)?;
+                if offset + rec_len == record.offset {
+                    let (_, removed_len, _) = self.entry_header(&buffer, record.offset)?;
+                    write_u16(&mut buffer, offset + 4, (rec_len + removed_len) as u16);
+                    break;
+                }
+                offset += rec_len;
+                if offset >= record.offset {
+                    return Err(Errno::EIO);
+                }
+            }
+        }
+
+        self.write_block(block, &buffer)
+    }
+
+    /// Let the entry described by `record` in the directory `dir` reference `inode` (e.g. `..` after moving a directory)
+    fn set_dir_entry(&self, dir: &Inode, record: &DirRecord, inode: u32, file_type: u8) -> Result<(), Errno> {
+        let block = self.data_block(dir, record.block)?;
+        let mut buffer = vec![0u8; self.block_size];
+        self.read_block(block, &mut buffer)?;
+        write_u32(&mut buffer, record.offset, inode);
+        if self.filetype_feature {
+            buffer[record.offset + 7] = file_type;
+        }
+        self.write_block(block, &buffer)
+    }
+
+    /// Write a directory entry into the directory block `buffer`
+    fn write_entry(&self, buffer: &mut [u8], offset: usize, inode: u32, rec_len: usize, name: &str, file_type: u8) {
+        write_u32(buffer, offset, inode);
+        write_u16(buffer, offset + 4, rec_len as u16);
+        buffer[offset + 6] = name.len() as u8;
+        buffer[offset + 7] = if self.filetype_feature { file_type } else { 0 };
+        buffer[offset + DIR_ENTRY_HEADER..offset + DIR_ENTRY_HEADER + name.len()].copy_from_slice(name.as_bytes());
+    }
+
+    fn named_object(self: &Arc<Self>, number: u32) -> Result<NamedObject, Errno> {
+        let inode = self.read_inode(number)?;
+        if inode.is_dir() {
+            Ok((Arc::new(Dir::new(self.clone(), number, inode.generation())) as Arc<dyn DirectoryObject>).into())
+        } else {
+            Ok((Arc::new(File::new(self.clone(), number, inode.generation())) as Arc<dyn FileObject>).into())
+        }
+    }
+}
+
+/// An on-disk inode together with its number. Fields not interpreted here are kept unchanged, when writing it back.
+struct Inode {
+    number: u32,
+    raw: Vec<u8>,
+}
+
+impl Inode {
+    /// Create a new inode with the given `mode` (type and permission bits), owned by `uid` and `gid`
+    fn new(number: u32, size: usize, mode: u16, uid: u32, gid: u32) -> Inode {
+        let mut inode = Inode {
+            number,
+            raw: vec![0u8; size],
+        };
+        let now = now_secs();
+        inode.set_mode(mode);
+        inode.set_owner(uid, gid);
+        inode.set_accessed_time(now);
+        inode.set_changed_time(now);
+        inode.set_modified_time(now);
+        inode.set_links(if mode & S_IFMT == S_IFDIR { 2 } else { 1 });
+        inode
+    }
+
+    fn mode(&self) -> u16 {
+        read_u16(&self.raw, 0)
+    }
+
+    fn set_mode(&mut self, mode: u16) {
+        write_u16(&mut self.raw, 0, mode);
+    }
+
+    fn is_dir(&self) -> bool {
+        self.mode() & S_IFMT == S_IFDIR
+    }
+
+    fn is_regular(&self) -> bool {
+        self.mode() & S_IFMT == S_IFREG
+    }
+
+    fn is_symlink(&self) -> bool {
+        self.mode() & S_IFMT == S_IFLNK
+    }
+
+    /// Owner and group are split into a lower and an upper 16 bit half
+    fn uid(&self) -> u32 {
+        read_u16(&self.raw, 2) as u32 | (read_u16(&self.raw, 120) as u32) << 16
+    }
+
+    fn gid(&self) -> u32 {
+        read_u16(&self.raw, 24) as u32 | (read_u16(&self.raw, 122) as u32) << 16
+    }
+
+    fn set_owner(&mut self, uid: u32, gid: u32) {
+        write_u16(&mut self.raw, 2, uid as u16);
+        write_u16(&mut self.raw, 120, (uid >> 16) as u16);
+        write_u16(&mut self.raw, 24, gid as u16);
+        write_u16(&mut self.raw, 122, (gid >> 16) as u16);
+    }
+
+    /// The upper 32 bits of the size of regular files are stored in `i_size_high` (large_file feature)
+    fn size(&self) -> u64 {
+        let low = read_u32(&self.raw, 4) as u64;
+        if self.is_regular() {
+            low | (read_u32(&self.raw, 108) as u64) << 32
+        } else {
+            low
+        }
+    }
+
+    fn set_size(&mut self, size: u64) {
+        write_u32(&mut self.raw, 4, size as u32);
+        if self.is_regular() {
+            write_u32(&mut self.raw, 108, (size >> 32) as u32);
+        }
+    }
+
+    /// Timestamps are stored in seconds since 1.1.1970
+    fn accessed_time(&self) -> u32 {
+        read_u32(&self.raw, 8)
+    }
+
+    fn set_accessed_time(&mut self, time: u32) {
+        write_u32(&mut self.raw, 8, time);
+    }
+
+    fn changed_time(&self) -> u32 {
+        read_u32(&self.raw, 12)
+    }
+
+    fn set_changed_time(&mut self, time: u32) {
+        write_u32(&mut self.raw, 12, time);
+    }
+
+    fn modified_time(&self) -> u32 {
+        read_u32(&self.raw, 16)
+    }
+
+    fn set_modified_time(&mut self, time: u32) {
+        write_u32(&mut self.raw, 16, time);
+    }
+
+    fn set_deleted_time(&mut self, time: u32) {
+        write_u32(&mut self.raw, 20, time);
+    }
+
+    /// Update modification and change time (after modifying the content)
+    fn touch(&mut self) {
+        let now = now_secs();
+        self.set_modified_time(now);
+        self.set_changed_time(now);
+    }
+
+    /// Set the permission bits (e.g. 0o755)
+    fn set_permissions(&mut self, permissions: u32) {
+        self.set_mode((self.mode() & !PERMISSIONS_MASK) | (permissions as u16 & PERMISSIONS_MASK));
+        self.set_changed_time(now_secs());
+    }
+
+    fn change_owner(&mut self, uid: u32, gid: u32) {
+        self.set_owner(uid, gid);
+        self.set_changed_time(now_secs());
+    }
+
+    /// Set access and modification times (given in ms), `None` leaves a timestamp unchanged
+    fn set_times(&mut self, accessed: Option<u64>, modified: Option<u64>) {
+        if let Some(accessed) = accessed {
+            self.set_accessed_time((accessed / 1000) as u32);
+        }
+        if let Some(modified) = modified {
+            self.set_modified_time((modified / 1000) as u32);
+        }
+        self.set_changed_time(now_secs());
+    }
+
+    fn links(&self) -> u16 {
+        read_u16(&self.raw, 26)
+    }
+
+    fn set_links(&mut self, links: u16) {
+        write_u16(&mut self.raw, 26, links);
+    }
+
+    /// Incremented each time the inode number is used for a new object
+    fn generation(&self) -> u32 {
+        read_u32(&self.raw, 100)
+    }
+
+    fn set_generation(&mut self, generation: u32) {
+        write_u32(&mut self.raw, 100, generation);
+    }
+
+    /// Number of allocated 512 byte units (including indirect and extended attribute blocks)
+    fn sectors(&self) -> u32 {
+        read_u32(&self.raw, 28)
+    }
+
+    fn set_sectors(&mut self, sectors: u32) {
+        write_u32(&mut self.raw, 28, sectors);
+    }
+
+    fn flags(&self) -> u32 {
+        read_u32(&self.raw, 32)
+    }
+
+    fn set_flags(&mut self, flags: u32) {
+        write_u32(&mut self.raw, 32, flags);
+    }
+
+    fn block(&self, slot: usize) -> u32 {
+        read_u32(&self.raw, 40 + slot * 4)
+    }
+
+    fn set_block(&mut self, slot: usize, block: u32) {
+        write_u32(&mut self.raw, 40 + slot * 4, block);
+    }
+
+    /// Block containing the extended attributes
+    fn file_acl(&self) -> u32 {
+        read_u32(&self.raw, 104)
+    }
+
+    fn set_file_acl(&mut self, block: u32) {
+        write_u32(&mut self.raw, 104, block);
+    }
+
+    /// Check if the block pointers reference data blocks. Device files store the device number there
+    /// and fast symlinks the target (they have no blocks, except for an extended attribute block).
+    fn has_data_blocks(&self, block_units: u32) -> bool {
+        match self.mode() & S_IFMT {
+            S_IFREG | S_IFDIR => true,
+            S_IFLNK => {
+                let xattr_units = if self.file_acl() != 0 { block_units } else { 0 };
+                self.sectors() > xattr_units
+            }
+            _ => false,
+        }
+    }
+
+    fn stat(&self) -> Stat {
+        let mode = match self.mode() & S_IFMT {
+            S_IFDIR => MODE_DIR,
+            S_IFLNK => MODE_LINK,
+            S_IFCHR => MODE_CHAR_DEVICE,
+            S_IFBLK => MODE_BLOCK_DEVICE,
+            S_IFIFO => MODE_FIFO,
+            _ => MODE_FILE,
+        };
+
+        Stat {
+            mode: Mode::new(mode).with_permissions((self.mode() & PERMISSIONS_MASK) as u32),
+            size: self.size() as usize,
+            blocks: self.sectors() as usize,
+            // ext2 has no creation time, the time of the last inode change comes closest
+            created_time: self.changed_time() as u64 * 1000,
+            modified_time: self.modified_time() as u64 * 1000,
+            accessed_time: self.accessed_time() as u64 * 1000,
+            uid: self.uid(),
+            gid: self.gid(),
+        }
+    }
+}
+
+/// A directory entry, together with its position within the directory
+struct DirRecord {
+    inode: u32,
+    name: String,
+    file_type: u8,
+    block: u64,
+    offset: usize,
+}
+
+impl DirRecord {
+    fn is_dot_entry(&self) -> bool {
+        self.name == "." || self.name == ".."
+    }
+}
+
+pub struct Dir {
+    volume: Arc<Volume>,
+    inode: u32,
+    generation: u32,
+}
+
+impl Dir {
+    fn new(volume: Arc<Volume>, inode: u32, generation: u32) -> Dir {
+        Dir { volume, inode, generation }
+    }
+
+    /// Create the entry `name` referencing a new inode with `mode` (type and permission bits). \
+    /// `init` fills in the content of the new inode (e.g. the first block of a directory).
+    fn create(
+        &self,
+        name: &str,
+        mode: u16,
+        init: impl FnOnce(&Volume, &mut Inode) -> Result<(), Errno>,
+    ) -> Result<NamedObject, Errno> {
+        let volume = &self.volume;
+        volume.check_writable()?;
+        check_name(name)?;
+
+        let update = volume.lock_update();
+        let mut dir = volume.object_inode(self.inode, self.generation)?;
+        if volume.find_entry(&dir, name)?.is_some() {
+            return Err(Errno::EEXIST);
+        }
+
+        let is_dir = mode & S_IFMT == S_IFDIR;
+        let number = volume.allocate_inode(volume.inode_group(self.inode), is_dir)?;
+        let (uid, gid) = access::credentials();
+        let generation = volume.read_inode(number)?.generation().wrapping_add(1);
+        let mut inode = Inode::new(number, volume.inode_size, mode, uid, gid);
+        inode.set_generation(generation);
+
+        let result = init(volume, &mut inode)
+            .and_then(|_| volume.write_inode(&inode))
+            .and_then(|_| volume.add_dir_entry(&mut dir, name, number, entry_file_type(mode)));
+        if let Err(error) = result {
+            // Give everything back, since no entry is referencing the new inode
+            if inode.has_data_blocks(volume.block_units()) {
+                volume.free_blocks(&mut inode, 0)?;
+            }
+            volume.free_inode(number, is_dir)?;
+            return Err(error);
+        }
+
+        // The '..' entry of a new directory is another link to this directory
+        if is_dir {
+            dir.set_links(dir.links() + 1);
+        }
+        dir.touch();
+        volume.write_inode(&dir)?;
+        drop(update);
+
+        volume.named_object(number)
+    }
+
+    /// Remove the entry `name`, which must (`dir` = true) or must not be a directory
+    fn remove(&self, name: &str, dir: bool) -> Result<(), Errno> {
+        let volume = &self.volume;
+        volume.check_writable()?;
+
+        let _update = volume.lock_update();
+        let mut parent = volume.object_inode(self.inode, self.generation)?;
+        let record = volume
+            .find_entry(&parent, name)?
+            .filter(|record| !record.is_dot_entry())
+            .ok_or(Errno::ENOENT)?;
+
+        let mut inode = volume.read_inode(record.inode)?;
+        match (dir, inode.is_dir()) {
+            (false, true) => return Err(Errno::EISDIR),
+            (true, false) => return Err(Errno::ENOTDIR),
+            (true, true) if !volume.is_empty_dir(&inode)? => return Err(Errno::ENOTEMPTY),
+            _ => {}
+        }
+
+        volume.remove_dir_entry(&parent, &record)?;
+        volume.release_link(&mut inode)?;
+
+        // The '..' entry of the removed directory no longer references this directory
+        if dir {
+            parent.set_links(parent.links().saturating_sub(1));
+        }
+        parent.touch();
+        volume.write_inode(&parent)
+    }
+
+    /// Update the inode of this directory (serialized with all other modifications)
+    fn update(&self, update: impl FnOnce(&mut Inode)) -> Result<(), Errno> {
+        self.volume.check_writable()?;
+        let _update = self.volume.lock_update();
+        self.volume.update_inode(self.inode, self.generation, update)
+    }
+}
+
+impl DirectoryObject for Dir {
+    fn lookup(&self, name: &str) -> Result<NamedObject, Errno> {
+        let dir = self.volume.object_inode(self.inode, self.generation)?;
+        match self.volume.find_entry(&dir, name)? {
+            Some(record) => self.volume.named_object(record.inode),
+            None => Err(Errno::ENOENT),
+        }
+    }
+
+    fn create_file(&self, name: &str, mode: Mode) -> Result<NamedObject, Errno> {
+        self.create(name, S_IFREG | mode.permissions() as u16, |_, _| Ok(()))
+    }
+
+    fn create_dir(&self, name: &str, mode: Mode) -> Result<NamedObject, Errno> {
+        let parent = self.inode;
+        self.create(name, S_IFDIR | mode.permissions() as u16, |volume, inode| {
+            // Every directory starts with the entries '.' and '..'
+            let block = volume.map_block(inode, 0)?;
+            let mut buffer = vec![0u8; volume.block_size];
+            let dot_len = entry_size(1);
+            volume.write_entry(&mut buffer, 0, inode.number, dot_len, ".", FT_DIR);
+            volume.write_entry(&mut buffer, dot_len, parent, volume.block_size - dot_len, "..", FT_DIR);
+            volume.write_block(block, &buffer)?;
+            inode.set_size(volume.block_size as u64);
+            Ok(())
+        })
+    }
+
+    fn stat(&self) -> Result<Stat, Errno> {
+        Ok(self.volume.object_inode(self.inode, self.generation)?.stat())
+    }
+
+    fn readdir(&self, index: usize) -> Result<Option<DirEntry>, Errno> {
+        let dir = self.volume.object_inode(self.inode, self.generation)?;
+        let record = self
+            .volume
+            .dir_records(&dir)?
+            .into_iter()
+            .filter(|record| !record.is_dot_entry())
+            .nth(index);
+
+        match record {
+            Some(record) => {
+                // Without the filetype feature, the type is only known from the inode
+                let file_type = match record.file_type {
+                    FT_UNKNOWN => file_type_of_mode(self.volume.read_inode(record.inode)?.mode()),
+                    file_type => entry_to_file_type(file_type),
+                };
+                Ok(Some(DirEntry {
+                    inode: record.inode as u64,
+                    file_type,
+                    name: record.name,
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn create_symlink(&self, name: &str, target: &str) -> Result<NamedObject, Errno> {
+        if target.is_empty() || target.len() >= self.volume.block_size {
+            return Err(Errno::EINVAL);
+        }
+
+        self.create(name, S_IFLNK | 0o777, |volume, inode| {
+            if target.len() < FAST_SYMLINK_SIZE {
+                inode.raw[40..40 + target.len()].copy_from_slice(target.as_bytes());
+            } else {
+                let block = volume.map_block(inode, 0)?;
+                let mut buffer = vec![0u8; volume.block_size];
+                buffer[..target.len()].copy_from_slice(target.as_bytes());
+                volume.write_block(block, &buffer)?;
+            }
+            inode.set_size(target.len() as u64);
+            Ok(())
+        })
+    }
+
+    fn unlink(&self, name: &str) -> Result<(), Errno> {
+        self.remove(name, false)
+    }
+
+    fn rmdir(&self, name: &str) -> Result<(), Errno> {
+        self.remove(name, true)
+    }
+
+    fn rename(&self, old_name: &str, new_dir: &Arc<dyn DirectoryObject>, new_name: &str) -> Result<(), Errno> {
+        // Both directories must belong to the same ext2 volume
+        let new_dir = new_dir.as_any().downcast_ref::<Dir>().ok_or(Errno::EXDEV)?;
+        if !Arc::ptr_eq(&self.volume, &new_dir.volume) {
+            return Err(Errno::EXDEV);
+        }
+
+        let volume = &self.volume;
+        volume.check_writable()?;
+        check_name(new_name)?;
+
+        let _update = volume.lock_update();
+        let old_parent = volume.object_inode(self.inode, self.generation)?;
+        let record = volume
+            .find_entry(&old_parent, old_name)?
+            .filter(|record| !record.is_dot_entry())
+            .ok_or(Errno::ENOENT)?;
+        let moved = volume.read_inode(record.inode)?;
+        let file_type = entry_file_type(moved.mode());
+
+        // Add the new entry (or let an existing one reference the moved inode)
+        let mut new_parent = volume.object_inode(new_dir.inode, new_dir.generation)?;
+        match volume.find_entry(&new_parent, new_name)? {
+            Some(target) if target.inode == record.inode => return Ok(()),
+            Some(target) => {
+                let mut replaced = volume.read_inode(target.inode)?;
+                match (moved.is_dir(), replaced.is_dir()) {
+                    (false, true) => return Err(Errno::EISDIR),
+                    (true, false) => return Err(Errno::ENOTDIR),
+                    (true, true) if !volume.is_empty_dir(&replaced)? => return Err(Errno::ENOTEMPTY),
+                    _ => {}
+                }
+
+                volume.set_dir_entry(&new_parent, &target, record.inode, file_type)?;
+                if replaced.is_dir() {
+                    new_parent.set_links(new_parent.links().saturating_sub(1));
+                }
+                volume.release_link(&mut replaced)?;
+            }
+            None => volume.add_dir_entry(&mut new_parent, new_name, record.inode, file_type)?,
+        }
+        new_parent.touch();
+        volume.write_inode(&new_parent)?;
+
+        // Remove the old entry (read again, since both parents may be the same directory)
+        let mut old_parent = volume.object_inode(self.inode, self.generation)?;
+        let record = volume.find_entry(&old_parent, old_name)?.ok_or(Errno::EIO)?;
+        volume.remove_dir_entry(&old_parent, &record)?;
+
+        // A moved directory has to reference its new parent with '..'
+        if moved.is_dir() && self.inode != new_dir.inode {
+            let dot_dot = volume.find_entry(&moved, "..")?.ok_or(Errno::EIO)?;
+            volume.set_dir_entry(&moved, &dot_dot, new_dir.inode, FT_DIR)?;
+            old_parent.set_links(old_parent.links().saturating_sub(1));
+            volume.update_inode(new_dir.inode, new_dir.generation, |inode| inode.set_links(inode.links() + 1))?;
+        }
+        old_parent.touch();
+        volume.write_inode(&old_parent)
+    }
+
+    fn chmod(&self, permissions: u32) -> Result<(), Errno> {
+        self.update(|inode| inode.set_permissions(permissions))
+    }
+
+    fn chown(&self, uid: u32, gid: u32) -> Result<(), Errno> {
+        self.update(|inode| inode.change_owner(uid, gid))
+    }
+
+    fn utimes(&self, accessed: Option<u64>, modified: Option<u64>) -> Result<(), Errno> {
+        self.update(|inode| inode.set_times(accessed, modified))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Debug for Dir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ext2Dir").field("inode", &self.inode).finish()
+    }
+}
+
+/// A file, symbolic link or special file (device, FIFO or socket, whose content cannot be accessed)
+pub struct File {
+    volume: Arc<Volume>,
+    inode: u32,
+    generation: u32, // an open file may be removed while it is in use (see `Volume::object_inode`)
+}
+
+impl File {
+    fn new(volume: Arc<Volume>, inode: u32, generation: u32) -> File {
+        File { volume, inode, generation }
+    }
+
+    /// Read the inode of this file, which must still exist and be a regular file
+    fn regular_inode(&self) -> Result<Inode, Errno> {
+        let inode = self.volume.object_inode(self.inode, self.generation)?;
+        if !inode.is_regular() {
+            return Err(Errno::EINVAL);
+        }
+        Ok(inode)
+    }
+
+    /// Maximum size of a regular file (2 GiB without the large_file feature; the block pointers
+    /// of an inode limit the size as well)
+    fn max_size(&self) -> u64 {
+        let per_block = self.volume.pointers_per_block();
+        let blocks = DIRECT_BLOCKS as u64 + per_block + per_block.pow(2) + per_block.pow(3);
+        let size = blocks * self.volume.block_size as u64;
+        if self.volume.large_file_feature { size } else { size.min(i32::MAX as u64) }
+    }
+
+    /// Write `buf` at `offset` into the file `inode`, allocating missing blocks. \
+    /// The caller must hold the update lock and write `inode` back afterwards.
+    fn write_blocks(&self, inode: &mut Inode, buf: &[u8], offset: u64) -> Result<(), Errno> {
+        let volume = &self.volume;
+        let block_size = volume.block_size;
+        let mut buffer = vec![0u8; block_size];
+
+        let mut written = 0;
+        while written < buf.len() {
+            let pos = offset + written as u64;
+            let block_offset = (pos % block_size as u64) as usize;
+            let len = (block_size - block_offset).min(buf.len() - written);
+            let block = volume.map_block(inode, pos / block_size as u64)?;
+
+            if len == block_size {
+                volume.write_block(block, &buf[written..written + len])?;
+            } else {
+                volume.read_block(block, &mut buffer)?;
+                buffer[block_offset..block_offset + len].copy_from_slice(&buf[written..written + len]);
+                volume.write_block(block, &buffer)?;
+            }
+            written += len;
+        }
+
+        Ok(())
+    }
+}
+
+impl FileObject for File {
+    fn stat(&self) -> Result<Stat, Errno> {
+        Ok(self.volume.object_inode(self.inode, self.generation)?.stat())
+    }
+
+    fn read(&self, buf: &mut [u8], offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        let volume = &self.volume;
+        let inode = self.regular_inode()?;
+        let size = inode.size();
+        let offset = offset as u64;
+        if offset >= size {
+            return Ok(0);
+        }
+
+        let len = (buf.len() as u64).min(size - offset) as usize;
+        let block_size = volume.block_size;
+        let mut buffer = vec![0u8; block_size];
+        let mut read = 0;
+        while read < len {
+            let pos = offset + read as u64;
+            let block_offset = (pos % block_size as u64) as usize;
+            let chunk = (block_size - block_offset).min(len - read);
+
+            // Holes (sparse files) read as zeros
+            match volume.data_block(&inode, pos / block_size as u64)? {
+                0 => buf[read..read + chunk].fill(0),
+                block => {
+                    volume.read_block(block, &mut buffer)?;
+                    buf[read..read + chunk].copy_from_slice(&buffer[block_offset..block_offset + chunk]);
+                }
+            }
+            read += chunk;
+        }
+
+        Ok(len)
+    }
+
+    fn write(&self, buf: &[u8], offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.volume.check_writable()?;
+        let offset = offset as u64;
+        let end = offset.checked_add(buf.len() as u64).ok_or(Errno::EINVAL)?;
+        if end > self.max_size() {
+            return Err(Errno::EINVAL);
+        }
+
+        let _update = self.volume.lock_update();
+        let mut inode = self.regular_inode()?;
+        let size = inode.size();
+
+        // Writing behind the end of file: the skipped part reads as zeros (blocks in between remain holes)
+        if offset > size {
+            self.volume.zero_block_tail(&inode, size)?;
+        }
+
+        // The inode is written back even if writing fails, since blocks may have been allocated already
+        let result = self.write_blocks(&mut inode, buf, offset);
+        if result.is_ok() {
+            inode.set_size(size.max(end));
+        }
+        inode.touch();
+        self.volume.write_inode(&inode)?;
+
+        result.map(|_| buf.len())
+    }
+
+    fn truncate(&self, size: usize) -> Result<(), Errno> {
+        self.volume.check_writable()?;
+        let size = size as u64;
+        if size > self.max_size() {
+            return Err(Errno::EINVAL);
+        }
+
+        let _update = self.volume.lock_update();
+        let mut inode = self.regular_inode()?;
+        let old_size = inode.size();
+
+        if size < old_size {
+            // Shrinking: give back all blocks behind the new end of file
+            let result = self.volume.free_blocks(&mut inode, size.div_ceil(self.volume.block_size as u64));
+            if let Err(error) = result {
+                self.volume.write_inode(&inode)?;
+                return Err(error);
+            }
+        }
+
+        // Growing or shrinking: the last block has to contain zeros behind the end of file
+        self.volume.zero_block_tail(&inode, size.min(old_size))?;
+
+        inode.set_size(size);
+        inode.touch();
+        self.volume.write_inode(&inode)
+    }
+
+    fn readlink(&self) -> Result<String, Errno> {
+        let volume = &self.volume;
+        let inode = volume.object_inode(self.inode, self.generation)?;
+        if !inode.is_symlink() {
+            return Err(Errno::EINVAL);
+        }
+
+        let size = inode.size() as usize;
+        if size >= volume.block_size {
+            return Err(Errno::EIO);
+        }
+
+        let target = if inode.has_data_blocks(volume.block_units()) {
+            let mut buffer = vec![0u8; volume.block_size];
+            volume.read_block(inode.block(0), &mut buffer)?;
+            buffer.truncate(size);
+            buffer
+        } else {
+            // Fast symlink: target is stored in the block pointers
+            if size >= FAST_SYMLINK_SIZE {
+                return Err(Errno::EIO);
+            }
+            inode.raw[40..40 + size].to_vec()
+        };
+
+        String::from_utf8(target).map_err(|_| Errno::EIO)
+    }
+
+    fn chmod(&self, permissions: u32) -> Result<(), Errno> {
+        self.volume.check_writable()?;
+        let _update = self.volume.lock_update();
+        self.volume.update_inode(self.inode, self.generation, |inode| inode.set_permissions(permissions))
+    }
+
+    fn chown(&self, uid: u32, gid: u32) -> Result<(), Errno> {
+        self.volume.check_writable()?;
+        let _update = self.volume.lock_update();
+        self.volume.update_inode(self.inode, self.generation, |inode| inode.change_owner(uid, gid))
+    }
+
+    fn utimes(&self, accessed: Option<u64>, modified: Option<u64>) -> Result<(), Errno> {
+        self.volume.check_writable()?;
+        let _update = self.volume.lock_update();
+        self.volume.update_inode(self.inode, self.generation, |inode| inode.set_times(accessed, modified))
+    }
+}
+
+impl fmt::Debug for File {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ext2File").field("inode", &self.inode).finish()
+    }
+}
+
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Helper functions for bitmaps, directory entries and names.              ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+
+fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([buf[offset], buf[offset + 1], buf[offset + 2], buf[offset + 3]])
+}
+
+fn write_u16(buf: &mut [u8], offset: usize, value: u16) {
+    buf[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+fn write_u32(buf: &mut [u8], offset: usize, value: u32) {
+    buf[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// Current time in seconds since 1.1.1970 (as used by ext2 timestamps)
+fn now_secs() -> u32 {
+    (stat::now() / 1000) as u32
+}
+
+fn get_bit(bitmap: &[u8], bit: usize) -> bool {
+    bitmap[bit / 8] & (1 << (bit % 8)) != 0
+}
+
+fn set_bit(bitmap: &mut [u8], bit: usize, value: bool) {
+    if value {
+        bitmap[bit / 8] |= 1 << (bit % 8);
+    } else {
+        bitmap[bit / 8] &= !(1 << (bit % 8));
+    }
+}
+
+/// Find the first cleared bit in `bitmap` within `start..end`
+fn find_zero_bit(bitmap: &[u8], start: usize, end: usize) -> Option<usize> {
+    (start..end).find(|&bit| !get_bit(bitmap, bit))
+}
+
+/// Size of a directory entry with a name of `name_len` bytes (entries are aligned to 4 bytes)
+fn entry_size(name_len: usize) -> usize {
+    (DIR_ENTRY_HEADER + name_len).next_multiple_of(4)
+}
+
+/// File type stored in the directory entry of an inode with `mode`
+fn entry_file_type(mode: u16) -> u8 {
+    match mode & S_IFMT {
+        S_IFREG => FT_REG_FILE,
+        S_IFDIR => FT_DIR,
+        S_IFCHR => FT_CHRDEV,
+        S_IFBLK => FT_BLKDEV,
+        S_IFIFO => FT_FIFO,
+        S_IFSOCK => FT_SOCK,
+        S_IFLNK => FT_SYMLINK,
+        _ => FT_UNKNOWN,
+    }
+}
+
+fn entry_to_file_type(file_type: u8) -> FileType {
+    match file_type {
+        FT_DIR => FileType::Directory,
+        FT_CHRDEV => FileType::CharDevice,
+        FT_BLKDEV => FileType::BlockDevice,
+        FT_FIFO => FileType::Fifo,
+        FT_SYMLINK => FileType::Link,
+        _ => FileType::Regular,
+    }
+}
+
+fn file_type_of_mode(mode: u16) -> FileType {
+    entry_to_file_type(entry_file_type(mode))
+}
+
+/// Check if `name` is allowed as name of a directory entry
+fn check_name(name: &str) -> Result<(), Errno> {
+    if name.is_empty() || name == "." || name == ".." || name.len() > MAX_NAME_LENGTH || name.contains(['/', '\0']) {
+        return Err(Errno::EINVAL);
+    }
+    Ok(())
+}
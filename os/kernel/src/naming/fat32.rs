@@ -0,0 +1,1022 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: fat32                                                           ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ FAT32 file system working on any block device (disk or partition).      ║
+   ║ Supports reading, writing, creating files and directories as well as    ║
+   ║ long file names (VFAT). Deleting entries is not supported yet.          ║
+   ║                                                                         ║
+   ║ Layout of a FAT32 volume:                                               ║
+   ║   - reserved sectors (boot sector with BPB, FSInfo sector, ...)         ║
+   ║   - one or more copies of the file allocation table (FAT)               ║
+   ║   - data region, divided into clusters (starting with cluster 2)        ║
+   ║ Directories are cluster chains of 32 byte entries. A long file name is  ║
+   ║ stored in additional entries, placed right before the short entry.      ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt;
+use core::result::Result;
+use log::info;
+use spin::Mutex;
+
+use super::stat::{Mode, Stat, MODE_DIR, MODE_FILE};
+use super::traits::{DirectoryObject, FileObject, FileSystem, NamedObject};
+use super::update_lock::{UpdateGuard, UpdateLock};
+use crate::storage::block::BlockDevice;
+use naming::shared_types::{DirEntry, FileType, OpenOptions};
+use syscall::return_vals::Errno;
+
+/// Attributes of a directory entry
+const ATTR_READ_ONLY: u8 = 0x01;
+const ATTR_HIDDEN: u8 = 0x02;
+const ATTR_SYSTEM: u8 = 0x04;
+const ATTR_VOLUME_ID: u8 = 0x08;
+const ATTR_DIRECTORY: u8 = 0x10;
+const ATTR_ARCHIVE: u8 = 0x20;
+const ATTR_LONG_NAME: u8 = ATTR_READ_ONLY | ATTR_HIDDEN | ATTR_SYSTEM | ATTR_VOLUME_ID;
+
+/// Special values of the first byte of a directory entry
+const ENTRY_END: u8 = 0x00;
+const ENTRY_DELETED: u8 = 0xe5;
+
+/// Long file name entries
+const LFN_LAST_ENTRY: u8 = 0x40;
+const LFN_ORDINAL_MASK: u8 = 0x3f;
+const LFN_CHARS_PER_ENTRY: usize = 13;
+const LFN_CHAR_OFFSETS: [usize; LFN_CHARS_PER_ENTRY] = [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30];
+const MAX_NAME_LENGTH: usize = 255;
+
+/// Values in the file allocation table (only the lower 28 bits are used)
+const FAT_ENTRY_MASK: u32 = 0x0fff_ffff;
+const FAT_FREE: u32 = 0;
+const FAT_END_OF_CHAIN: u32 = 0x0fff_fff8;
+const FAT_END_OF_CHAIN_MARK: u32 = 0x0fff_ffff;
+const FIRST_DATA_CLUSTER: u32 = 2;
+
+/// FSInfo sector signatures
+const FS_INFO_LEAD_SIGNATURE: u32 = 0x4161_5252;
+const FS_INFO_STRUCT_SIGNATURE: u32 = 0x6141_7272;
+
+const BOOT_SIGNATURE: u16 = 0xaa55;
+const DIR_ENTRY_SIZE: usize = 32;
+
+pub struct Fat32 {
+    volume: Arc<Volume>,
+}
+
+impl Fat32 {
+    /// Open the FAT32 file system stored on `device`. \
+    /// Returns `Err(Errno::EINVAL)` if the device does not contain a FAT32 file system.
+    pub fn new(device: Arc<dyn BlockDevice + Send + Sync>) -> Result<Fat32, Errno> {
+        let volume = Volume::open(device)?;
+        info!(
+            "FAT32: found volume with [{}] clusters of [{}] bytes",
+            volume.cluster_count,
+            volume.cluster_size()
+        );
+
+        Ok(Fat32 {
+            volume: Arc::new(volume),
+        })
+    }
+}
+
+impl FileSystem for Fat32 {
+    fn root_dir(&self) -> Arc<dyn DirectoryObject> {
+        Arc::new(Dir::new(self.volume.clone(), self.volume.root_cluster))
+    }
+}
+
+/// Allocation state of the volume: which clusters are in use (read from the FAT when opening the volume)
+/// and the hints mirrored in the FSInfo sector. It is only locked for updating it, never during disk I/O.
+struct AllocState {
+    used: Vec<u64>, // bit per data cluster, starting with `FIRST_DATA_CLUSTER`
+    free_count: u32,
+    next_free: u32,
+}
+
+impl AllocState {
+    fn is_used(&self, cluster: u32) -> bool {
+        let index = (cluster - FIRST_DATA_CLUSTER) as usize;
+        self.used[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    fn set_used(&mut self, cluster: u32, used: bool) {
+        let index = (cluster - FIRST_DATA_CLUSTER) as usize;
+        if used {
+            self.used[index / 64] |= 1 << (index % 64);
+        } else {
+            self.used[index / 64] &= !(1 << (index % 64));
+        }
+    }
+}
+
+/// Geometry of the volume (parsed from the BIOS parameter block) and helper functions
+/// for accessing sectors, clusters and the file allocation table.
+struct Volume {
+    device: Arc<dyn BlockDevice + Send + Sync>,
+    bytes_per_sector: usize,
+    sectors_per_cluster: usize,
+    fat_start: u64,
+    fat_size: u64,
+    fat_count: usize,
+    data_start: u64,
+    cluster_count: u32,
+    root_cluster: u32,
+    fs_info_sector: Option<u64>,
+    alloc_state: Mutex<AllocState>,
+    update_lock: UpdateLock, // serializes all modifying operations on the volume
+}
+
+impl Volume {
+    fn open(device: Arc<dyn BlockDevice + Send + Sync>) -> Result<Volume, Errno> {
+        let sector_size = device.sector_size() as usize;
+        let mut boot_sector = vec![0u8; sector_size];
+        if device.read(0, 1, &mut boot_sector) != 1 {
+            return Err(Errno::EIO);
+        }
+
+        if read_u16(&boot_sector, 510) != BOOT_SIGNATURE {
+            return Err(Errno::EINVAL);
+        }
+
+        let bytes_per_sector = read_u16(&boot_sector, 11) as usize;
+        let sectors_per_cluster = boot_sector[13] as usize;
+        let reserved_sectors = read_u16(&boot_sector, 14) as u64;
+        let fat_count = boot_sector[16] as usize;
+        let root_entry_count = read_u16(&boot_sector, 17);
+        let total_sectors_16 = read_u16(&boot_sector, 19) as u64;
+        let fat_size_16 = read_u16(&boot_sector, 22);
+        let total_sectors_32 = read_u32(&boot_sector, 32) as u64;
+        let fat_size = read_u32(&boot_sector, 36) as u64;
+        let root_cluster = read_u32(&boot_sector, 44);
+        let fs_info = read_u16(&boot_sector, 48) as u64;
+
+        // FAT12/16 volumes have a fixed root directory and a 16 bit FAT size
+        if root_entry_count != 0 || fat_size_16 != 0 || fat_size == 0 {
+            return Err(Errno::EINVAL);
+        }
+        if bytes_per_sector != sector_size || sectors_per_cluster == 0 || !sectors_per_cluster.is_power_of_two() || fat_count == 0 {
+            return Err(Errno::EINVAL);
+        }
+
+        let total_sectors = if total_sectors_16 != 0 { total_sectors_16 } else { total_sectors_32 };
+        let data_start = reserved_sectors + fat_count as u64 * fat_size;
+        if total_sectors <= data_start {
+            return Err(Errno::EINVAL);
+        }
+        let cluster_count = ((total_sectors - data_start) / sectors_per_cluster as u64) as u32;
+
+        let mut volume = Volume {
+            device,
+            bytes_per_sector,
+            sectors_per_cluster,
+            fat_start: reserved_sectors,
+            fat_size,
+            fat_count,
+            data_start,
+            cluster_count,
+            root_cluster,
+            fs_info_sector: None,
+            alloc_state: Mutex::new(AllocState {
+                used: Vec::new(),
+                free_count: 0,
+                next_free: FIRST_DATA_CLUSTER,
+            }),
+            update_lock: UpdateLock::new(),
+        };
+
+        if !volume.is_data_cluster(root_cluster) || fat_size * (bytes_per_sector as u64 / 4) < (cluster_count + FIRST_DATA_CLUSTER) as u64 {
+            return Err(Errno::EINVAL);
+        }
+        volume.read_used_clusters()?;
+
+        // Use the allocation hint from FSInfo sector (if present and valid), the free count is known exactly
+        if fs_info != 0 && fs_info < reserved_sectors {
+            let mut buffer = vec![0u8; bytes_per_sector];
+            volume.read_sectors(fs_info, 1, &mut buffer)?;
+            if read_u32(&buffer, 0) == FS_INFO_LEAD_SIGNATURE && read_u32(&buffer, 484) == FS_INFO_STRUCT_SIGNATURE {
+                volume.fs_info_sector = Some(fs_info);
+                let next_free = read_u32(&buffer, 492);
+                if volume.is_data_cluster(next_free) {
+                    volume.alloc_state.lock().next_free = next_free;
+                }
+            }
+        }
+
+        Ok(volume)
+    }
+
+    /// Mark all clusters as used, which have a non-free entry in the (first) FAT (the lock is only taken for storing the result)
+    fn read_used_clusters(&self) -> Result<(), Errno> {
+        const SECTORS_PER_READ: usize = 64;
+        let entries_per_sector = self.bytes_per_sector / 4;
+        let sector_count = (self.cluster_count + FIRST_DATA_CLUSTER).div_ceil(entries_per_sector as u32) as usize;
+        let mut buffer = vec![0u8; SECTORS_PER_READ * self.bytes_per_sector];
+        let mut scanned = AllocState { used: vec![0; (self.cluster_count as usize).div_ceil(64)], free_count: self.cluster_count, next_free: 0 };
+
+        for first in (0..sector_count).step_by(SECTORS_PER_READ) {
+            let count = SECTORS_PER_READ.min(sector_count - first);
+            self.read_sectors(self.fat_start + first as u64, count, &mut buffer)?;
+            for (index, entry) in buffer[..count * self.bytes_per_sector].chunks_exact(4).enumerate() {
+                let cluster = (first * entries_per_sector + index) as u32;
+                if self.is_data_cluster(cluster) && read_u32(entry, 0) & FAT_ENTRY_MASK != FAT_FREE {
+                    scanned.set_used(cluster, true);
+                    scanned.free_count -= 1;
+                }
+            }
+        }
+
+        let mut state = self.alloc_state.lock();
+        state.used = scanned.used;
+        state.free_count = scanned.free_count;
+        Ok(())
+    }
+
+    /// Block until no other modifying operation is running on the volume (see `UpdateLock`)
+    fn lock_update(&self) -> UpdateGuard<'_> {
+        self.update_lock.lock()
+    }
+
+    fn cluster_size(&self) -> usize {
+        self.bytes_per_sector * self.sectors_per_cluster
+    }
+
+    fn is_data_cluster(&self, cluster: u32) -> bool {
+        cluster >= FIRST_DATA_CLUSTER && cluster < self.cluster_count + FIRST_DATA_CLUSTER
+    }
+
+    fn cluster_to_sector(&self, cluster: u32) -> u64 {
+        self.data_start + (cluster - FIRST_DATA_CLUSTER) as u64 * self.sectors_per_cluster as u64
+    }
+
+    fn read_sectors(&self, sector: u64, count: usize, buffer: &mut [u8]) -> Result<(), Errno> {
+        if self.device.read(sector, count, buffer) != count {
+            return Err(Errno::EIO);
+        }
+        Ok(())
+    }
+
+    fn write_sectors(&self, sector: u64, count: usize, buffer: &[u8]) -> Result<(), Errno> {
+        if self.device.write(sector, count, buffer) != count {
+            return Err(Errno::EIO);
+        }
+        Ok(())
+    }
+
+    fn read_cluster(&self, cluster: u32, buffer: &mut [u8]) -> Result<(), Errno> {
+        self.read_sectors(self.cluster_to_sector(cluster), self.sectors_per_cluster, buffer)
+    }
+
+    fn write_cluster(&self, cluster: u32, buffer: &[u8]) -> Result<(), Errno> {
+        self.write_sectors(self.cluster_to_sector(cluster), self.sectors_per_cluster, buffer)
+    }
+
+    /// Read the FAT entry of `cluster` (from the first FAT copy)
+    fn fat_entry(&self, cluster: u32) -> Result<u32, Errno> {
+        let offset = cluster as usize * 4;
+        let mut buffer = vec![0u8; self.bytes_per_sector];
+        self.read_sectors(self.fat_start + (offset / self.bytes_per_sector) as u64, 1, &mut buffer)?;
+        Ok(read_u32(&buffer, offset % self.bytes_per_sector) & FAT_ENTRY_MASK)
+    }
+
+    /// Write the FAT entry of `cluster` into all FAT copies (keeping the reserved upper 4 bits)
+    fn set_fat_entry(&self, cluster: u32, value: u32) -> Result<(), Errno> {
+        let offset = cluster as usize * 4;
+        let mut buffer = vec![0u8; self.bytes_per_sector];
+        for fat in 0..self.fat_count {
+            let sector = self.fat_start + fat as u64 * self.fat_size + (offset / self.bytes_per_sector) as u64;
+            self.read_sectors(sector, 1, &mut buffer)?;
+            let pos = offset % self.bytes_per_sector;
+            let old = read_u32(&buffer, pos);
+            write_u32(&mut buffer, pos, (old & !FAT_ENTRY_MASK) | (value & FAT_ENTRY_MASK));
+            self.write_sectors(sector, 1, &buffer)?;
+        }
+        Ok(())
+    }
+
+    /// Follow the cluster chain starting at `first` and return all clusters
+    fn cluster_chain(&self, first: u32) -> Result<Vec<u32>, Errno> {
+        let mut chain = Vec::new();
+        let mut cluster = first;
+        while self.is_data_cluster(cluster) {
+            // A chain longer than the volume must contain a loop
+            if chain.len() >= self.cluster_count as usize {
+                return Err(Errno::EIO);
+            }
+            chain.push(cluster);
+            cluster = self.fat_entry(cluster)?;
+        }
+
+        // A valid chain ends with an end of chain marker (and not with a free or bad cluster)
+        if !chain.is_empty() && cluster < FAT_END_OF_CHAIN {
+            return Err(Errno::EIO);
+        }
+        Ok(chain)
+    }
+
+    /// Reserve a free cluster in `alloc_state`, searching from the hint `next_free` on
+    fn reserve_cluster(&self) -> Result<u32, Errno> {
+        let mut state = self.alloc_state.lock();
+        let start = if self.is_data_cluster(state.next_free) { state.next_free } else { FIRST_DATA_CLUSTER };
+        let cluster = (0..self.cluster_count)
+            .map(|i| FIRST_DATA_CLUSTER + (start - FIRST_DATA_CLUSTER + i) % self.cluster_count)
+            .find(|&cluster| !state.is_used(cluster))
+            .ok_or(Errno::ENOSPC)?;
+
+        state.set_used(cluster, true);
+        state.free_count -= 1;
+        state.next_free = cluster + 1;
+        Ok(cluster)
+    }
+
+    /// Give `clusters` back to `alloc_state`
+    fn release_clusters(&self, clusters: &[u32]) {
+        let mut state = self.alloc_state.lock();
+        for &cluster in clusters {
+            state.set_used(cluster, false);
+        }
+        state.free_count += clusters.len() as u32;
+    }
+
+    /// Allocate a free cluster, fill it with zeros and append it to `previous` (if given). \
+    /// The caller must hold the update lock (see `lock_update`).
+    fn allocate_cluster(&self, previous: Option<u32>) -> Result<u32, Errno> {
+        let cluster = self.reserve_cluster()?;
+        let result = self.write_cluster(cluster, &vec![0u8; self.cluster_size()])
+            .and_then(|_| self.set_fat_entry(cluster, FAT_END_OF_CHAIN_MARK))
+            .and_then(|_| previous.map_or(Ok(()), |previous| self.set_fat_entry(previous, cluster)));
+        if let Err(errno) = result {
+            // Cluster is not referenced by any chain, so it can be reused
+            self.set_fat_entry(cluster, FAT_FREE).ok();
+            self.release_clusters(&[cluster]);
+            return Err(errno);
+        }
+
+        self.write_fs_info()?;
+        Ok(cluster)
+    }
+
+    /// Mark all `clusters` as free. \
+    /// The caller must hold the update lock (see `lock_update`).
+    fn free_clusters(&self, clusters: &[u32]) -> Result<(), Errno> {
+        for &cluster in clusters {
+            self.set_fat_entry(cluster, FAT_FREE)?;
+        }
+
+        self.release_clusters(clusters);
+        self.write_fs_info()
+    }
+
+    /// Write the allocation hints back into the FSInfo sector
+    fn write_fs_info(&self) -> Result<(), Errno> {
+        if let Some(sector) = self.fs_info_sector {
+            let (free_count, next_free) = {
+                let state = self.alloc_state.lock();
+                (state.free_count, state.next_free)
+            };
+
+            let mut buffer = vec![0u8; self.bytes_per_sector];
+            self.read_sectors(sector, 1, &mut buffer)?;
+            write_u32(&mut buffer, 488, free_count);
+            write_u32(&mut buffer, 492, next_free);
+            self.write_sectors(sector, 1, &buffer)?;
+        }
+        Ok(())
+    }
+
+    /// Read the 32 byte directory entry stored at `location`
+    fn read_entry(&self, location: EntryLocation) -> Result<[u8; DIR_ENTRY_SIZE], Errno> {
+        let mut buffer = vec![0u8; self.bytes_per_sector];
+        self.read_sectors(location.sector, 1, &mut buffer)?;
+
+        let mut entry = [0u8; DIR_ENTRY_SIZE];
+        entry.copy_from_slice(&buffer[location.offset..location.offset + DIR_ENTRY_SIZE]);
+        Ok(entry)
+    }
+
+    /// Write the 32 byte directory `entry` at `location`
+    fn write_entry(&self, location: EntryLocation, entry: &[u8; DIR_ENTRY_SIZE]) -> Result<(), Errno> {
+        let mut buffer = vec![0u8; self.bytes_per_sector];
+        self.read_sectors(location.sector, 1, &mut buffer)?;
+        buffer[location.offset..location.offset + DIR_ENTRY_SIZE].copy_from_slice(entry);
+        self.write_sectors(location.sector, 1, &buffer)
+    }
+}
+
+/// Position of a directory entry on the device
+#[derive(Debug, Copy, Clone)]
+struct EntryLocation {
+    sector: u64,
+    offset: usize,
+}
+
+/// A directory entry with its (long) name already assembled
+struct DirRecord {
+    name: String,
+    short_name: [u8; 11],
+    attributes: u8,
+    first_cluster: u32,
+    location: EntryLocation,
+}
+
+impl DirRecord {
+    fn is_dir(&self) -> bool {
+        self.attributes & ATTR_DIRECTORY != 0
+    }
+
+    fn is_dot_entry(&self) -> bool {
+        self.short_name[0] == b'.'
+    }
+}
+
+pub struct Dir {
+    volume: Arc<Volume>,
+    first_cluster: u32,
+}
+
+impl Dir {
+    fn new(volume: Arc<Volume>, first_cluster: u32) -> Dir {
+        Dir { volume, first_cluster }
+    }
+
+    /// Read all valid entries of this directory (including `.` and `..`)
+    fn records(&self) -> Result<Vec<DirRecord>, Errno> {
+        let volume = &self.volume;
+        let mut records = Vec::new();
+        let mut cluster_buffer = vec![0u8; volume.cluster_size()];
+
+        // Long name parts collected so far (in UTF-16), together with the expected checksum
+        let mut lfn: Vec<u16> = Vec::new();
+        let mut lfn_checksum: Option<u8> = None;
+        let mut lfn_next_ordinal = 0u8;
+
+        for cluster in volume.cluster_chain(self.first_cluster)? {
+            volume.read_cluster(cluster, &mut cluster_buffer)?;
+
+            for (index, entry) in cluster_buffer.chunks_exact(DIR_ENTRY_SIZE).enumerate() {
+                if entry[0] == ENTRY_END {
+                    return Ok(records);
+                }
+                if entry[0] == ENTRY_DELETED {
+                    lfn_checksum = None;
+                    continue;
+                }
+
+                let attributes = entry[11];
+                if attributes & ATTR_LONG_NAME == ATTR_LONG_NAME {
+                    let ordinal = entry[0] & LFN_ORDINAL_MASK;
+                    if entry[0] & LFN_LAST_ENTRY != 0 {
+                        // First physical entry holds the last part of the name
+                        lfn = vec![0xffff; ordinal as usize * LFN_CHARS_PER_ENTRY];
+                        lfn_checksum = Some(entry[13]);
+                    } else if lfn_checksum != Some(entry[13]) || ordinal != lfn_next_ordinal {
+                        lfn_checksum = None;
+                        continue;
+                    }
+                    if ordinal == 0 {
+                        lfn_checksum = None;
+                        continue;
+                    }
+
+                    let start = (ordinal as usize - 1) * LFN_CHARS_PER_ENTRY;
+                    for (i, &pos) in LFN_CHAR_OFFSETS.iter().enumerate() {
+                        lfn[start + i] = read_u16(entry, pos);
+                    }
+                    lfn_next_ordinal = ordinal - 1;
+                    continue;
+                }
+
+                let mut short_name = [0u8; 11];
+                short_name.copy_from_slice(&entry[0..11]);
+
+                // Volume label is not a real entry
+                if attributes & ATTR_VOLUME_ID != 0 {
+                    lfn_checksum = None;
+                    continue;
+                }
+
+                // Use long name if it belongs to this short entry
+                let name = match lfn_checksum {
+                    Some(checksum) if lfn_next_ordinal == 0 && checksum == lfn_checksum_of(&short_name) => {
+                        let units: Vec<u16> = lfn.iter().copied().take_while(|&c| c != 0 && c != 0xffff).collect();
+                        String::from_utf16_lossy(&units)
+                    }
+                    _ => short_name_to_string(&short_name, entry[12]),
+                };
+                lfn_checksum = None;
+
+                let byte_offset = index * DIR_ENTRY_SIZE;
+                records.push(DirRecord {
+                    name,
+                    short_name,
+                    attributes,
+                    first_cluster: entry_first_cluster(entry),
+                    location: EntryLocation {
+                        sector: volume.cluster_to_sector(cluster) + (byte_offset / volume.bytes_per_sector) as u64,
+                        offset: byte_offset % volume.bytes_per_sector,
+                    },
+                });
+            }
+        }
+
+        Ok(records)
+    }
+
+    /// Find the entry with `name` (names are case insensitive in FAT)
+    fn find(&self, name: &str) -> Result<Option<DirRecord>, Errno> {
+        Ok(self
+            .records()?
+            .into_iter()
+            .filter(|record| !record.is_dot_entry())
+            .find(|record| record.name.eq_ignore_ascii_case(name)))
+    }
+
+    /// Find `count` consecutive free entries, extending the directory if necessary. \
+    /// The caller must hold the update lock of the volume.
+    fn find_free_entries(&self, count: usize) -> Result<Vec<EntryLocation>, Errno> {
+        let volume = &self.volume;
+        let entries_per_cluster = volume.cluster_size() / DIR_ENTRY_SIZE;
+        let mut cluster_buffer = vec![0u8; volume.cluster_size()];
+        let mut run: Vec<EntryLocation> = Vec::new();
+
+        let location_of = |cluster: u32, index: usize| {
+            let byte_offset = index * DIR_ENTRY_SIZE;
+            EntryLocation {
+                sector: volume.cluster_to_sector(cluster) + (byte_offset / volume.bytes_per_sector) as u64,
+                offset: byte_offset % volume.bytes_per_sector,
+            }
+        };
+
+        let chain = volume.cluster_chain(self.first_cluster)?;
+        for &cluster in chain.iter() {
+            volume.read_cluster(cluster, &mut cluster_buffer)?;
+            for (index, entry) in cluster_buffer.chunks_exact(DIR_ENTRY_SIZE).enumerate() {
+                if entry[0] == ENTRY_END || entry[0] == ENTRY_DELETED {
+                    run.push(location_of(cluster, index));
+                    if run.len() == count {
+                        return Ok(run);
+                    }
+                } else {
+                    run.clear();
+                }
+            }
+        }
+
+        // Not enough space -> append new (zeroed) clusters to the directory
+        let mut last = *chain.last().ok_or(Errno::EIO)?;
+        while run.len() < count {
+            last = volume.allocate_cluster(Some(last))?;
+            for index in 0..entries_per_cluster {
+                run.push(location_of(last, index));
+                if run.len() == count {
+                    break;
+                }
+            }
+        }
+
+        Ok(run)
+    }
+
+    /// Create a new entry `name` with the given `attributes` and `first_cluster`. \
+    /// The caller must hold the update lock of the volume.
+    fn create_entry(&self, name: &str, attributes: u8, first_cluster: u32) -> Result<(), Errno> {
+        check_name(name)?;
+
+        let records = self.records()?;
+        if records.iter().any(|record| !record.is_dot_entry() && record.name.eq_ignore_ascii_case(name)) {
+            return Err(Errno::EEXIST);
+        }
+
+        let (short_name, needs_lfn) = generate_short_name(name, &records)?;
+        let mut entries: Vec<[u8; DIR_ENTRY_SIZE]> = if needs_lfn {
+            long_name_entries(name, lfn_checksum_of(&short_name))
+        } else {
+            Vec::new()
+        };
+        entries.push(short_entry(&short_name, attributes, first_cluster, 0));
+
+        let locations = self.find_free_entries(entries.len())?;
+        for (location, entry) in locations.iter().zip(entries.iter()) {
+            self.volume.write_entry(*location, entry)?;
+        }
+
+        Ok(())
+    }
+
+    fn named_object(&self, record: &DirRecord) -> NamedObject {
+        if record.is_dir() {
+            // A first cluster of 0 in '..' refers to the root directory
+            let cluster = if record.first_cluster == 0 { self.volume.root_cluster } else { record.first_cluster };
+            (Arc::new(Dir::new(self.volume.clone(), cluster)) as Arc<dyn DirectoryObject>).into()
+        } else {
+            (Arc::new(File::new(self.volume.clone(), record.location)) as Arc<dyn FileObject>).into()
+        }
+    }
+}
+
+impl DirectoryObject for Dir {
+    fn lookup(&self, name: &str) -> Result<NamedObject, Errno> {
+        match self.find(name)? {
+            Some(record) => Ok(self.named_object(&record)),
+            None => Err(Errno::ENOENT),
+        }
+    }
+
+    fn create_file(&self, name: &str, _mode: Mode) -> Result<NamedObject, Errno> {
+        {
+            let _update = self.volume.lock_update();
+            self.create_entry(name, ATTR_ARCHIVE, 0)?;
+        }
+        self.lookup(name)
+    }
+
+    fn create_dir(&self, name: &str, _mode: Mode) -> Result<NamedObject, Errno> {
+        {
+            let _update = self.volume.lock_update();
+            if self.find(name)?.is_some() {
+                return Err(Errno::EEXIST);
+            }
+
+            // Every directory (except root) starts with the entries '.' and '..'
+            let cluster = self.volume.allocate_cluster(None)?;
+            let parent = if self.first_cluster == self.volume.root_cluster { 0 } else { self.first_cluster };
+            let mut cluster_buffer = vec![0u8; self.volume.cluster_size()];
+            cluster_buffer[0..DIR_ENTRY_SIZE].copy_from_slice(&short_entry(b".          ", ATTR_DIRECTORY, cluster, 0));
+            cluster_buffer[DIR_ENTRY_SIZE..2 * DIR_ENTRY_SIZE].copy_from_slice(&short_entry(b"..         ", ATTR_DIRECTORY, parent, 0));
+
+            if let Err(e) = self.volume.write_cluster(cluster, &cluster_buffer).and_then(|_| self.create_entry(name, ATTR_DIRECTORY, cluster)) {
+                // Give cluster back, since no entry is referencing it
+                self.volume.free_clusters(&[cluster])?;
+                return Err(e);
+            }
+        }
+        self.lookup(name)
+    }
+
+    fn stat(&self) -> Result<Stat, Errno> {
+        Ok(Stat::new(Mode::new(MODE_DIR), 0))
+    }
+
+    fn readdir(&self, index: usize) -> Result<Option<DirEntry>, Errno> {
+        let record = self
+            .records()?
+            .into_iter()
+            .filter(|record| !record.is_dot_entry())
+            .nth(index);
+
+        // the position of the entry on the disk identifies the object
+        let bytes_per_sector = self.volume.bytes_per_sector as u64;
+        Ok(record.map(|record| DirEntry {
+            inode: (record.location.sector * bytes_per_sector + record.location.offset as u64) / DIR_ENTRY_SIZE as u64,
+            file_type: if record.is_dir() { FileType::Directory } else { FileType::Regular },
+            name: record.name,
+        }))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Debug for Dir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Fat32Dir").field("cluster", &self.first_cluster).finish()
+    }
+}
+
+/// A file is identified by the location of its short directory entry,
+/// which holds the first cluster and the size of the file.
+pub struct File {
+    volume: Arc<Volume>,
+    location: EntryLocation,
+}
+
+impl File {
+    fn new(volume: Arc<Volume>, location: EntryLocation) -> File {
+        File { volume, location }
+    }
+
+    /// Return first cluster and size of the file from its directory entry
+    fn entry_info(&self) -> Result<(u32, u32), Errno> {
+        let entry = self.volume.read_entry(self.location)?;
+        Ok((entry_first_cluster(&entry), read_u32(&entry, 28)))
+    }
+
+    /// Update first cluster and size in the directory entry of the file
+    fn update_entry_info(&self, first_cluster: u32, size: u32) -> Result<(), Errno> {
+        let mut entry = self.volume.read_entry(self.location)?;
+        write_u16(&mut entry, 20, (first_cluster >> 16) as u16);
+        write_u16(&mut entry, 26, first_cluster as u16);
+        write_u32(&mut entry, 28, size);
+        entry[11] |= ATTR_ARCHIVE;
+        self.volume.write_entry(self.location, &entry)
+    }
+
+    /// Write `buf` at `offset` into the cluster chain starting at `first_cluster`.
+    /// Missing clusters are allocated (the caller must hold the update lock of the volume).
+    /// Returns the (possibly new) first cluster.
+    fn write_clusters(&self, first_cluster: u32, buf: &[u8], offset: usize) -> Result<u32, Errno> {
+        let volume = &self.volume;
+        let cluster_size = volume.cluster_size();
+        let needed_clusters = offset.checked_add(buf.len()).ok_or(Errno::EINVAL)?.div_ceil(cluster_size);
+
+        // Extend cluster chain as needed
+        let mut chain = volume.cluster_chain(first_cluster)?;
+        while chain.len() < needed_clusters {
+            let cluster = volume.allocate_cluster(chain.last().copied())?;
+            chain.push(cluster);
+        }
+
+        let mut cluster_buffer = vec![0u8; cluster_size];
+        let mut written = 0;
+        while written < buf.len() {
+            let pos = offset + written;
+            let cluster = chain[pos / cluster_size];
+            let cluster_offset = pos % cluster_size;
+            let len = (cluster_size - cluster_offset).min(buf.len() - written);
+
+            if len == cluster_size {
+                volume.write_cluster(cluster, &buf[written..written + len])?;
+            } else {
+                volume.read_cluster(cluster, &mut cluster_buffer)?;
+                cluster_buffer[cluster_offset..cluster_offset + len].copy_from_slice(&buf[written..written + len]);
+                volume.write_cluster(cluster, &cluster_buffer)?;
+            }
+            written += len;
+        }
+
+        Ok(chain.first().copied().unwrap_or(first_cluster))
+    }
+}
+
+impl FileObject for File {
+    fn stat(&self) -> Result<Stat, Errno> {
+        let (_, size) = self.entry_info()?;
+        Ok(Stat::new(Mode::new(MODE_FILE), size as usize))
+    }
+
+    fn read(&self, buf: &mut [u8], offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        let volume = &self.volume;
+        let (first_cluster, size) = self.entry_info()?;
+        let size = size as usize;
+        if offset >= size {
+            return Ok(0);
+        }
+
+        let len = buf.len().min(size - offset);
+        let cluster_size = volume.cluster_size();
+        let chain = volume.cluster_chain(first_cluster)?;
+        if chain.len() < (offset + len).div_ceil(cluster_size) {
+            return Err(Errno::EIO);
+        }
+
+        let mut cluster_buffer = vec![0u8; cluster_size];
+        let mut read = 0;
+        while read < len {
+            let pos = offset + read;
+            let cluster_offset = pos % cluster_size;
+            let chunk = (cluster_size - cluster_offset).min(len - read);
+
+            volume.read_cluster(chain[pos / cluster_size], &mut cluster_buffer)?;
+            buf[read..read + chunk].copy_from_slice(&cluster_buffer[cluster_offset..cluster_offset + chunk]);
+            read += chunk;
+        }
+
+        Ok(len)
+    }
+
+    fn write(&self, buf: &[u8], offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let end = offset.checked_add(buf.len()).ok_or(Errno::EINVAL)?;
+        if end > u32::MAX as usize {
+            return Err(Errno::EINVAL); // FAT32 files are limited to 4 GiB
+        }
+
+        let _update = self.volume.lock_update();
+        let (mut first_cluster, size) = self.entry_info()?;
+        let size = size as usize;
+
+        // Writing behind the end of file: the last cluster may contain garbage behind
+        // the old end of file, which has to be zeroed (new clusters are zeroed anyway)
+        let cluster_size = self.volume.cluster_size();
+        if offset > size && size % cluster_size != 0 {
+            let gap = (cluster_size - size % cluster_size).min(offset - size);
+            first_cluster = self.write_clusters(first_cluster, &vec![0u8; gap], size)?;
+        }
+
+        first_cluster = self.write_clusters(first_cluster, buf, offset)?;
+        self.update_entry_info(first_cluster, size.max(end) as u32)?;
+
+        Ok(buf.len())
+    }
+
+    fn truncate(&self, size: usize) -> Result<(), Errno> {
+        if size > u32::MAX as usize {
+            return Err(Errno::EINVAL); // FAT32 files are limited to 4 GiB
+        }
+
+        let _update = self.volume.lock_update();
+        let (mut first_cluster, old_size) = self.entry_info()?;
+        let old_size = old_size as usize;
+
+        if size > old_size {
+            // Growing: fill with zeros (also clears garbage behind the old end of file in the last cluster)
+            first_cluster = self.write_clusters(first_cluster, &vec![0u8; size - old_size], old_size)?;
+        } else {
+            // Shrinking: give back all clusters behind the new end of file
+            let chain = self.volume.cluster_chain(first_cluster)?;
+            let keep = size.div_ceil(self.volume.cluster_size()).min(chain.len());
+            if keep == 0 {
+                first_cluster = 0;
+            } else if keep < chain.len() {
+                self.volume.set_fat_entry(chain[keep - 1], FAT_END_OF_CHAIN_MARK)?;
+            }
+            self.volume.free_clusters(&chain[keep..])?;
+        }
+
+        self.update_entry_info(first_cluster, size as u32)
+    }
+}
+
+impl fmt::Debug for File {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Fat32File").field("location", &self.location).finish()
+    }
+}
+
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Helper functions for directory entries and names.                       ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+
+fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([buf[offset], buf[offset + 1], buf[offset + 2], buf[offset + 3]])
+}
+
+fn write_u16(buf: &mut [u8], offset: usize, value: u16) {
+    buf[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+fn write_u32(buf: &mut [u8], offset: usize, value: u32) {
+    buf[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn entry_first_cluster(entry: &[u8]) -> u32 {
+    ((read_u16(entry, 20) as u32) << 16) | read_u16(entry, 26) as u32
+}
+
+/// Build a short directory entry
+fn short_entry(short_name: &[u8; 11], attributes: u8, first_cluster: u32, size: u32) -> [u8; DIR_ENTRY_SIZE] {
+    let mut entry = [0u8; DIR_ENTRY_SIZE];
+    entry[0..11].copy_from_slice(short_name);
+    entry[11] = attributes;
+    write_u16(&mut entry, 20, (first_cluster >> 16) as u16);
+    write_u16(&mut entry, 26, first_cluster as u16);
+    write_u32(&mut entry, 28, size);
+    entry
+}
+
+/// Build the long name entries for `name` in the order they are stored on disk
+fn long_name_entries(name: &str, checksum: u8) -> Vec<[u8; DIR_ENTRY_SIZE]> {
+    let units: Vec<u16> = name.encode_utf16().collect();
+    let count = units.len().div_ceil(LFN_CHARS_PER_ENTRY);
+    let mut entries = Vec::with_capacity(count);
+
+    for part in 0..count {
+        let mut entry = [0u8; DIR_ENTRY_SIZE];
+        entry[0] = (part + 1) as u8;
+        if part == count - 1 {
+            entry[0] |= LFN_LAST_ENTRY;
+        }
+        entry[11] = ATTR_LONG_NAME;
+        entry[13] = checksum;
+
+        // Name is terminated with 0x0000 and padded with 0xffff
+        for (i, &pos) in LFN_CHAR_OFFSETS.iter().enumerate() {
+            let index = part * LFN_CHARS_PER_ENTRY + i;
+            let unit = if index < units.len() {
+                units[index]
+            } else if index == units.len() {
+                0x0000
+            } else {
+                0xffff
+            };
+            write_u16(&mut entry, pos, unit);
+        }
+        entries.push(entry);
+    }
+
+    entries.reverse();
+    entries
+}
+
+fn lfn_checksum_of(short_name: &[u8; 11]) -> u8 {
+    short_name
+        .iter()
+        .fold(0u8, |sum, &c| ((sum & 1) << 7).wrapping_add(sum >> 1).wrapping_add(c))
+}
+
+/// Convert an 8.3 name into a string (considering the lower case flags used by Windows NT)
+fn short_name_to_string(short_name: &[u8; 11], case_flags: u8) -> String {
+    let convert = |bytes: &[u8], lower: bool| {
+        bytes
+            .iter()
+            .take_while(|&&c| c != b' ')
+            .map(|&c| if lower { c.to_ascii_lowercase() as char } else { c as char })
+            .collect::<String>()
+    };
+
+    let mut name = convert(&short_name[0..8], case_flags & 0x08 != 0);
+    // 0x05 is used as replacement for a leading 0xe5 (which marks deleted entries)
+    if short_name[0] == 0x05 {
+        name.replace_range(0..1, "\u{e5}");
+    }
+
+    let ext = convert(&short_name[8..11], case_flags & 0x10 != 0);
+    if !ext.is_empty() {
+        name.push('.');
+        name.push_str(&ext);
+    }
+    name
+}
+
+/// Check if `name` is allowed as a long file name
+fn check_name(name: &str) -> Result<(), Errno> {
+    if name.is_empty() || name == "." || name == ".." || name.encode_utf16().count() > MAX_NAME_LENGTH {
+        return Err(Errno::EINVAL);
+    }
+    if name.chars().any(|c| (c as u32) < 0x20 || "\"*/:<>?\\|".contains(c)) {
+        return Err(Errno::EINVAL);
+    }
+    Ok(())
+}
+
+/// Generate a unique 8.3 name for `name`. Returns the short name and `true`,
+/// if additional long name entries are needed to store `name`.
+fn generate_short_name(name: &str, records: &[DirRecord]) -> Result<([u8; 11], bool), Errno> {
+    let (base, ext) = match name.rfind('.') {
+        Some(pos) if pos > 0 => (&name[..pos], &name[pos + 1..]),
+        _ => (name, ""),
+    };
+
+    // Convert characters to upper case and replace those not allowed in short names
+    let mut lossy = false;
+    let mut convert = |part: &str| {
+        part.chars()
+            .filter(|&c| c != ' ' && c != '.')
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || "$%'-_@~`!(){}^#&".contains(c) {
+                    c.to_ascii_uppercase() as u8
+                } else {
+                    lossy = true;
+                    b'_'
+                }
+            })
+            .collect::<Vec<u8>>()
+    };
+    let base_chars = convert(base);
+    let ext_chars = convert(ext);
+
+    let mut short_name = [b' '; 11];
+    short_name[8..8 + ext_chars.len().min(3)].copy_from_slice(&ext_chars[..ext_chars.len().min(3)]);
+
+    // Name fits exactly into 8.3 format -> no long name needed
+    let fits = !lossy && !base_chars.is_empty() && base_chars.len() <= 8 && ext_chars.len() <= 3
+        && base.len() == base_chars.len() && ext.len() == ext_chars.len()
+        && !name.ends_with('.') && name == name.to_ascii_uppercase();
+    if fits {
+        short_name[0..base_chars.len()].copy_from_slice(&base_chars);
+        if records.iter().any(|record| record.short_name == short_name) {
+            return Err(Errno::EEXIST);
+        }
+        return Ok((short_name, false));
+    }
+
+    // Generate numeric tail ("NAME~1.EXT") until the short name is unique
+    for n in 1..1000000usize {
+        let tail = format!("~{}", n);
+        let base_len = base_chars.len().min(8 - tail.len());
+        let mut candidate = short_name;
+        candidate[0..8].fill(b' ');
+        candidate[0..base_len].copy_from_slice(&base_chars[..base_len]);
+        candidate[base_len..base_len + tail.len()].copy_from_slice(tail.as_bytes());
+
+        if !records.iter().any(|record| record.short_name == candidate) {
+            return Ok((candidate, true));
+        }
+    }
+
+    Err(Errno::ENOSPC)
+}
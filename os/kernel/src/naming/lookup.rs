@@ -6,15 +6,18 @@
    ║ Author: Michael Schoettner, Univ. Duesseldorf, 30.12.2024               ║
    ╚═════════════════════════════════════════════════════════════════════════╝
 */
+use alloc::format;
 use alloc::string::String;
-use alloc::vec::Vec;
 use alloc::sync::Arc;
-use super::api::ROOT;
+use alloc::vec::Vec;
+use super::access;
+use super::mount;
 use super::traits;
 use super::traits::{NamedObject, DirectoryObject};
+use crate::process_manager;
 use syscall::return_vals::Errno;
 
-/// Resolves an absolute path into an `DirectoryLike`
+/// Resolves `path` into an `DirectoryLike`
 pub(super) fn lookup_dir(path: &String) -> Result<Arc<dyn DirectoryObject>, Errno> {
     match lookup_named_object(path)? {
         NamedObject::DirectoryObject(dir) => Ok(dir),
@@ -22,52 +25,116 @@ pub(super) fn lookup_dir(path: &String) -> Result<Arc<dyn DirectoryObject>, Errn
     }
 }
 
-/// Resolves absolute `path` into a named object. \
+/// Max. number of symbolic links followed during one lookup (more result in `Errno::ELOOP`)
+const MAX_FOLLOWED_LINKS: usize = 32;
+
+/// Resolves `path` into a named object (see `absolute_path` for relative paths). \
+/// The lookup starts in the root directory of the file system mounted closest to `path`. \
+/// Symbolic links are followed. Each directory on the way requires search (execute) permission. \
 /// Returns `Ok(NamedObject)` or `Err`
 pub(super) fn lookup_named_object(path: &String) -> Result<NamedObject, Errno> {
-    let mut found_named_object;
+    resolve(path, true).map(|(named_object, _)| named_object)
+}
 
-    if check_absolute_path(path) {
-        if path == "/" {
-            found_named_object = traits::as_named_object(ROOT.get().unwrap().root_dir());
-            return Ok(found_named_object);
-        }
-        let mut components: Vec<&str> = path.split("/").collect();
-        components.remove(0); // remove empty string at position 0
-
-        // get root directory and open the desired file
-        let mut current_dir = ROOT.get().unwrap().root_dir();
-        let mut len = components.len();
-        let mut found;
-        for component in &components {
-            found = current_dir.lookup(component);
-            if found.is_err() {
-                return Err(Errno::ENOENT);
-            }
-            found_named_object = found.unwrap();
+/// Resolves `path` into a named object like `lookup_named_object`,
+/// but if the last component is a symbolic link, the link itself is returned.
+pub(super) fn lookup_named_object_nofollow(path: &String) -> Result<NamedObject, Errno> {
+    resolve(path, false).map(|(named_object, _)| named_object)
+}
 
+/// Resolves the directory `path` like `lookup_dir` and returns its absolute path without symbolic links,
+/// i.e. its location, whose leading components are the directories reached by ".." from it.
+pub(super) fn resolved_dir_path(path: &String) -> Result<String, Errno> {
+    match resolve(path, true)? {
+        (NamedObject::DirectoryObject(_), resolved_path) => Ok(resolved_path),
+        (NamedObject::FileObject(_), _) => Err(Errno::ENOTDIR),
+    }
+}
+
+/// Helper function for the lookup functions. If a symbolic link is found, its target replaces
+/// the path up to the link and the lookup restarts with the resulting path. \
+/// Returns the named object and the path it has been found with (after replacing links).
+fn resolve(path: &str, follow_last: bool) -> Result<(NamedObject, String), Errno> {
+    let mut path = absolute_path(path)?;
+    let mut followed_links = 0;
+
+    'restart: loop {
+        // get root directory of the responsible file system and the path below its mount point
+        let (root_dir, remaining) = mount::find_mount(&path).ok_or(Errno::ENOENT)?;
+        let mut found_named_object = traits::as_named_object(root_dir);
+
+        let components: Vec<&str> = remaining.split("/").filter(|component| !component.is_empty()).collect();
+        for (index, component) in components.iter().enumerate() {
             // if not last component, this must be a directory
-            if len > 1 {
-                if !found_named_object.is_dir() {
-                    return Err(Errno::ENOENT);
-                }
-                current_dir = found_named_object.as_dir().unwrap().clone();
-            // if this is the last component, this must be a file or directory (see flags)
-            } else {
-                return Ok(found_named_object.clone());
+            let current_dir = match &found_named_object {
+                NamedObject::DirectoryObject(dir) => dir.clone(),
+                NamedObject::FileObject(_) => return Err(Errno::ENOENT),
+            };
+            access::check(&current_dir.stat()?, access::EXECUTE)?;
+            found_named_object = current_dir.lookup(component).map_err(|_| Errno::ENOENT)?;
+
+            let is_last = index == components.len() - 1;
+            if (is_last && !follow_last) || !found_named_object.is_link() {
+                continue;
+            }
+
+            followed_links += 1;
+            if followed_links > MAX_FOLLOWED_LINKS {
+                return Err(Errno::ELOOP);
             }
-            len = len - 1;
+
+            // build new path: target of the link (relative to the directory of the link) + rest of the path
+            let target = found_named_object.as_file()?.readlink()?;
+            let all_components: Vec<&str> = path.split('/').filter(|component| !component.is_empty()).collect();
+            let link_index = all_components.len() - components.len() + index;
+            let target = if target.starts_with('/') {
+                target
+            } else {
+                format!("/{}/{}", all_components[..link_index].join("/"), target)
+            };
+            path = absolute_path(&format!("{}/{}", target, all_components[link_index + 1..].join("/")))?;
+            continue 'restart;
         }
+
+        return Ok((found_named_object, path));
     }
-    Err(Errno::ENOENT)
 }
 
-/// Helper function for checking if `path` is an abolute path
-fn check_absolute_path(path: &String) -> bool {
-    if let Some(pos) = path.find('/') {
-        if pos == 0 {
-            return true;
+/// Convert `path` into a normalized absolute path. A relative `path` is resolved against the current
+/// working directory of the calling process. Empty components (repeated slashes) and "." are removed,
+/// ".." removes the previous component ("/.." is "/"). \
+/// Returns `Ok(absolute path)` or `Err(Errno::ENOENT)` for an empty `path`
+pub(super) fn absolute_path(path: &str) -> Result<String, Errno> {
+    if path.is_empty() {
+        return Err(Errno::ENOENT);
+    }
+
+    let cwd = if path.starts_with('/') {
+        String::new()
+    } else {
+        process_manager().read().current_process().cwd.lock().clone()
+    };
+
+    let mut components: Vec<&str> = Vec::new();
+    for component in cwd.split('/').chain(path.split('/')) {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            _ => components.push(component),
         }
     }
-    false
+    Ok(format!("/{}", components.join("/")))
+}
+
+/// Split `path` into the absolute path of its parent directory and its last component. \
+/// Returns `Err(Errno::EINVAL)` for the root directory (which has no name)
+pub(super) fn split_parent(path: &str) -> Result<(String, String), Errno> {
+    let path = absolute_path(path)?;
+    match path.rsplit_once('/') {
+        Some((_, "")) | None => Err(Errno::EINVAL),
+        Some(("", name)) => Ok((String::from("/"), String::from(name))),
+        Some((parent, name)) => Ok((String::from(parent), String::from(name))),
+    }
 }
@@ -1,7 +1,19 @@
 pub mod api;
+pub mod open_objects;
 pub mod stat;
 
-mod open_objects;
 mod tmpfs;
+mod fat32;
+mod ext2;
+mod pmemfs;
+mod shmfs;
+mod tarfs;
+mod devfs;
+mod procfs;
+mod pipe;
+mod watch;
+mod access;
 mod lookup;
-mod traits;
\ No newline at end of file
+mod mount;
+mod update_lock;
+pub mod traits;
\ No newline at end of file
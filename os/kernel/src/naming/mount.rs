@@ -0,0 +1,132 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: mount                                                           ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Mount table of the naming service. Each entry attaches a file system    ║
+   ║ to an absolute path. The root file system is mounted at "/". A lookup   ║
+   ║ starts at the file system with the longest mount path matching the      ║
+   ║ path to be resolved. A block device or memory region can be used by     ║
+   ║ only one mounted file system at a time.                                 ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use spin::RwLock;
+
+use super::traits::{DirectoryObject, FileSystem};
+use syscall::return_vals::Errno;
+
+/// A file system attached to the absolute path `path`
+struct MountPoint {
+    path: String,
+    fs: Arc<dyn FileSystem>,
+    device: Option<String>, // block device or non-volatile memory region storing the file system
+}
+
+static MOUNT_TABLE: RwLock<Vec<MountPoint>> = RwLock::new(Vec::new());
+
+/// Mount `fs` stored on `device` (if any) at the absolute `path`. The caller has to make sure that `path`
+/// is an existing directory (except for the root file system). \
+/// Returns `Ok(0)` or `Err(Errno::EBUSY)` if there is already a file system mounted at `path` or using `device`.
+pub(super) fn mount(path: &str, fs: Arc<dyn FileSystem>, device: Option<&str>) -> Result<usize, Errno> {
+    let path = trim_path(path);
+    let mut table = MOUNT_TABLE.write();
+    if table.iter().any(|mount_point| mount_point.path == path || device.is_some_and(|device| mount_point.uses(device))) {
+        return Err(Errno::EBUSY);
+    }
+
+    table.push(MountPoint {
+        path: path.to_string(),
+        fs,
+        device: device.map(str::to_string),
+    });
+    Ok(0)
+}
+
+/// Check if a mounted file system uses `device` (see `MountPoint::uses`)
+pub(super) fn is_device_mounted(device: &str) -> bool {
+    MOUNT_TABLE.read().iter().any(|mount_point| mount_point.uses(device))
+}
+
+/// Unmount the file system mounted at `path`. \
+/// The root file system and file systems with other file systems mounted below cannot be unmounted. \
+/// Returns the device the file system has been stored on (if any).
+pub(super) fn umount(path: &str) -> Result<Option<String>, Errno> {
+    let path = trim_path(path);
+    if path == "/" {
+        return Err(Errno::EBUSY);
+    }
+
+    let mut table = MOUNT_TABLE.write();
+    let index = table
+        .iter()
+        .position(|mount_point| mount_point.path == path)
+        .ok_or(Errno::EINVAL)?;
+
+    if table.iter().any(|mount_point| remaining_path(&mount_point.path, path).is_some_and(|rest| !rest.is_empty())) {
+        return Err(Errno::EBUSY);
+    }
+
+    Ok(table.remove(index).device)
+}
+
+/// Find the file system responsible for the absolute `path` (the one with the longest matching mount path). \
+/// Returns the root directory of this file system and the part of `path` below the mount point.
+pub(super) fn find_mount(path: &str) -> Option<(Arc<dyn DirectoryObject>, String)> {
+    let table = MOUNT_TABLE.read();
+    table
+        .iter()
+        .filter_map(|mount_point| {
+            remaining_path(path, &mount_point.path).map(|rest| (mount_point, rest))
+        })
+        .max_by_key(|(mount_point, _)| mount_point.path.len())
+        .map(|(mount_point, rest)| (mount_point.fs.root_dir(), rest.to_string()))
+}
+
+/// Check if a file system is mounted at the absolute `path`
+pub(super) fn is_mount_point(path: &str) -> bool {
+    let path = trim_path(path);
+    MOUNT_TABLE.read().iter().any(|mount_point| mount_point.path == path)
+}
+
+/// Check if the absolute paths `path1` and `path2` are handled by the same file system
+pub(super) fn same_mount(path1: &str, path2: &str) -> bool {
+    let table = MOUNT_TABLE.read();
+    let mount_index = |path: &str| {
+        table
+            .iter()
+            .enumerate()
+            .filter(|(_, mount_point)| remaining_path(path, &mount_point.path).is_some())
+            .max_by_key(|(_, mount_point)| mount_point.path.len())
+            .map(|(index, _)| index)
+    };
+    mount_index(path1) == mount_index(path2)
+}
+
+impl MountPoint {
+    /// Check if the file system is stored on `device`, a partition of it (e.g. `ata0p0` of `ata0`)
+    /// or the drive containing it
+    fn uses(&self, device: &str) -> bool {
+        let overlaps = |name: &str, other: &str| name.strip_prefix(other).is_some_and(|rest| rest.is_empty() || rest.starts_with('p'));
+        self.device.as_deref().is_some_and(|own| overlaps(own, device) || overlaps(device, own))
+    }
+}
+
+/// Return the part of `path` below `mount_path`, if `path` lies within `mount_path`
+fn remaining_path<'a>(path: &'a str, mount_path: &str) -> Option<&'a str> {
+    if mount_path == "/" {
+        return path.strip_prefix('/');
+    }
+
+    match path.strip_prefix(mount_path) {
+        Some(rest) if rest.is_empty() => Some(rest),
+        Some(rest) => rest.strip_prefix('/'),
+        None => None,
+    }
+}
+
+/// Remove trailing slashes (but keep "/")
+fn trim_path(path: &str) -> &str {
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() { "/" } else { trimmed }
+}
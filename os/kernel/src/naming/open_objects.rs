@@ -1,8 +1,8 @@
 /* ╔═════════════════════════════════════════════════════════════════════════╗
    ║ Module: open_objects                                                    ║
    ╟─────────────────────────────────────────────────────────────────────────╢
-   ║ Managing opened objects in a global table (OPEN_OBJECTS). And providing ║
-   ║ all major functions for the naming service.                             ║
+   ║ Managing opened objects in a table per process (see 'Process'). And     ║
+   ║ providing all major functions for the naming service.                   ║
    ╟─────────────────────────────────────────────────────────────────────────╢
    ║ Author: Michael Schoettner, Univ. Duesseldorf, 30.12.2024               ║
    ╚═════════════════════════════════════════════════════════════════════════╝
@@ -11,44 +11,45 @@ use alloc::string::String;
 use alloc::boxed::Box;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
+use core::ptr;
 use core::result::Result;
 use core::sync::atomic::{AtomicUsize, Ordering};
-use spin::{Mutex, Once};
 
-use super::traits::NamedObject;
+use super::traits::{FileObject, NamedObject};
+use super::access;
+use super::devfs;
 use super::lookup;
-use naming::shared_types::{DirEntry, OpenOptions, SeekOrigin};
+use super::stat::Stat;
+use crate::process_manager;
+use naming::shared_types::{DirEntry, FileType, OpenOptions, PollFlags, RawDirentHeader, SeekOrigin, STDERR, STDIN, STDOUT};
 use syscall::return_vals::{Errno, SyscallResult};
 
 
-/// Max. number of open objetcs
+/// Max. number of open objetcs per process
 const MAX_OPEN_OBJECTS: usize = 0x1000;
 
-/// Helper function returning safe access to the open object table (oot)
-fn get_open_object_table() -> Arc<Mutex<Box<OpenObjectTable>>> {
-    let oot: Arc<Mutex<Box<OpenObjectTable>>> = OPEN_OBJECTS.get().unwrap().clone();
-    return oot;
+/// Helper function looking up the 'OpenedObject' for `handle` in the table of the current process. \
+/// The table is only locked during the lookup, so blocking operations on the object do not block the table.
+fn lookup_opened_object(handle: usize) -> Result<Arc<OpenedObject>, Errno> {
+    let process = process_manager().read().current_process();
+    let mut open_objects = process.open_objects.lock();
+    open_objects.lookup_opened_object(handle).map(|opened_object| opened_object.clone())
 }
 
-static OPEN_OBJECTS: Once<Arc<Mutex<Box<OpenObjectTable>>>> = Once::new();
-
-struct OpenObjectTable {
+/// Table of opened objects, each process has its own one
+pub struct OpenObjectTable {
     open_handles: Vec<(usize, Option<Arc<OpenedObject>>)>,
     free_handles: Box<[usize; MAX_OPEN_OBJECTS]>,
 }
 
-
-pub(super) fn open_object_table_init() {
-    OPEN_OBJECTS.call_once(|| Arc::new(Mutex::new(OpenObjectTable::new())));
-}
-
 pub(super) fn open(path: &String, flags: OpenOptions) -> Result<usize, Errno> {
-    // try to open the named object for the given path
-    let result = lookup::lookup_named_object(path);
-    if result.is_err() {
-        return Err(Errno::ENOENT);
-    }
-    let found_named_object: NamedObject = result.unwrap();
+    // try to open the named object for the given path (or the link itself)
+    let result = if flags.contains(OpenOptions::NOFOLLOW) {
+        lookup::lookup_named_object_nofollow(path)
+    } else {
+        lookup::lookup_named_object(path)
+    };
+    let found_named_object: NamedObject = result?;
 
     // check if path is a directory and this was requested
     if flags.contains(OpenOptions::DIRECTORY) {
@@ -57,11 +58,52 @@ pub(super) fn open(path: &String, flags: OpenOptions) -> Result<usize, Errno> {
         }
     }
 
+    // directories cannot be written (only through the operations of the naming service)
+    if found_named_object.is_dir() && (flags.is_writable() || flags.contains(OpenOptions::TRUNCATE)) {
+        return Err(Errno::EISDIR);
+    }
+
+    // check permissions for the requested access (directories are checked by `readdir`)
+    if !found_named_object.is_dir() {
+        let mut requested = 0;
+        if flags.is_readable() {
+            requested |= access::READ;
+        }
+        if flags.is_writable() {
+            requested |= access::WRITE;
+        }
+        access::check(&found_named_object.stat()?, requested)?;
+    }
+
+    // some objects provide a different object for each open (e.g. the ends of a FIFO)
+    let opened_file = match &found_named_object {
+        NamedObject::FileObject(file) => {
+            if flags.contains(OpenOptions::TRUNCATE) {
+                if !flags.is_writable() {
+                    return Err(Errno::EINVAL);
+                }
+                if file.stat()?.mode.file_type() == FileType::Regular {
+                    file.truncate(0)?;
+                }
+            }
+            file.open(flags)?
+        }
+        NamedObject::DirectoryObject(_) => None,
+    };
+    let named_object = opened_file.map(NamedObject::from).unwrap_or(found_named_object);
+
+    open_object(named_object, flags)
+}
+
+/// Allocate a new handle for `named_object` in the table of the current process (used by `open` and for pipes)
+pub(super) fn open_object(named_object: NamedObject, flags: OpenOptions) -> Result<usize, Errno> {
     // try to allocate an new handle
-    let res = get_open_object_table()
+    let process = process_manager().read().current_process();
+    let res = process
+        .open_objects
         .lock()
         .allocate_handle(Arc::new(OpenedObject::new(
-            Arc::new(found_named_object),
+            Arc::new(named_object),
             AtomicUsize::new(0),
             flags,
         )));
@@ -69,13 +111,18 @@ pub(super) fn open(path: &String, flags: OpenOptions) -> Result<usize, Errno> {
 }
 
 pub(super) fn write(fh: usize, buf: &[u8]) -> Result<usize, Errno> {
-    get_open_object_table()
-        .lock()
-        .lookup_opened_object(fh)
+    lookup_opened_object(fh)
         .and_then(|opened_object| {
             // Make `opened_object` mutable here
+            if !opened_object.options.is_writable() {
+                return Err(Errno::EBADF);
+            }
             opened_object.named_object.as_file().and_then(|file| {
-                let pos = opened_object.pos.load(Ordering::SeqCst);
+                let pos = if opened_object.options.contains(OpenOptions::APPEND) {
+                    file.stat()?.size
+                } else {
+                    opened_object.pos.load(Ordering::SeqCst)
+                };
                 let bytes_written = file.write(buf, pos, opened_object.options)?;
                 opened_object
                     .pos
@@ -86,11 +133,12 @@ pub(super) fn write(fh: usize, buf: &[u8]) -> Result<usize, Errno> {
 }
 
 pub(super) fn read(fh: usize, buf: &mut [u8]) -> Result<usize, Errno> {
-    get_open_object_table()
-        .lock()
-        .lookup_opened_object(fh)
+    lookup_opened_object(fh)
         .and_then(|opened_object| {
             // Make `opened_object` mutable here
+            if !opened_object.options.is_readable() {
+                return Err(Errno::EBADF);
+            }
             opened_object.named_object.as_file().and_then(|file| {
                 let pos = opened_object.pos.load(Ordering::SeqCst);
                 let bytes_read = file.read(buf, pos, opened_object.options)?;
@@ -100,31 +148,27 @@ pub(super) fn read(fh: usize, buf: &mut [u8]) -> Result<usize, Errno> {
         })
 }
 
+/// Set the position of `fh`. For directories the position is the offset of the next entry
+/// returned by `readdir` or `getdents` (seeking relative to the end is not possible).
 pub fn seek(fh: usize, offset: usize, origin: SeekOrigin) -> Result<usize, Errno> {
-    get_open_object_table()
-        .lock()
-        .lookup_opened_object(fh)
+    lookup_opened_object(fh)
         .and_then(|opened_object| {
-            // Make `opened_object` mutable here
-            opened_object.named_object.as_file().and_then(|file| {
-                let new_pos = match origin {
-                    SeekOrigin::Start => offset,
-                    SeekOrigin::End => file.stat()?.size as usize + offset,
-                    SeekOrigin::Current => opened_object.pos.load(Ordering::SeqCst) + offset,
-                };
-                opened_object.pos.store(new_pos, Ordering::SeqCst);
-                Ok(0) // Success
-            })
+            let new_pos = match origin {
+                SeekOrigin::Start => offset,
+                SeekOrigin::End => opened_object.named_object.as_file()?.stat()?.size as usize + offset,
+                SeekOrigin::Current => opened_object.pos.load(Ordering::SeqCst) + offset,
+            };
+            opened_object.pos.store(new_pos, Ordering::SeqCst);
+            Ok(0) // Success
         })
 }
 
 pub(super) fn readdir(fh: usize) -> Result<Option<DirEntry>, Errno> {
-    let res = get_open_object_table()
-        .lock()
-        .lookup_opened_object(fh)
+    let res = lookup_opened_object(fh)
         .and_then(|opened_object| {
             // Make `opened_object` mutable here
             opened_object.named_object.as_dir().and_then(|dir| {
+                access::check(&dir.stat()?, access::READ)?;
                 let pos = opened_object.pos.load(Ordering::SeqCst);
                 let dir_entry = dir.readdir(pos)?;
                 opened_object.pos.store(pos + 1, Ordering::SeqCst);
@@ -137,22 +181,177 @@ pub(super) fn readdir(fh: usize) -> Result<Option<DirEntry>, Errno> {
     }
 }
 
+/// Fill `buf` with as many entries (`RawDirentHeader` followed by the name) of the directory referenced by `fh` as fit. \
+/// Returns the number of bytes written (0 at the end of the directory) or `Err(Errno::EINVAL)` if the next entry does not fit.
+pub(super) fn getdents(fh: usize, buf: &mut [u8]) -> Result<usize, Errno> {
+    let opened_object = lookup_opened_object(fh)?;
+    let dir = opened_object.named_object.as_dir()?;
+    access::check(&dir.stat()?, access::READ)?;
+
+    let mut written = 0;
+    let mut pos = opened_object.pos.load(Ordering::SeqCst);
+    while let Some(dir_entry) = dir.readdir(pos)? {
+        let name = &dir_entry.name.as_bytes()[..dir_entry.name.len().min(u16::MAX as usize)];
+        let record_len = RawDirentHeader::record_len(name.len());
+        if written + record_len > buf.len() {
+            if written == 0 {
+                return Err(Errno::EINVAL);
+            }
+            break;
+        }
+
+        let header = RawDirentHeader {
+            inode: dir_entry.inode,
+            next_offset: pos as u64 + 1,
+            record_len: record_len as u16,
+            name_len: name.len() as u16,
+            d_type: dir_entry.file_type as u8,
+        };
+        let record = &mut buf[written..written + record_len];
+        unsafe { ptr::write_unaligned(record.as_mut_ptr() as *mut RawDirentHeader, header); }
+        record[RawDirentHeader::SIZE..RawDirentHeader::SIZE + name.len()].copy_from_slice(name);
+        record[RawDirentHeader::SIZE + name.len()..].fill(0);
+
+        written += record_len;
+        pos += 1;
+        opened_object.pos.store(pos, Ordering::SeqCst);
+    }
+    Ok(written)
+}
+
+/// Set the size of the file referenced by `fh` (opened for writing) to `size` bytes
+pub(super) fn truncate(fh: usize, size: usize) -> Result<usize, Errno> {
+    lookup_opened_object(fh).and_then(|opened_object| {
+        if !opened_object.options.is_writable() {
+            return Err(Errno::EBADF);
+        }
+        opened_object.named_object.as_file()?.truncate(size).map(|_| 0)
+    })
+}
+
+/// Return the file referenced by `fh` together with the options used for opening it
+pub(super) fn file(fh: usize) -> Result<(Arc<dyn FileObject>, OpenOptions), Errno> {
+    let opened_object = lookup_opened_object(fh)?;
+    Ok((opened_object.named_object.as_file()?.clone(), opened_object.options))
+}
+
+/// Return the ready events of `fh` (directories are always readable, reading or writing
+/// is only reported if allowed by the options of the handle)
+pub(super) fn poll(fh: usize) -> PollFlags {
+    let opened_object = match lookup_opened_object(fh) {
+        Ok(opened_object) => opened_object,
+        Err(_) => return PollFlags::INVALID,
+    };
+
+    let mut events = match opened_object.named_object.as_ref() {
+        NamedObject::FileObject(file) => file.poll(),
+        NamedObject::DirectoryObject(_) => PollFlags::READABLE,
+    };
+    if !opened_object.options.is_readable() {
+        events.remove(PollFlags::READABLE);
+    }
+    if !opened_object.options.is_writable() {
+        events.remove(PollFlags::WRITABLE);
+    }
+    events
+}
+
+pub(super) fn fstat(fh: usize) -> Result<Stat, Errno> {
+    lookup_opened_object(fh).and_then(|opened_object| opened_object.named_object.stat())
+}
+
 pub(super) fn close(handle: usize) -> Result<usize, Errno> {
-    get_open_object_table().lock().free_handle(handle)
+    let process = process_manager().read().current_process();
+    let mut open_objects = process.open_objects.lock();
+    open_objects.free_handle(handle)
+}
+
+/// Allocate a new handle referencing the same opened object as `handle` (sharing the position)
+pub(super) fn dup(handle: usize) -> Result<usize, Errno> {
+    let process = process_manager().read().current_process();
+    let mut open_objects = process.open_objects.lock();
+    let opened_object = open_objects.lookup_opened_object(handle)?.clone();
+    open_objects.allocate_handle(opened_object)
+}
+
+/// Let `new_handle` reference the same opened object as `handle`, an object already using `new_handle` is closed
+pub(super) fn dup2(handle: usize, new_handle: usize) -> Result<usize, Errno> {
+    let process = process_manager().read().current_process();
+    let mut open_objects = process.open_objects.lock();
+    let opened_object = open_objects.lookup_opened_object(handle)?.clone();
+    if handle == new_handle {
+        return Ok(new_handle);
+    }
+    open_objects.insert_handle(new_handle, opened_object)
 }
 
 /*pub(super) fn dump() {
-    get_open_object_table().lock().dump();
+    process_manager().read().current_process().open_objects.lock().dump();
 }*/
 
 
 impl OpenObjectTable {
-    /// Create a new OpenObjectTable
-    fn new() -> Box<OpenObjectTable> {
-        Box::new(OpenObjectTable {
+    /// Create a new OpenObjectTable containing only the standard handles (`STDIN`, `STDOUT` and `STDERR`)
+    /// connected to the terminal
+    pub fn new() -> OpenObjectTable {
+        let mut table = OpenObjectTable::empty();
+        table.add_std_handles();
+        table
+    }
+
+    fn empty() -> OpenObjectTable {
+        OpenObjectTable {
             open_handles: Vec::new(),
             free_handles: Box::new([0; MAX_OPEN_OBJECTS]),
-        })
+        }
+    }
+
+    /// Connect all standard handles not used yet to the terminal
+    fn add_std_handles(&mut self) {
+        for (handle, options) in [(STDIN, OpenOptions::READONLY), (STDOUT, OpenOptions::WRITEONLY), (STDERR, OpenOptions::WRITEONLY)] {
+            if self.lookup_opened_object(handle).is_err() {
+                let terminal = Arc::new(NamedObject::from(devfs::terminal_file()));
+                let _ = self.insert_handle(handle, Arc::new(OpenedObject::new(terminal, AtomicUsize::new(0), options)));
+            }
+        }
+    }
+
+    /// Create the table for a new child process containing the given `handles` of this table. \
+    /// Each entry of `handles` is a pair `(handle in child, handle in this table)`. The child shares the
+    /// opened objects (including the current position) with this table. Standard handles not given in
+    /// `handles` are inherited from this table (or connected to the terminal, if this table has none). \
+    /// Returns `Ok(OpenObjectTable)` or `Err(Errno::EINVALH)` if a handle is invalid.
+    pub fn clone_handles(&mut self, handles: &[(usize, usize)]) -> Result<OpenObjectTable, Errno> {
+        let mut child = OpenObjectTable::empty();
+        for std_handle in [STDIN, STDOUT, STDERR] {
+            if let Ok(opened_object) = self.lookup_opened_object(std_handle) {
+                child.insert_handle(std_handle, opened_object.clone())?;
+            }
+        }
+        for &(child_handle, parent_handle) in handles {
+            let opened_object = self.lookup_opened_object(parent_handle)?.clone();
+            child.insert_handle(child_handle, opened_object)?;
+        }
+        child.add_std_handles();
+        Ok(child)
+    }
+
+    /// Close all handles (called when the owning process terminates)
+    pub fn close_all(&mut self) {
+        self.open_handles.clear();
+        self.free_handles.fill(0);
+    }
+
+    /// Insert `opened_object` with the given `handle`, an object already using `handle` is closed
+    fn insert_handle(&mut self, handle: usize, opened_object: Arc<OpenedObject>) -> Result<usize, Errno> {
+        if handle >= MAX_OPEN_OBJECTS {
+            return Err(Errno::EINVALH);
+        }
+
+        let _ = self.free_handle(handle);
+        self.free_handles[handle] = 1;
+        self.open_handles.push((handle, Some(opened_object)));
+        Ok(handle)
     }
 
     /// Lookup an 'OpenedObject' for a given handle
@@ -189,7 +388,7 @@ impl OpenObjectTable {
             // Remove the handle from `open_handles`
             self.open_handles.swap_remove(index);
             // set handle as free
-            self.free_handles[index] = 0;
+            self.free_handles[opened_object_handle] = 0;
             Ok(0)
         } else {
             // Handle not found
@@ -0,0 +1,271 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: pipe                                                            ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Anonymous pipes: a bounded ring buffer in the kernel with a read end    ║
+   ║ and a write end, both used through the handles of the naming service.   ║
+   ║ Reading from an empty pipe blocks until data is available or the write  ║
+   ║ end is closed (end of file), writing into a full pipe blocks until the  ║
+   ║ reader has consumed data. Writing without a reader fails with EPIPE.    ║
+   ║ Named FIFOs (see 'Fifo') are pipes with a name in a file system. Each   ║
+   ║ open creates a new read or write end and blocks until the other side    ║
+   ║ has been opened as well.                                                ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use core::fmt;
+use core::result::Result;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use spin::{Mutex, RwLock};
+
+use super::stat::{Mode, Stat, MODE_FIFO};
+use super::traits::FileObject;
+use crate::process::scheduler::WaitQueue;
+use crate::scheduler;
+use naming::shared_types::{OpenOptions, PollFlags};
+use syscall::return_vals::Errno;
+
+/// Capacity of the ring buffer of a pipe
+const PIPE_SIZE: usize = 4096;
+
+/// Create a new pipe and return its read end and its write end
+pub(super) fn create() -> (Arc<dyn FileObject>, Arc<dyn FileObject>) {
+    let pipe = Arc::new(Pipe::new());
+    (Arc::new(PipeReader::new(pipe.clone())), Arc::new(PipeWriter::new(pipe)))
+}
+
+/// Buffer shared by all ends of a pipe
+struct Pipe {
+    buffer: Mutex<VecDeque<u8>>,
+    readers: AtomicUsize,      // number of open read ends
+    writers: AtomicUsize,      // number of open write ends
+    reader_opens: AtomicUsize, // number of read ends opened so far
+    writer_opens: AtomicUsize, // number of write ends opened so far
+    queue: WaitQueue,          // threads waiting for data, buffer space or the other side
+}
+
+impl Pipe {
+    fn new() -> Pipe {
+        Pipe {
+            buffer: Mutex::new(VecDeque::with_capacity(PIPE_SIZE)),
+            readers: AtomicUsize::new(0),
+            writers: AtomicUsize::new(0),
+            reader_opens: AtomicUsize::new(0),
+            writer_opens: AtomicUsize::new(0),
+            queue: WaitQueue::new(),
+        }
+    }
+
+    fn stat(&self) -> Stat {
+        Stat::new(Mode::new(MODE_FIFO), self.buffer.lock().len())
+    }
+}
+
+/// Named pipe stored in a file system. The FIFO itself cannot be read or written,
+/// opening it creates a read end or a write end (see `open`).
+pub(super) struct Fifo {
+    pipe: Arc<Pipe>,
+    stat: RwLock<Stat>,
+}
+
+impl Fifo {
+    pub(super) fn new(permissions: u32, uid: u32, gid: u32) -> Fifo {
+        Fifo {
+            pipe: Arc::new(Pipe::new()),
+            stat: RwLock::new(Stat::created(Mode::new(MODE_FIFO).with_permissions(permissions), uid, gid)),
+        }
+    }
+}
+
+impl FileObject for Fifo {
+    fn stat(&self) -> Result<Stat, Errno> {
+        Ok(Stat {
+            size: self.pipe.buffer.lock().len(),
+            ..*self.stat.read()
+        })
+    }
+
+    fn chmod(&self, permissions: u32) -> Result<(), Errno> {
+        let mut stat = self.stat.write();
+        stat.mode = stat.mode.with_permissions(permissions);
+        Ok(())
+    }
+
+    fn chown(&self, uid: u32, gid: u32) -> Result<(), Errno> {
+        let mut stat = self.stat.write();
+        stat.uid = uid;
+        stat.gid = gid;
+        Ok(())
+    }
+
+    fn utimes(&self, accessed: Option<u64>, modified: Option<u64>) -> Result<(), Errno> {
+        self.stat.write().set_times(accessed, modified);
+        Ok(())
+    }
+
+    /// Create a write end if opened for writing, otherwise a read end. Blocks until the other side has been opened
+    /// (a counterpart opened and already closed in the meantime also counts).
+    fn open(&self, options: OpenOptions) -> Result<Option<Arc<dyn FileObject>>, Errno> {
+        let pipe = &self.pipe;
+        let end: Arc<dyn FileObject>;
+        let (others, other_opens) = if options.is_writable() {
+            end = Arc::new(PipeWriter::new(pipe.clone()));
+            (&pipe.readers, &pipe.reader_opens)
+        } else {
+            end = Arc::new(PipeReader::new(pipe.clone()));
+            (&pipe.writers, &pipe.writer_opens)
+        };
+
+        let opens = other_opens.load(Ordering::SeqCst);
+        scheduler().wait(&pipe.queue, None, || {
+            others.load(Ordering::SeqCst) > 0 || other_opens.load(Ordering::SeqCst) != opens
+        });
+        Ok(Some(end))
+    }
+}
+
+impl fmt::Debug for Fifo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Fifo").finish()
+    }
+}
+
+/// Read end of a pipe (or FIFO), closed when the last handle referencing it is closed
+struct PipeReader {
+    pipe: Arc<Pipe>,
+}
+
+impl PipeReader {
+    fn new(pipe: Arc<Pipe>) -> PipeReader {
+        pipe.readers.fetch_add(1, Ordering::SeqCst);
+        pipe.reader_opens.fetch_add(1, Ordering::SeqCst);
+        scheduler().wake_up(&pipe.queue);
+        PipeReader { pipe }
+    }
+}
+
+impl FileObject for PipeReader {
+    fn stat(&self) -> Result<Stat, Errno> {
+        Ok(self.pipe.stat())
+    }
+
+    fn read(&self, buf: &mut [u8], _offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        loop {
+            {
+                let mut buffer = self.pipe.buffer.lock();
+                if !buffer.is_empty() {
+                    let len = buf.len().min(buffer.len());
+                    for (byte, data) in buf.iter_mut().zip(buffer.drain(..len)) {
+                        *byte = data;
+                    }
+                    drop(buffer);
+
+                    // Wake up writers waiting for buffer space
+                    scheduler().wake_up(&self.pipe.queue);
+                    return Ok(len);
+                }
+
+                // Pipe is empty and cannot be filled anymore -> end of file
+                if self.pipe.writers.load(Ordering::SeqCst) == 0 {
+                    return Ok(0);
+                }
+            }
+
+            scheduler().wait(&self.pipe.queue, None, || {
+                !self.pipe.buffer.lock().is_empty() || self.pipe.writers.load(Ordering::SeqCst) == 0
+            });
+        }
+    }
+
+    /// Readable if data is available or all write ends are closed (end of file, reported as hangup)
+    fn poll(&self) -> PollFlags {
+        let mut events = PollFlags::empty();
+        if !self.pipe.buffer.lock().is_empty() {
+            events |= PollFlags::READABLE;
+        }
+        if self.pipe.writers.load(Ordering::SeqCst) == 0 {
+            events |= PollFlags::READABLE | PollFlags::HANGUP;
+        }
+        events
+    }
+}
+
+impl Drop for PipeReader {
+    fn drop(&mut self) {
+        self.pipe.readers.fetch_sub(1, Ordering::SeqCst);
+        scheduler().wake_up(&self.pipe.queue);
+    }
+}
+
+impl fmt::Debug for PipeReader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PipeReader").finish()
+    }
+}
+
+/// Write end of a pipe (or FIFO), closed when the last handle referencing it is closed
+struct PipeWriter {
+    pipe: Arc<Pipe>,
+}
+
+impl PipeWriter {
+    fn new(pipe: Arc<Pipe>) -> PipeWriter {
+        pipe.writers.fetch_add(1, Ordering::SeqCst);
+        pipe.writer_opens.fetch_add(1, Ordering::SeqCst);
+        scheduler().wake_up(&pipe.queue);
+        PipeWriter { pipe }
+    }
+}
+
+impl FileObject for PipeWriter {
+    fn stat(&self) -> Result<Stat, Errno> {
+        Ok(self.pipe.stat())
+    }
+
+    fn write(&self, buf: &[u8], _offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        let mut written = 0;
+        while written < buf.len() {
+            if self.pipe.readers.load(Ordering::SeqCst) == 0 {
+                return if written > 0 { Ok(written) } else { Err(Errno::EPIPE) };
+            }
+
+            {
+                let mut buffer = self.pipe.buffer.lock();
+                let len = (PIPE_SIZE - buffer.len()).min(buf.len() - written);
+                buffer.extend(&buf[written..written + len]);
+                written += len;
+            }
+
+            // Wake up readers and wait for buffer space (or the last reader to close)
+            scheduler().wake_up(&self.pipe.queue);
+            if written < buf.len() {
+                scheduler().wait(&self.pipe.queue, None, || {
+                    self.pipe.buffer.lock().len() < PIPE_SIZE || self.pipe.readers.load(Ordering::SeqCst) == 0
+                });
+            }
+        }
+
+        Ok(written)
+    }
+
+    /// Writable if the buffer is not full. Without a reader writing fails immediately (reported as hangup).
+    fn poll(&self) -> PollFlags {
+        if self.pipe.readers.load(Ordering::SeqCst) == 0 {
+            return PollFlags::WRITABLE | PollFlags::HANGUP;
+        }
+        if self.pipe.buffer.lock().len() < PIPE_SIZE { PollFlags::WRITABLE } else { PollFlags::empty() }
+    }
+}
+
+impl Drop for PipeWriter {
+    fn drop(&mut self) {
+        self.pipe.writers.fetch_sub(1, Ordering::SeqCst);
+        scheduler().wake_up(&self.pipe.queue);
+    }
+}
+
+impl fmt::Debug for PipeWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PipeWriter").finish()
+    }
+}
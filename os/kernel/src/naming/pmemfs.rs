@@ -0,0 +1,1166 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: pmemfs                                                          ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Crash-consistent file system stored in non-volatile memory (NVDIMM),    ║
+   ║ found in the ACPI NFIT (see 'memory::nvmem'). Meant for small config    ║
+   ║ and state files, which have to survive a power loss without a disk.     ║
+   ║ The first page of the region is skipped (last boot time, 'boot.rs').    ║
+   ║                                                                         ║
+   ║ Layout of a volume (4 KiB blocks):                                      ║
+   ║   - superblock (block 0), undo log, block bitmap, inode table           ║
+   ║   - data blocks (files, symbolic links and directories, which consist   ║
+   ║     of 128 byte entries with inode number and name)                     ║
+   ║ Every operation is a transaction. Before a cache line is modified in    ║
+   ║ place, its old content is appended to the undo log and made durable     ║
+   ║ (clflush and NFIT flush hints). Committing makes the new content        ║
+   ║ durable and empties the log. An incomplete transaction is rolled back   ║
+   ║ when mounting, so an operation is either completely visible or not at   ║
+   ║ all. Blocks allocated by a transaction are written without logging      ║
+   ║ (they are unreferenced after a rollback) and freed blocks are released  ║
+   ║ when committing (they cannot be reused by the same transaction).        ║
+   ║ Large writes are split into several transactions.                       ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt;
+use core::mem::{self, size_of};
+use core::ptr;
+use core::result::Result;
+use core::slice;
+use log::{info, warn};
+use spin::{Mutex, MutexGuard};
+use x86_64::structures::paging::frame::PhysFrameRange;
+
+use super::access;
+use super::stat::{self, Mode, Stat, DIR_PERMISSIONS, MODE_DIR, MODE_FILE, MODE_LINK, ROOT_UID};
+use super::traits::{DirectoryObject, FileObject, FileSystem, NamedObject};
+use crate::memory::{nvmem, PAGE_SIZE};
+use naming::shared_types::{DirEntry, FileType, OpenOptions};
+use syscall::return_vals::Errno;
+
+const MAGIC: [u8; 8] = *b"D3PMEMFS";
+const VERSION: u32 = 1;
+const BLOCK_SIZE: usize = 4096;
+
+/// The first page of the region is left untouched (it holds the last boot time, see `boot.rs`)
+const RESERVED_SIZE: usize = PAGE_SIZE;
+
+/// Smallest volume and smallest undo log (in blocks). Otherwise the log gets 1/64 of the volume,
+/// which is enough for freeing all blocks of the volume within one transaction.
+const MIN_BLOCKS: usize = 128;
+const MIN_LOG_BLOCKS: usize = 64;
+
+/// One inode per 16 KiB of the volume
+const BYTES_PER_INODE: usize = 16 * 1024;
+const INODE_SIZE: usize = 128;
+const ROOT_INODE: u32 = 1;
+
+/// Block pointers of an inode: 12 direct ones, followed by a single and a double indirect one
+const DIRECT_BLOCKS: usize = 12;
+const POINTERS_PER_BLOCK: usize = BLOCK_SIZE / size_of::<u32>();
+const MAX_FILE_SIZE: usize = (DIRECT_BLOCKS + POINTERS_PER_BLOCK + POINTERS_PER_BLOCK * POINTERS_PER_BLOCK) * BLOCK_SIZE;
+
+/// Directory entry: inode number (0 = unused entry), length of the name, reserved, name
+const ENTRY_SIZE: usize = 128;
+const ENTRY_HEADER: usize = 8;
+const MAX_NAME_LENGTH: usize = ENTRY_SIZE - ENTRY_HEADER;
+
+/// Granularity of the undo log (size of a cache line)
+const LINE_SIZE: usize = 64;
+/// A log record consists of offset and length of a range, followed by its old content
+const RECORD_HEADER: usize = 16;
+
+/// Maximum number of bytes written by one transaction (larger writes are split to fit into the log)
+const WRITE_CHUNK: usize = 16 * BLOCK_SIZE;
+
+const ZEROS: [u8; BLOCK_SIZE] = [0; BLOCK_SIZE];
+
+pub struct PmemFs {
+    volume: Arc<Volume>,
+    root_generation: u32,
+}
+
+impl PmemFs {
+    /// Open the file system stored in the non-volatile memory `range` (formatting it, if it contains none). \
+    /// Returns `Err(Errno::EINVAL)` if the range is too small or contains an incompatible file system.
+    pub fn new(range: PhysFrameRange) -> Result<PmemFs, Errno> {
+        // The range is identity mapped by `nvmem::init`
+        let start = range.start.start_address().as_u64() as usize;
+        let end = range.end.start_address().as_u64() as usize;
+        let pmem = Pmem {
+            base: (start + RESERVED_SIZE) as *mut u8,
+            size: (end - start).saturating_sub(RESERVED_SIZE) / BLOCK_SIZE * BLOCK_SIZE,
+        };
+
+        let volume = Volume::open(pmem)?;
+        info!(
+            "pmemfs: found volume with [{}] blocks of [{}] bytes and [{}] inodes",
+            volume.block_count, BLOCK_SIZE, volume.inode_count
+        );
+
+        let root_generation = volume.begin().object_inode(ROOT_INODE, None)?.generation;
+        Ok(PmemFs {
+            volume: Arc::new(volume),
+            root_generation,
+        })
+    }
+}
+
+impl FileSystem for PmemFs {
+    fn root_dir(&self) -> Arc<dyn DirectoryObject> {
+        Arc::new(Dir::new(self.volume.clone(), ROOT_INODE, self.root_generation))
+    }
+}
+
+/// Superblock, stored in block 0 (the layout only depends on the number of blocks)
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq)]
+struct Superblock {
+    magic: [u8; 8],
+    version: u32,
+    block_size: u32,
+    block_count: u64,
+    inode_count: u64,
+    log_start: u64,
+    log_blocks: u64,
+    bitmap_start: u64,
+    inode_table_start: u64,
+    data_start: u64,
+}
+
+impl Superblock {
+    /// Compute the layout of a volume with `block_count` blocks (the magic is not set)
+    fn new(block_count: usize) -> Superblock {
+        let log_blocks = (block_count / 64).max(MIN_LOG_BLOCKS);
+        let bitmap_blocks = block_count.div_ceil(BLOCK_SIZE * 8);
+        let inode_count = (block_count * BLOCK_SIZE / BYTES_PER_INODE).next_multiple_of(BLOCK_SIZE / INODE_SIZE);
+        let bitmap_start = 1 + log_blocks;
+        let inode_table_start = bitmap_start + bitmap_blocks;
+
+        Superblock {
+            magic: [0; 8],
+            version: VERSION,
+            block_size: BLOCK_SIZE as u32,
+            block_count: block_count as u64,
+            inode_count: inode_count as u64,
+            log_start: 1,
+            log_blocks: log_blocks as u64,
+            bitmap_start: bitmap_start as u64,
+            inode_table_start: inode_table_start as u64,
+            data_start: (inode_table_start + inode_count * INODE_SIZE / BLOCK_SIZE) as u64,
+        }
+    }
+}
+
+/// The part of the non-volatile memory region used by the file system
+struct Pmem {
+    base: *mut u8,
+    size: usize,
+}
+
+// The memory is only accessed while holding the lock on the log (see `Transaction`)
+unsafe impl Send for Pmem {}
+unsafe impl Sync for Pmem {}
+
+impl Pmem {
+    fn read(&self, offset: usize, buf: &mut [u8]) {
+        assert!(offset + buf.len() <= self.size, "pmemfs: read out of bounds");
+        unsafe { ptr::copy_nonoverlapping(self.base.add(offset), buf.as_mut_ptr(), buf.len()) }
+    }
+
+    /// Write `data` at `offset` and flush the cache lines (durable after `nvmem::drain`)
+    fn write(&self, offset: usize, data: &[u8]) {
+        assert!(offset + data.len() <= self.size, "pmemfs: write out of bounds");
+        unsafe { ptr::copy_nonoverlapping(data.as_ptr(), self.base.add(offset), data.len()) }
+        nvmem::flush(self.base as usize + offset, data.len());
+    }
+
+    fn zero(&self, offset: usize, len: usize) {
+        for start in (offset..offset + len).step_by(BLOCK_SIZE) {
+            self.write(start, &ZEROS[..BLOCK_SIZE.min(offset + len - start)]);
+        }
+    }
+
+    fn read_u8(&self, offset: usize) -> u8 {
+        let mut buf = [0u8; 1];
+        self.read(offset, &mut buf);
+        buf[0]
+    }
+
+    fn read_u32(&self, offset: usize) -> u32 {
+        let mut buf = [0u8; 4];
+        self.read(offset, &mut buf);
+        u32::from_le_bytes(buf)
+    }
+
+    fn read_u64(&self, offset: usize) -> u64 {
+        let mut buf = [0u8; 8];
+        self.read(offset, &mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    fn read_struct<T: Copy>(&self, offset: usize) -> T {
+        assert!(offset + size_of::<T>() <= self.size, "pmemfs: read out of bounds");
+        unsafe { ptr::read_unaligned(self.base.add(offset).cast::<T>()) }
+    }
+}
+
+/// State of the undo log (the number of records is also stored durably at the start of the log)
+struct Log {
+    records: u64,
+    /// Offset behind the last record
+    end: usize,
+}
+
+struct Volume {
+    pmem: Pmem,
+    block_count: usize,
+    inode_count: usize,
+    /// Offsets of the log (starting with its header), the bitmap and the inode table in bytes
+    log_start: usize,
+    log_end: usize,
+    bitmap_start: usize,
+    inode_table_start: usize,
+    /// Number of the first data block
+    data_start: usize,
+    log: Mutex<Log>,
+}
+
+impl Volume {
+    fn open(pmem: Pmem) -> Result<Volume, Errno> {
+        let block_count = pmem.size / BLOCK_SIZE;
+        if block_count < MIN_BLOCKS {
+            return Err(Errno::EINVAL);
+        }
+
+        let mut superblock = pmem.read_struct::<Superblock>(0);
+        if superblock.magic != MAGIC {
+            info!("pmemfs: no file system found, formatting [{}] blocks", block_count);
+            superblock = Superblock::new(block_count);
+            format(&pmem, &superblock);
+            superblock.magic = MAGIC;
+        }
+
+        // The layout is derived from the number of blocks, which must fit into the region
+        if superblock.block_count as usize > block_count
+            || superblock != (Superblock { magic: MAGIC, ..Superblock::new(superblock.block_count as usize) })
+        {
+            return Err(Errno::EINVAL);
+        }
+
+        let log_start = superblock.log_start as usize * BLOCK_SIZE;
+        let volume = Volume {
+            pmem,
+            block_count: superblock.block_count as usize,
+            inode_count: superblock.inode_count as usize,
+            log_start,
+            log_end: log_start + superblock.log_blocks as usize * BLOCK_SIZE,
+            bitmap_start: superblock.bitmap_start as usize * BLOCK_SIZE,
+            inode_table_start: superblock.inode_table_start as usize * BLOCK_SIZE,
+            data_start: superblock.data_start as usize,
+            log: Mutex::new(Log {
+                records: 0,
+                end: log_start + LINE_SIZE,
+            }),
+        };
+
+        volume.recover()?;
+        Ok(volume)
+    }
+
+    /// Start a transaction (waits until the running transaction is finished)
+    fn begin(&self) -> Transaction<'_> {
+        Transaction {
+            volume: self,
+            log: self.log.lock(),
+            logged: BTreeSet::new(),
+            allocated: BTreeSet::new(),
+            freed: Vec::new(),
+        }
+    }
+
+    /// Roll back a transaction interrupted by a crash (records left in the log)
+    fn recover(&self) -> Result<(), Errno> {
+        let mut log = self.log.lock();
+        log.records = self.pmem.read_u64(self.log_start);
+        if log.records > 0 {
+            warn!("pmemfs: rolling back incomplete transaction ([{}] log records)", log.records);
+            self.roll_back(&mut log)?;
+        }
+        Ok(())
+    }
+
+    /// Restore the old content of all ranges in the log (in reverse order) and empty the log. \
+    /// If this is interrupted, it is repeated when mounting the next time.
+    fn roll_back(&self, log: &mut Log) -> Result<(), Errno> {
+        let mut records = Vec::new();
+        let mut pos = self.log_start + LINE_SIZE;
+        for _ in 0..log.records {
+            if pos + RECORD_HEADER > self.log_end {
+                return Err(Errno::EIO);
+            }
+            let offset = self.pmem.read_u64(pos) as usize;
+            let len = self.pmem.read_u64(pos + 8) as usize;
+            let outside_log = offset.checked_add(len).is_some_and(|end| end <= self.log_start || offset >= self.log_end);
+            if len > self.log_end - pos - RECORD_HEADER || !outside_log || offset + len > self.pmem.size {
+                return Err(Errno::EIO);
+            }
+            records.push((offset, len, pos + RECORD_HEADER));
+            pos += RECORD_HEADER + len;
+        }
+
+        let mut buffer = Vec::new();
+        for &(offset, len, old_content) in records.iter().rev() {
+            buffer.resize(len, 0);
+            self.pmem.read(old_content, &mut buffer);
+            self.pmem.write(offset, &buffer);
+        }
+        nvmem::drain();
+
+        self.clear_log(log);
+        Ok(())
+    }
+
+    fn clear_log(&self, log: &mut Log) {
+        self.pmem.write(self.log_start, &0u64.to_le_bytes());
+        nvmem::drain();
+        log.records = 0;
+        log.end = self.log_start + LINE_SIZE;
+    }
+
+    /// Offset of the data block `block` (block numbers read from the volume are checked)
+    fn block_offset(&self, block: u32) -> Result<usize, Errno> {
+        let block = block as usize;
+        if block < self.data_start || block >= self.block_count {
+            return Err(Errno::EIO);
+        }
+        Ok(block * BLOCK_SIZE)
+    }
+
+    fn inode_offset(&self, number: u32) -> Result<usize, Errno> {
+        if number == 0 || number as usize > self.inode_count {
+            return Err(Errno::EIO);
+        }
+        Ok(self.inode_table_start + (number as usize - 1) * INODE_SIZE)
+    }
+
+    /// Update the inode `number` of an object in a transaction of its own
+    fn update_inode(&self, number: u32, generation: u32, update: impl FnOnce(&mut Inode)) -> Result<(), Errno> {
+        let mut tx = self.begin();
+        let mut inode = tx.object_inode(number, Some(generation))?;
+        update(&mut inode);
+        tx.write_inode(number, &inode)?;
+        tx.commit()
+    }
+
+    fn named_object(self: &Arc<Self>, number: u32, inode: &Inode) -> NamedObject {
+        if inode.is_dir() {
+            NamedObject::DirectoryObject(Arc::new(Dir::new(self.clone(), number, inode.generation)))
+        } else {
+            NamedObject::FileObject(Arc::new(File::new(self.clone(), number, inode.generation)))
+        }
+    }
+}
+
+/// Create an empty file system with the root directory. \
+/// The magic is written last, so an interrupted formatting is repeated when mounting the next time.
+fn format(pmem: &Pmem, superblock: &Superblock) {
+    let data_start = superblock.data_start as usize;
+    let bitmap_start = superblock.bitmap_start as usize * BLOCK_SIZE;
+
+    // Empty log, bitmap with all blocks up to the first data block in use and only free inodes
+    pmem.write(superblock.log_start as usize * BLOCK_SIZE, &0u64.to_le_bytes());
+    pmem.zero(bitmap_start, (data_start - superblock.bitmap_start as usize) * BLOCK_SIZE);
+    pmem.write(bitmap_start, &vec![0xff; data_start / 8]);
+    pmem.write(bitmap_start + data_start / 8, &[(1u8 << (data_start % 8)) - 1]);
+
+    let root = Inode::new(Mode::new(MODE_DIR).with_permissions(DIR_PERMISSIONS), ROOT_UID, 0, 1);
+    pmem.write(superblock.inode_table_start as usize * BLOCK_SIZE, as_bytes(&root));
+    pmem.write(0, as_bytes(superblock));
+    nvmem::drain();
+
+    pmem.write(0, &MAGIC);
+    nvmem::drain();
+}
+
+/// All accesses to the file system are done within a transaction, which holds the lock on the log. \
+/// Dropping a transaction without calling `commit` rolls back all its modifications.
+struct Transaction<'a> {
+    volume: &'a Volume,
+    log: MutexGuard<'a, Log>,
+    /// Cache lines (offset / `LINE_SIZE`), whose old content is already in the log
+    logged: BTreeSet<usize>,
+    /// Blocks allocated by this transaction (modified without logging)
+    allocated: BTreeSet<usize>,
+    /// Blocks to be released by `commit`
+    freed: Vec<u32>,
+}
+
+impl Transaction<'_> {
+    /// Make all modifications durable and empty the log
+    fn commit(mut self) -> Result<(), Errno> {
+        for block in mem::take(&mut self.freed) {
+            self.set_block_used(block, false)?;
+        }
+
+        // Every modification has logged at least one record (e.g. for allocating a block)
+        if self.log.records > 0 {
+            nvmem::drain();
+            self.volume.clear_log(&mut self.log);
+        }
+        Ok(())
+    }
+
+    fn read(&self, offset: usize, buf: &mut [u8]) {
+        self.volume.pmem.read(offset, buf);
+    }
+
+    /// Write `data` at `offset` (within one block), logging the old content before
+    /// (unless the block has been allocated by this transaction)
+    fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), Errno> {
+        if !self.allocated.contains(&(offset / BLOCK_SIZE)) {
+            self.log_range(offset, data.len())?;
+        }
+        self.volume.pmem.write(offset, data);
+        Ok(())
+    }
+
+    /// Append the old content of all cache lines covering the range to the log (if not logged yet)
+    fn log_range(&mut self, offset: usize, len: usize) -> Result<(), Errno> {
+        let mut run: Option<(usize, usize)> = None;
+        for line in offset / LINE_SIZE..(offset + len).div_ceil(LINE_SIZE) {
+            if self.logged.insert(line) {
+                let (_, count) = run.get_or_insert((line, 0));
+                *count += 1;
+            } else if let Some((first, count)) = run.take() {
+                self.append_record(first * LINE_SIZE, count * LINE_SIZE)?;
+            }
+        }
+        if let Some((first, count)) = run {
+            self.append_record(first * LINE_SIZE, count * LINE_SIZE)?;
+        }
+        Ok(())
+    }
+
+    /// Append a record with the old content of `len` bytes at `offset` to the log. \
+    /// The record is durable before it is counted and counted before the range is modified.
+    fn append_record(&mut self, offset: usize, len: usize) -> Result<(), Errno> {
+        let volume = self.volume;
+        let pos = self.log.end;
+        if pos + RECORD_HEADER + len > volume.log_end {
+            return Err(Errno::ENOSPC);
+        }
+
+        let mut record = vec![0u8; RECORD_HEADER + len];
+        record[0..8].copy_from_slice(&(offset as u64).to_le_bytes());
+        record[8..16].copy_from_slice(&(len as u64).to_le_bytes());
+        volume.pmem.read(offset, &mut record[RECORD_HEADER..]);
+        volume.pmem.write(pos, &record);
+        nvmem::drain();
+
+        self.log.records += 1;
+        volume.pmem.write(volume.log_start, &self.log.records.to_le_bytes());
+        nvmem::drain();
+        self.log.end = pos + record.len();
+        Ok(())
+    }
+
+    fn set_block_used(&mut self, block: u32, used: bool) -> Result<(), Errno> {
+        let offset = self.volume.bitmap_start + block as usize / 8;
+        let mut byte = self.volume.pmem.read_u8(offset);
+        if used {
+            byte |= 1 << (block % 8);
+        } else {
+            byte &= !(1 << (block % 8));
+        }
+        self.write(offset, &[byte])
+    }
+
+    /// Allocate a data block filled with zeros
+    fn allocate_block(&mut self) -> Result<u32, Errno> {
+        let volume = self.volume;
+        let mut block = volume.data_start;
+        while block < volume.block_count {
+            let byte = volume.pmem.read_u8(volume.bitmap_start + block / 8);
+            if byte == 0xff {
+                block = (block / 8 + 1) * 8;
+            } else if byte & (1 << (block % 8)) != 0 {
+                block += 1;
+            } else {
+                self.set_block_used(block as u32, true)?;
+                self.allocated.insert(block);
+                volume.pmem.zero(block * BLOCK_SIZE, BLOCK_SIZE);
+                return Ok(block as u32);
+            }
+        }
+        Err(Errno::ENOSPC)
+    }
+
+    /// Free the data block `block` (released by `commit`)
+    fn free_block(&mut self, block: u32) -> Result<(), Errno> {
+        self.volume.block_offset(block)?;
+        self.freed.push(block);
+        Ok(())
+    }
+
+    fn read_inode(&self, number: u32) -> Result<Inode, Errno> {
+        Ok(self.volume.pmem.read_struct(self.volume.inode_offset(number)?))
+    }
+
+    fn write_inode(&mut self, number: u32, inode: &Inode) -> Result<(), Errno> {
+        self.write(self.volume.inode_offset(number)?, as_bytes(inode))
+    }
+
+    /// Read the inode of a file or directory object, which must still exist (`generation` detects
+    /// objects whose inode has been removed and reused afterwards)
+    fn object_inode(&self, number: u32, generation: Option<u32>) -> Result<Inode, Errno> {
+        let inode = self.read_inode(number)?;
+        if inode.mode == 0 || generation.is_some_and(|generation| generation != inode.generation) {
+            return Err(Errno::ENOENT);
+        }
+        Ok(inode)
+    }
+
+    /// Find a free inode, returns its number and the generation for the next object using it
+    fn free_inode(&self) -> Result<(u32, u32), Errno> {
+        for number in 1..=self.volume.inode_count as u32 {
+            let inode = self.read_inode(number)?;
+            if inode.mode == 0 {
+                return Ok((number, inode.generation.wrapping_add(1)));
+            }
+        }
+        Err(Errno::ENOSPC)
+    }
+
+    /// Free the inode `number` and all its blocks (the generation is kept)
+    fn release_inode(&mut self, number: u32, inode: &mut Inode) -> Result<(), Errno> {
+        self.free_blocks(inode, 0)?;
+        *inode = Inode::free(inode.generation);
+        self.write_inode(number, inode)
+    }
+
+    /// Return the data block with `index` of `inode` (0 = hole)
+    fn data_block(&self, inode: &Inode, index: usize) -> Result<u32, Errno> {
+        let (slot, path) = block_path(index)?;
+        let mut block = inode.blocks[slot];
+        for index in path {
+            if block == 0 {
+                break;
+            }
+            block = self.volume.pmem.read_u32(self.volume.block_offset(block)? + index * size_of::<u32>());
+        }
+        Ok(block)
+    }
+
+    /// Return the data block with `index` of `inode`, allocating missing blocks (the caller writes back `inode`)
+    fn map_block(&mut self, inode: &mut Inode, index: usize) -> Result<u32, Errno> {
+        let (slot, path) = block_path(index)?;
+        if inode.blocks[slot] == 0 {
+            inode.blocks[slot] = self.allocate_block()?;
+        }
+
+        let mut block = inode.blocks[slot];
+        for index in path {
+            let entry = self.volume.block_offset(block)? + index * size_of::<u32>();
+            let mut next = self.volume.pmem.read_u32(entry);
+            if next == 0 {
+                next = self.allocate_block()?;
+                self.write(entry, &next.to_le_bytes())?;
+            }
+            block = next;
+        }
+        Ok(block)
+    }
+
+    /// Free all data blocks of `inode` starting with block index `first` (the caller writes back `inode`)
+    fn free_blocks(&mut self, inode: &mut Inode, first: usize) -> Result<(), Errno> {
+        for slot in first.min(DIRECT_BLOCKS)..DIRECT_BLOCKS {
+            if inode.blocks[slot] != 0 {
+                self.free_block(inode.blocks[slot])?;
+                inode.blocks[slot] = 0;
+            }
+        }
+
+        let mut start = DIRECT_BLOCKS;
+        for (slot, level) in [(DIRECT_BLOCKS, 1), (DIRECT_BLOCKS + 1, 2)] {
+            let span = POINTERS_PER_BLOCK.pow(level);
+            let block = inode.blocks[slot];
+            if block != 0 && first < start + span && self.free_tree(block, level, first.saturating_sub(start))? {
+                inode.blocks[slot] = 0;
+            }
+            start += span;
+        }
+        Ok(())
+    }
+
+    /// Free the blocks of the tree below `block` (`level` 0 = data block) starting with block index `first`
+    /// within the tree. Returns `true` if `block` itself has been freed.
+    fn free_tree(&mut self, block: u32, level: u32, first: usize) -> Result<bool, Errno> {
+        if level > 0 {
+            let span = POINTERS_PER_BLOCK.pow(level - 1);
+            let offset = self.volume.block_offset(block)?;
+            for index in first / span..POINTERS_PER_BLOCK {
+                let entry = offset + index * size_of::<u32>();
+                let child = self.volume.pmem.read_u32(entry);
+                if child == 0 {
+                    continue;
+                }
+
+                let child_first = if index == first / span { first % span } else { 0 };
+                if self.free_tree(child, level - 1, child_first)? && first > 0 {
+                    self.write(entry, &0u32.to_le_bytes())?;
+                }
+            }
+        }
+
+        if first > 0 {
+            return Ok(false);
+        }
+        self.free_block(block)?;
+        Ok(true)
+    }
+
+    /// Read the content of `inode` at `offset` into `buf` (holes read as zeros)
+    fn read_data(&self, inode: &Inode, buf: &mut [u8], offset: usize) -> Result<(), Errno> {
+        let mut done = 0;
+        while done < buf.len() {
+            let pos = offset + done;
+            let block_offset = pos % BLOCK_SIZE;
+            let len = (BLOCK_SIZE - block_offset).min(buf.len() - done);
+            match self.data_block(inode, pos / BLOCK_SIZE)? {
+                0 => buf[done..done + len].fill(0),
+                block => self.read(self.volume.block_offset(block)? + block_offset, &mut buf[done..done + len]),
+            }
+            done += len;
+        }
+        Ok(())
+    }
+
+    /// Write `data` at `offset` into the content of `inode`, allocating missing blocks (the caller writes back `inode`)
+    fn write_data(&mut self, inode: &mut Inode, data: &[u8], offset: usize) -> Result<(), Errno> {
+        let mut done = 0;
+        while done < data.len() {
+            let pos = offset + done;
+            let block_offset = pos % BLOCK_SIZE;
+            let len = (BLOCK_SIZE - block_offset).min(data.len() - done);
+            let block = self.map_block(inode, pos / BLOCK_SIZE)?;
+            self.write(self.volume.block_offset(block)? + block_offset, &data[done..done + len])?;
+            done += len;
+        }
+        Ok(())
+    }
+
+    /// Fill the last block of `inode` behind `size` with zeros (bytes behind the end of file are always zero)
+    fn zero_tail(&mut self, inode: &Inode, size: usize) -> Result<(), Errno> {
+        let block_offset = size % BLOCK_SIZE;
+        if block_offset != 0 {
+            let block = self.data_block(inode, size / BLOCK_SIZE)?;
+            if block != 0 {
+                self.write(self.volume.block_offset(block)? + block_offset, &ZEROS[block_offset..])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read all used entries of the directory `dir`
+    fn entries(&self, dir: &Inode) -> Result<Vec<Entry>, Errno> {
+        let mut entries = Vec::new();
+        let mut buffer = [0u8; ENTRY_SIZE];
+        for slot in 0..dir.size as usize / ENTRY_SIZE {
+            self.read_data(dir, &mut buffer, slot * ENTRY_SIZE)?;
+            let inode = u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]);
+            if inode == 0 {
+                continue;
+            }
+
+            let name_len = u16::from_le_bytes([buffer[4], buffer[5]]) as usize;
+            if name_len == 0 || name_len > MAX_NAME_LENGTH {
+                return Err(Errno::EIO);
+            }
+            let name = String::from_utf8(buffer[ENTRY_HEADER..ENTRY_HEADER + name_len].to_vec()).map_err(|_| Errno::EIO)?;
+            entries.push(Entry { slot, inode, name });
+        }
+        Ok(entries)
+    }
+
+    fn find_entry(&self, dir: &Inode, name: &str) -> Result<Option<Entry>, Errno> {
+        Ok(self.entries(dir)?.into_iter().find(|entry| entry.name == name))
+    }
+
+    /// Add the entry `name` referencing `inode` to `dir`, reusing the first unused slot (the caller writes back `dir`)
+    fn add_entry(&mut self, dir: &mut Inode, name: &str, inode: u32) -> Result<(), Errno> {
+        let slots = dir.size as usize / ENTRY_SIZE;
+        let used: BTreeSet<usize> = self.entries(dir)?.iter().map(|entry| entry.slot).collect();
+        let slot = (0..slots).find(|slot| !used.contains(slot)).unwrap_or(slots);
+
+        let mut entry = [0u8; ENTRY_SIZE];
+        entry[0..4].copy_from_slice(&inode.to_le_bytes());
+        entry[4..6].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        entry[ENTRY_HEADER..ENTRY_HEADER + name.len()].copy_from_slice(name.as_bytes());
+        self.write_data(dir, &entry[..ENTRY_HEADER + name.len()], slot * ENTRY_SIZE)?;
+
+        if slot == slots {
+            dir.size += ENTRY_SIZE as u64;
+        }
+        Ok(())
+    }
+
+    /// Let the entry in `slot` of `dir` reference `inode` (0 removes the entry)
+    fn set_entry_inode(&mut self, dir: &mut Inode, slot: usize, inode: u32) -> Result<(), Errno> {
+        self.write_data(dir, &inode.to_le_bytes(), slot * ENTRY_SIZE)
+    }
+}
+
+impl Drop for Transaction<'_> {
+    fn drop(&mut self) {
+        if self.log.records > 0 {
+            self.volume.roll_back(&mut self.log).expect("pmemfs: failed to roll back transaction");
+        }
+    }
+}
+
+/// Inode as stored in the inode table (free, if `mode` is 0)
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Inode {
+    /// Type and permission bits (see `Mode`)
+    mode: u32,
+    uid: u32,
+    gid: u32,
+    /// Incremented whenever the inode is used for a new object
+    generation: u32,
+    size: u64,
+    created_time: u64,
+    modified_time: u64,
+    accessed_time: u64,
+    blocks: [u32; DIRECT_BLOCKS + 2],
+    reserved: [u8; 24],
+}
+
+const _: () = assert!(size_of::<Inode>() == INODE_SIZE);
+
+impl Inode {
+    fn new(mode: Mode, uid: u32, gid: u32, generation: u32) -> Inode {
+        let now = stat::now();
+        Inode {
+            mode: mode.bits(),
+            uid,
+            gid,
+            generation,
+            size: 0,
+            created_time: now,
+            modified_time: now,
+            accessed_time: now,
+            blocks: [0; DIRECT_BLOCKS + 2],
+            reserved: [0; 24],
+        }
+    }
+
+    fn free(generation: u32) -> Inode {
+        Inode {
+            mode: 0,
+            uid: 0,
+            gid: 0,
+            generation,
+            size: 0,
+            created_time: 0,
+            modified_time: 0,
+            accessed_time: 0,
+            blocks: [0; DIRECT_BLOCKS + 2],
+            reserved: [0; 24],
+        }
+    }
+
+    fn file_type(&self) -> FileType {
+        Mode::new(self.mode).file_type()
+    }
+
+    fn is_dir(&self) -> bool {
+        self.file_type() == FileType::Directory
+    }
+
+    fn touch(&mut self) {
+        self.modified_time = stat::now();
+    }
+
+    fn set_permissions(&mut self, permissions: u32) {
+        self.mode = Mode::new(self.mode).with_permissions(permissions).bits();
+    }
+
+    fn change_owner(&mut self, uid: u32, gid: u32) {
+        self.uid = uid;
+        self.gid = gid;
+    }
+
+    fn set_times(&mut self, accessed: Option<u64>, modified: Option<u64>) {
+        if let Some(accessed) = accessed {
+            self.accessed_time = accessed;
+        }
+        if let Some(modified) = modified {
+            self.modified_time = modified;
+        }
+    }
+
+    fn stat(&self) -> Stat {
+        Stat {
+            mode: Mode::new(self.mode),
+            size: self.size as usize,
+            blocks: 0,
+            created_time: self.created_time,
+            modified_time: self.modified_time,
+            accessed_time: self.accessed_time,
+            uid: self.uid,
+            gid: self.gid,
+        }
+    }
+}
+
+/// A used directory entry
+struct Entry {
+    slot: usize,
+    inode: u32,
+    name: String,
+}
+
+pub struct Dir {
+    volume: Arc<Volume>,
+    inode: u32,
+    generation: u32,
+}
+
+impl Dir {
+    fn new(volume: Arc<Volume>, inode: u32, generation: u32) -> Dir {
+        Dir { volume, inode, generation }
+    }
+
+    /// Create the entry `name` referencing a new inode with `mode`. \
+    /// `init` fills in the content of the new inode (e.g. the target of a symbolic link).
+    fn create(
+        &self,
+        name: &str,
+        mode: Mode,
+        init: impl FnOnce(&mut Transaction, &mut Inode) -> Result<(), Errno>,
+    ) -> Result<NamedObject, Errno> {
+        check_name(name)?;
+        let mut tx = self.volume.begin();
+        let mut dir = tx.object_inode(self.inode, Some(self.generation))?;
+        if tx.find_entry(&dir, name)?.is_some() {
+            return Err(Errno::EEXIST);
+        }
+
+        let (number, generation) = tx.free_inode()?;
+        let (uid, gid) = access::credentials();
+        let mut inode = Inode::new(mode, uid, gid, generation);
+        init(&mut tx, &mut inode)?;
+        tx.write_inode(number, &inode)?;
+
+        tx.add_entry(&mut dir, name, number)?;
+        dir.touch();
+        tx.write_inode(self.inode, &dir)?;
+        tx.commit()?;
+
+        Ok(self.volume.named_object(number, &inode))
+    }
+
+    /// Remove the entry `name`, which must (`dir` = true) or must not be a directory
+    fn remove(&self, name: &str, dir: bool) -> Result<(), Errno> {
+        let mut tx = self.volume.begin();
+        let mut parent = tx.object_inode(self.inode, Some(self.generation))?;
+        let entry = tx.find_entry(&parent, name)?.ok_or(Errno::ENOENT)?;
+
+        let mut inode = tx.read_inode(entry.inode)?;
+        match (dir, inode.is_dir()) {
+            (false, true) => return Err(Errno::EISDIR),
+            (true, false) => return Err(Errno::ENOTDIR),
+            (true, true) if !tx.entries(&inode)?.is_empty() => return Err(Errno::ENOTEMPTY),
+            _ => {}
+        }
+
+        tx.set_entry_inode(&mut parent, entry.slot, 0)?;
+        tx.release_inode(entry.inode, &mut inode)?;
+        parent.touch();
+        tx.write_inode(self.inode, &parent)?;
+        tx.commit()
+    }
+}
+
+impl DirectoryObject for Dir {
+    fn lookup(&self, name: &str) -> Result<NamedObject, Errno> {
+        let tx = self.volume.begin();
+        let dir = tx.object_inode(self.inode, Some(self.generation))?;
+        let entry = tx.find_entry(&dir, name)?.ok_or(Errno::ENOENT)?;
+        let inode = tx.object_inode(entry.inode, None)?;
+        Ok(self.volume.named_object(entry.inode, &inode))
+    }
+
+    fn create_file(&self, name: &str, mode: Mode) -> Result<NamedObject, Errno> {
+        self.create(name, Mode::new(MODE_FILE).with_permissions(mode.permissions()), |_, _| Ok(()))
+    }
+
+    fn create_dir(&self, name: &str, mode: Mode) -> Result<NamedObject, Errno> {
+        self.create(name, Mode::new(MODE_DIR).with_permissions(mode.permissions()), |_, _| Ok(()))
+    }
+
+    fn stat(&self) -> Result<Stat, Errno> {
+        Ok(self.volume.begin().object_inode(self.inode, Some(self.generation))?.stat())
+    }
+
+    fn readdir(&self, index: usize) -> Result<Option<DirEntry>, Errno> {
+        let tx = self.volume.begin();
+        let dir = tx.object_inode(self.inode, Some(self.generation))?;
+        match tx.entries(&dir)?.into_iter().nth(index) {
+            Some(entry) => Ok(Some(DirEntry {
+                inode: entry.inode as u64,
+                file_type: tx.read_inode(entry.inode)?.file_type(),
+                name: entry.name,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    fn create_symlink(&self, name: &str, target: &str) -> Result<NamedObject, Errno> {
+        if target.is_empty() || target.len() >= BLOCK_SIZE {
+            return Err(Errno::EINVAL);
+        }
+
+        self.create(name, Mode::new(MODE_LINK).with_permissions(0o777), |tx, inode| {
+            tx.write_data(inode, target.as_bytes(), 0)?;
+            inode.size = target.len() as u64;
+            Ok(())
+        })
+    }
+
+    fn unlink(&self, name: &str) -> Result<(), Errno> {
+        self.remove(name, false)
+    }
+
+    fn rmdir(&self, name: &str) -> Result<(), Errno> {
+        self.remove(name, true)
+    }
+
+    fn rename(&self, old_name: &str, new_dir: &Arc<dyn DirectoryObject>, new_name: &str) -> Result<(), Errno> {
+        // Both directories must belong to the same volume
+        let new_dir = new_dir.as_any().downcast_ref::<Dir>().ok_or(Errno::EXDEV)?;
+        if !Arc::ptr_eq(&self.volume, &new_dir.volume) {
+            return Err(Errno::EXDEV);
+        }
+        check_name(new_name)?;
+
+        // Everything is done in one transaction, so the entry is never lost or visible twice after a crash
+        let mut tx = self.volume.begin();
+        let old_parent = tx.object_inode(self.inode, Some(self.generation))?;
+        let entry = tx.find_entry(&old_parent, old_name)?.ok_or(Errno::ENOENT)?;
+        let moved = tx.read_inode(entry.inode)?;
+
+        // Add the new entry (or let an existing one reference the moved inode)
+        let mut new_parent = tx.object_inode(new_dir.inode, Some(new_dir.generation))?;
+        match tx.find_entry(&new_parent, new_name)? {
+            Some(target) if target.inode == entry.inode => return Ok(()),
+            Some(target) => {
+                let mut replaced = tx.read_inode(target.inode)?;
+                match (moved.is_dir(), replaced.is_dir()) {
+                    (false, true) => return Err(Errno::EISDIR),
+                    (true, false) => return Err(Errno::ENOTDIR),
+                    (true, true) if !tx.entries(&replaced)?.is_empty() => return Err(Errno::ENOTEMPTY),
+                    _ => {}
+                }
+                tx.set_entry_inode(&mut new_parent, target.slot, entry.inode)?;
+                tx.release_inode(target.inode, &mut replaced)?;
+            }
+            None => tx.add_entry(&mut new_parent, new_name, entry.inode)?,
+        }
+        new_parent.touch();
+        tx.write_inode(new_dir.inode, &new_parent)?;
+
+        // Remove the old entry (read the parent again, since both parents may be the same directory)
+        let mut old_parent = tx.read_inode(self.inode)?;
+        tx.set_entry_inode(&mut old_parent, entry.slot, 0)?;
+        old_parent.touch();
+        tx.write_inode(self.inode, &old_parent)?;
+        tx.commit()
+    }
+
+    fn chmod(&self, permissions: u32) -> Result<(), Errno> {
+        self.volume.update_inode(self.inode, self.generation, |inode| inode.set_permissions(permissions))
+    }
+
+    fn chown(&self, uid: u32, gid: u32) -> Result<(), Errno> {
+        self.volume.update_inode(self.inode, self.generation, |inode| inode.change_owner(uid, gid))
+    }
+
+    fn utimes(&self, accessed: Option<u64>, modified: Option<u64>) -> Result<(), Errno> {
+        self.volume.update_inode(self.inode, self.generation, |inode| inode.set_times(accessed, modified))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Debug for Dir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PmemFsDir").field("inode", &self.inode).finish()
+    }
+}
+
+/// A file or symbolic link
+pub struct File {
+    volume: Arc<Volume>,
+    inode: u32,
+    generation: u32,
+}
+
+impl File {
+    fn new(volume: Arc<Volume>, inode: u32, generation: u32) -> File {
+        File { volume, inode, generation }
+    }
+
+    /// Read the inode of this file, which must be a regular file
+    fn regular_inode(&self, tx: &Transaction) -> Result<Inode, Errno> {
+        let inode = tx.object_inode(self.inode, Some(self.generation))?;
+        if inode.file_type() != FileType::Regular {
+            return Err(Errno::EINVAL);
+        }
+        Ok(inode)
+    }
+
+    /// Write `data` at `offset` in one transaction
+    fn write_chunk(&self, data: &[u8], offset: usize) -> Result<(), Errno> {
+        let mut tx = self.volume.begin();
+        let mut inode = self.regular_inode(&tx)?;
+
+        // Bytes behind the end of file are zero, so skipped parts read as zeros (blocks in between remain holes)
+        tx.write_data(&mut inode, data, offset)?;
+        inode.size = inode.size.max((offset + data.len()) as u64);
+        inode.touch();
+        tx.write_inode(self.inode, &inode)?;
+        tx.commit()
+    }
+}
+
+impl FileObject for File {
+    fn stat(&self) -> Result<Stat, Errno> {
+        Ok(self.volume.begin().object_inode(self.inode, Some(self.generation))?.stat())
+    }
+
+    fn read(&self, buf: &mut [u8], offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        let tx = self.volume.begin();
+        let inode = self.regular_inode(&tx)?;
+        let size = inode.size as usize;
+        if offset >= size {
+            return Ok(0);
+        }
+
+        let len = buf.len().min(size - offset);
+        tx.read_data(&inode, &mut buf[..len], offset)?;
+        Ok(len)
+    }
+
+    fn write(&self, buf: &[u8], offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        if offset.checked_add(buf.len()).is_none_or(|end| end > MAX_FILE_SIZE) {
+            return Err(Errno::EINVAL);
+        }
+
+        // Each chunk is written atomically, a failure after the first chunk results in a short write
+        let mut written = 0;
+        while written < buf.len() {
+            let len = WRITE_CHUNK.min(buf.len() - written);
+            match self.write_chunk(&buf[written..written + len], offset + written) {
+                Ok(()) => written += len,
+                Err(error) if written == 0 => return Err(error),
+                Err(_) => break,
+            }
+        }
+        Ok(written)
+    }
+
+    fn truncate(&self, size: usize) -> Result<(), Errno> {
+        if size > MAX_FILE_SIZE {
+            return Err(Errno::EINVAL);
+        }
+
+        let mut tx = self.volume.begin();
+        let mut inode = self.regular_inode(&tx)?;
+        if size < inode.size as usize {
+            // Shrinking: give back all blocks behind the new end of file and clear the rest of the last block
+            tx.free_blocks(&mut inode, size.div_ceil(BLOCK_SIZE))?;
+            tx.zero_tail(&inode, size)?;
+        }
+
+        inode.size = size as u64;
+        inode.touch();
+        tx.write_inode(self.inode, &inode)?;
+        tx.commit()
+    }
+
+    fn readlink(&self) -> Result<String, Errno> {
+        let tx = self.volume.begin();
+        let inode = tx.object_inode(self.inode, Some(self.generation))?;
+        if inode.file_type() != FileType::Link || inode.size as usize >= BLOCK_SIZE {
+            return Err(Errno::EINVAL);
+        }
+
+        let mut target = vec![0u8; inode.size as usize];
+        tx.read_data(&inode, &mut target, 0)?;
+        String::from_utf8(target).map_err(|_| Errno::EIO)
+    }
+
+    fn chmod(&self, permissions: u32) -> Result<(), Errno> {
+        self.volume.update_inode(self.inode, self.generation, |inode| inode.set_permissions(permissions))
+    }
+
+    fn chown(&self, uid: u32, gid: u32) -> Result<(), Errno> {
+        self.volume.update_inode(self.inode, self.generation, |inode| inode.change_owner(uid, gid))
+    }
+
+    fn utimes(&self, accessed: Option<u64>, modified: Option<u64>) -> Result<(), Errno> {
+        self.volume.update_inode(self.inode, self.generation, |inode| inode.set_times(accessed, modified))
+    }
+}
+
+impl fmt::Debug for File {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PmemFsFile").field("inode", &self.inode).finish()
+    }
+}
+
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Helper functions for block mapping and names.                           ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+
+/// Path to the data block with `index` of a file: slot in the inode and indices in the indirect blocks
+fn block_path(index: usize) -> Result<(usize, Vec<usize>), Errno> {
+    if index < DIRECT_BLOCKS {
+        return Ok((index, Vec::new()));
+    }
+
+    let index = index - DIRECT_BLOCKS;
+    if index < POINTERS_PER_BLOCK {
+        return Ok((DIRECT_BLOCKS, vec![index]));
+    }
+
+    let index = index - POINTERS_PER_BLOCK;
+    if index < POINTERS_PER_BLOCK * POINTERS_PER_BLOCK {
+        return Ok((DIRECT_BLOCKS + 1, vec![index / POINTERS_PER_BLOCK, index % POINTERS_PER_BLOCK]));
+    }
+    Err(Errno::EINVAL)
+}
+
+/// Raw bytes of a structure stored on the volume (superblock or inode, both without padding)
+fn as_bytes<T>(value: &T) -> &[u8] {
+    unsafe { slice::from_raw_parts(ptr::from_ref(value).cast::<u8>(), size_of::<T>()) }
+}
+
+/// Check if `name` is allowed as name of a directory entry
+fn check_name(name: &str) -> Result<(), Errno> {
+    if name.is_empty() || name == "." || name == ".." || name.len() > MAX_NAME_LENGTH || name.contains(['/', '\0']) {
+        return Err(Errno::EINVAL);
+    }
+    Ok(())
+}
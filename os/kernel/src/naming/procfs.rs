@@ -0,0 +1,261 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: procfs                                                          ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Process file system (mounted at /proc) providing kernel information as  ║
+   ║ text files, which are generated on each read:                           ║
+   ║   - meminfo: free page frames and heap usage                            ║
+   ║   - uptime: milliseconds since boot                                     ║
+   ║   - pci: detected PCI devices (address, ids and class)                  ║
+   ║   - blockdevices: registered block devices with their sizes             ║
+   ║   - <pid>/cmdline: name and arguments of the application                ║
+   ║   - <pid>/threads: thread ids of the process                            ║
+   ║   - <pid>/maps: virtual memory areas with type and tag                  ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use core::any::Any;
+use core::fmt;
+use core::fmt::Write;
+use core::result::Result;
+
+use super::stat::{Mode, Stat, MODE_DIR, MODE_FILE};
+use super::traits::{DirectoryObject, FileObject, FileSystem, NamedObject};
+use crate::memory::{frames, PAGE_SIZE};
+use crate::process::process::Process;
+use crate::{allocator, pci_bus, process_manager, storage, timer};
+use naming::shared_types::{DirEntry, FileType, OpenOptions};
+use syscall::return_vals::Errno;
+
+/// Everybody may read the files and list the directories, nobody may write
+const FILE_PERMISSIONS: u32 = 0o444;
+const DIR_PERMISSIONS: u32 = 0o555;
+
+/// Global files in the root directory
+const GLOBAL_FILES: [(&str, ProcFile); 4] = [
+    ("meminfo", ProcFile::MemInfo),
+    ("uptime", ProcFile::Uptime),
+    ("pci", ProcFile::Pci),
+    ("blockdevices", ProcFile::BlockDevices),
+];
+
+/// Files in each process directory
+const PROCESS_FILES: [&str; 3] = ["cmdline", "threads", "maps"];
+
+pub struct ProcFs {
+    root_dir: Arc<RootDir>,
+}
+
+impl ProcFs {
+    pub fn new() -> ProcFs {
+        ProcFs {
+            root_dir: Arc::new(RootDir {}),
+        }
+    }
+}
+
+impl FileSystem for ProcFs {
+    fn root_dir(&self) -> Arc<dyn DirectoryObject> {
+        self.root_dir.clone()
+    }
+}
+
+/// Root directory of procfs with the global files followed by one directory per active process
+pub struct RootDir {}
+
+impl DirectoryObject for RootDir {
+    fn lookup(&self, name: &str) -> Result<NamedObject, Errno> {
+        if let Some((_, file)) = GLOBAL_FILES.iter().find(|(file_name, _)| *file_name == name) {
+            return Ok((Arc::new(*file) as Arc<dyn FileObject>).into());
+        }
+
+        let pid = name.parse::<usize>().map_err(|_| Errno::ENOENT)?;
+        process(pid)?;
+        Ok((Arc::new(ProcessDir { pid }) as Arc<dyn DirectoryObject>).into())
+    }
+
+    fn create_file(&self, _name: &str, _mode: Mode) -> Result<NamedObject, Errno> {
+        Err(Errno::EACCES)
+    }
+
+    fn create_dir(&self, _name: &str, _mode: Mode) -> Result<NamedObject, Errno> {
+        Err(Errno::EACCES)
+    }
+
+    fn stat(&self) -> Result<Stat, Errno> {
+        Ok(Stat::new(Mode::new(MODE_DIR).with_permissions(DIR_PERMISSIONS), 0))
+    }
+
+    fn readdir(&self, index: usize) -> Result<Option<DirEntry>, Errno> {
+        if let Some((name, _)) = GLOBAL_FILES.get(index) {
+            return Ok(Some(DirEntry {
+                inode: index as u64 + 1,
+                file_type: FileType::Regular,
+                name: name.to_string(),
+            }));
+        }
+
+        let mut pids = process_manager().read().active_process_ids();
+        pids.sort();
+        Ok(pids.get(index - GLOBAL_FILES.len()).map(|pid| DirEntry {
+            inode: (*pid as u64 + 1) << 8,
+            file_type: FileType::Directory,
+            name: pid.to_string(),
+        }))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Debug for RootDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProcFsRootDir").finish()
+    }
+}
+
+/// Directory of the process `pid`
+struct ProcessDir {
+    pid: usize,
+}
+
+impl DirectoryObject for ProcessDir {
+    fn lookup(&self, name: &str) -> Result<NamedObject, Errno> {
+        let file = match name {
+            "cmdline" => ProcFile::Cmdline(self.pid),
+            "threads" => ProcFile::Threads(self.pid),
+            "maps" => ProcFile::Maps(self.pid),
+            _ => return Err(Errno::ENOENT),
+        };
+        Ok((Arc::new(file) as Arc<dyn FileObject>).into())
+    }
+
+    fn create_file(&self, _name: &str, _mode: Mode) -> Result<NamedObject, Errno> {
+        Err(Errno::EACCES)
+    }
+
+    fn create_dir(&self, _name: &str, _mode: Mode) -> Result<NamedObject, Errno> {
+        Err(Errno::EACCES)
+    }
+
+    fn stat(&self) -> Result<Stat, Errno> {
+        process(self.pid)?;
+        Ok(Stat::new(Mode::new(MODE_DIR).with_permissions(DIR_PERMISSIONS), 0))
+    }
+
+    fn readdir(&self, index: usize) -> Result<Option<DirEntry>, Errno> {
+        Ok(PROCESS_FILES.get(index).map(|name| DirEntry {
+            inode: ((self.pid as u64 + 1) << 8) + index as u64 + 1,
+            file_type: FileType::Regular,
+            name: name.to_string(),
+        }))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Debug for ProcessDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProcFsProcessDir").field("pid", &self.pid).finish()
+    }
+}
+
+/// Return the active process `pid` or `Err(Errno::ENOENT)` if it has terminated
+fn process(pid: usize) -> Result<Arc<Process>, Errno> {
+    process_manager().read().process(pid).ok_or(Errno::ENOENT)
+}
+
+/// Read-only text file, its content is generated from the current kernel state
+#[derive(Clone, Copy, Debug)]
+enum ProcFile {
+    MemInfo,
+    Uptime,
+    Pci,
+    BlockDevices,
+    Cmdline(usize),
+    Threads(usize),
+    Maps(usize),
+}
+
+impl ProcFile {
+    fn content(&self) -> Result<String, Errno> {
+        let mut text = String::new();
+        match *self {
+            ProcFile::MemInfo => {
+                let free_frames = frames::free_frames();
+                let _ = writeln!(text, "FreeFrames: {}", free_frames);
+                let _ = writeln!(text, "FreeMemory: {} KiB", free_frames * PAGE_SIZE / 1024);
+                let _ = writeln!(text, "HeapSize: {} KiB", allocator().size() / 1024);
+                let _ = writeln!(text, "HeapUsed: {} KiB", allocator().used() / 1024);
+            }
+            ProcFile::Uptime => {
+                let _ = writeln!(text, "{}", timer().systime_ms());
+            }
+            ProcFile::Pci => {
+                let pci = pci_bus();
+                for device in pci.devices() {
+                    let header = device.read().header();
+                    let (vendor_id, device_id) = header.id(pci.config_space());
+                    let (_, base_class, sub_class, interface) = header.revision_and_class(pci.config_space());
+                    let _ = writeln!(
+                        text,
+                        "{} {:04x}:{:04x} class {:02x}{:02x}{:02x}",
+                        header.address(), vendor_id, device_id, base_class, sub_class, interface
+                    );
+                }
+            }
+            ProcFile::BlockDevices => {
+                for name in storage::block_device_names() {
+                    if let Some(device) = storage::block_device(&name) {
+                        let size = device.sector_count() as usize * device.sector_size() as usize;
+                        let _ = writeln!(text, "{} {} KiB", name, size / 1024);
+                    }
+                }
+            }
+            ProcFile::Cmdline(pid) => {
+                let _ = writeln!(text, "{}", process(pid)?.cmdline.lock());
+            }
+            ProcFile::Threads(pid) => {
+                for thread_id in process(pid)?.thread_ids() {
+                    let _ = writeln!(text, "{}", thread_id);
+                }
+            }
+            ProcFile::Maps(pid) => {
+                for vma in process(pid)?.virtual_address_space.vmas() {
+                    let _ = writeln!(
+                        text,
+                        "0x{:016x}-0x{:016x} {:?} {}",
+                        vma.start().as_u64(), vma.end().as_u64(), vma.typ(), vma.tag()
+                    );
+                }
+            }
+        }
+        Ok(text)
+    }
+}
+
+impl FileObject for ProcFile {
+    fn stat(&self) -> Result<Stat, Errno> {
+        Ok(Stat::new(Mode::new(MODE_FILE).with_permissions(FILE_PERMISSIONS), self.content()?.len()))
+    }
+
+    fn read(&self, buf: &mut [u8], offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        let content = self.content()?;
+        let data = content.as_bytes();
+        if offset >= data.len() {
+            return Ok(0);
+        }
+
+        let len = buf.len().min(data.len() - offset);
+        buf[..len].copy_from_slice(&data[offset..offset + len]);
+        Ok(len)
+    }
+
+    fn write(&self, _buf: &[u8], _offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        Err(Errno::EACCES)
+    }
+}
+
@@ -0,0 +1,251 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: shmfs                                                           ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ File system for named shared memory objects (mounted at /dev/shm, used  ║
+   ║ by 'shm_open' and 'shm_unlink' of the naming library). It consists of a ║
+   ║ single directory of objects, which are sized using 'truncate' (or by    ║
+   ║ writing) and mapped with 'mmap' (MAP_SHARED) into several processes.    ║
+   ║ Removing a name does not free the memory of the object, this happens    ║
+   ║ when the last handle is closed and the last mapping is removed.         ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt;
+use core::ptr;
+use core::result::Result;
+use spin::rwlock::RwLock;
+
+use super::access;
+use super::stat::{self, Mode, Stat, DEFAULT_PERMISSIONS, MODE_DIR, MODE_FILE, ROOT_UID};
+use super::traits::{DirectoryObject, FileObject, FileSystem, NamedObject};
+use crate::memory::shm::SharedMemory;
+use naming::shared_types::{DirEntry, FileType, OpenOptions};
+use syscall::return_vals::Errno;
+
+pub struct ShmFs {
+    root_dir: Arc<Dir>,
+}
+
+impl ShmFs {
+    pub fn new() -> ShmFs {
+        ShmFs {
+            root_dir: Arc::new(Dir::new()),
+        }
+    }
+}
+
+impl FileSystem for ShmFs {
+    fn root_dir(&self) -> Arc<dyn DirectoryObject> {
+        self.root_dir.clone()
+    }
+}
+
+struct DirInner {
+    objects: Vec<(String, Arc<ShmObject>)>,
+    stat: Stat,
+}
+
+/// The only directory of shmfs (every process may create objects)
+pub struct Dir(RwLock<DirInner>);
+
+impl Dir {
+    fn new() -> Dir {
+        Dir(RwLock::new(DirInner {
+            objects: Vec::new(),
+            stat: Stat::created(Mode::new(MODE_DIR).with_permissions(DEFAULT_PERMISSIONS), ROOT_UID, 0),
+        }))
+    }
+
+    /// Return the index of the object `name` in `objects`
+    fn position(objects: &[(String, Arc<ShmObject>)], name: &str) -> Result<usize, Errno> {
+        objects
+            .iter()
+            .position(|(object_name, _)| object_name == name)
+            .ok_or(Errno::ENOENT)
+    }
+}
+
+impl DirectoryObject for Dir {
+    fn lookup(&self, name: &str) -> Result<NamedObject, Errno> {
+        let dir_lock = self.0.read();
+        let index = Dir::position(&dir_lock.objects, name)?;
+        Ok((dir_lock.objects[index].1.clone() as Arc<dyn FileObject>).into())
+    }
+
+    /// Create the empty shared memory object `name` with the permissions of `mode`, owned by the current process
+    fn create_file(&self, name: &str, mode: Mode) -> Result<NamedObject, Errno> {
+        let mut dir_lock = self.0.write();
+        if Dir::position(&dir_lock.objects, name).is_ok() {
+            return Err(Errno::EEXIST);
+        }
+
+        let (uid, gid) = access::credentials();
+        let object = Arc::new(ShmObject::new(mode.permissions(), uid, gid));
+        dir_lock.objects.push((name.to_string(), object.clone()));
+        dir_lock.stat.modified_time = stat::now();
+        Ok((object as Arc<dyn FileObject>).into())
+    }
+
+    fn create_dir(&self, _name: &str, _mode: Mode) -> Result<NamedObject, Errno> {
+        Err(Errno::ENOTSUP)
+    }
+
+    fn stat(&self) -> Result<Stat, Errno> {
+        Ok(self.0.read().stat)
+    }
+
+    fn readdir(&self, index: usize) -> Result<Option<DirEntry>, Errno> {
+        let dir_lock = self.0.read();
+        Ok(dir_lock.objects.get(index).map(|(name, object)| DirEntry {
+            inode: Arc::as_ptr(object) as u64,
+            file_type: FileType::Regular,
+            name: name.clone(),
+        }))
+    }
+
+    /// Remove the name of a shared memory object (its memory is freed, when it is no longer opened or mapped)
+    fn unlink(&self, name: &str) -> Result<(), Errno> {
+        let mut dir_lock = self.0.write();
+        let index = Dir::position(&dir_lock.objects, name)?;
+        dir_lock.objects.remove(index);
+        dir_lock.stat.modified_time = stat::now();
+        Ok(())
+    }
+
+    fn rename(&self, old_name: &str, new_dir: &Arc<dyn DirectoryObject>, new_name: &str) -> Result<(), Errno> {
+        // there is only one directory per shmfs
+        let new_dir = new_dir.as_any().downcast_ref::<Dir>().ok_or(Errno::EXDEV)?;
+        if !ptr::eq(self, new_dir) {
+            return Err(Errno::EXDEV);
+        }
+
+        let mut dir_lock = self.0.write();
+        Dir::position(&dir_lock.objects, old_name)?;
+        if old_name == new_name {
+            return Ok(());
+        }
+
+        if let Ok(replaced) = Dir::position(&dir_lock.objects, new_name) {
+            dir_lock.objects.remove(replaced);
+        }
+        let index = Dir::position(&dir_lock.objects, old_name)?;
+        dir_lock.objects[index].0 = new_name.to_string();
+        dir_lock.stat.modified_time = stat::now();
+        Ok(())
+    }
+
+    fn chmod(&self, permissions: u32) -> Result<(), Errno> {
+        let mut dir_lock = self.0.write();
+        dir_lock.stat.mode = dir_lock.stat.mode.with_permissions(permissions);
+        Ok(())
+    }
+
+    fn chown(&self, uid: u32, gid: u32) -> Result<(), Errno> {
+        let mut dir_lock = self.0.write();
+        dir_lock.stat.uid = uid;
+        dir_lock.stat.gid = gid;
+        Ok(())
+    }
+
+    fn utimes(&self, accessed: Option<u64>, modified: Option<u64>) -> Result<(), Errno> {
+        self.0.write().stat.set_times(accessed, modified);
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Debug for Dir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShmFsDir").finish()
+    }
+}
+
+/// Shared memory object, its size is kept in `stat`
+struct ShmObject {
+    memory: SharedMemory,
+    stat: RwLock<Stat>,
+}
+
+impl ShmObject {
+    fn new(permissions: u32, uid: u32, gid: u32) -> ShmObject {
+        ShmObject {
+            memory: SharedMemory::new(),
+            stat: RwLock::new(Stat::created(Mode::new(MODE_FILE).with_permissions(permissions), uid, gid)),
+        }
+    }
+}
+
+impl FileObject for ShmObject {
+    fn stat(&self) -> Result<Stat, Errno> {
+        Ok(*self.stat.read())
+    }
+
+    fn read(&self, buf: &mut [u8], offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        let mut stat = self.stat.write();
+        if offset >= stat.size {
+            return Ok(0);
+        }
+
+        let len = buf.len().min(stat.size - offset);
+        self.memory.read(&mut buf[..len], offset);
+        stat.accessed_time = stat::now();
+        Ok(len)
+    }
+
+    fn write(&self, buf: &[u8], offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        let end = offset.checked_add(buf.len()).ok_or(Errno::EINVAL)?;
+        let mut stat = self.stat.write();
+        if end > stat.size {
+            self.memory.resize(end)?;
+            stat.size = end;
+        }
+
+        self.memory.write(buf, offset);
+        stat.modified_time = stat::now();
+        Ok(buf.len())
+    }
+
+    /// Set the size of the object (pages removed are still accessible by mappings using them). \
+    /// Fails with `ENOSPC` beyond the max. size of shared memory objects or if the memory is exhausted.
+    fn truncate(&self, size: usize) -> Result<(), Errno> {
+        let mut stat = self.stat.write();
+        self.memory.resize(size)?;
+        stat.size = size;
+        stat.modified_time = stat::now();
+        Ok(())
+    }
+
+    fn chmod(&self, permissions: u32) -> Result<(), Errno> {
+        let mut stat = self.stat.write();
+        stat.mode = stat.mode.with_permissions(permissions);
+        Ok(())
+    }
+
+    fn chown(&self, uid: u32, gid: u32) -> Result<(), Errno> {
+        let mut stat = self.stat.write();
+        stat.uid = uid;
+        stat.gid = gid;
+        Ok(())
+    }
+
+    fn utimes(&self, accessed: Option<u64>, modified: Option<u64>) -> Result<(), Errno> {
+        self.stat.write().set_times(accessed, modified);
+        Ok(())
+    }
+
+    fn shared_memory(&self) -> Option<&SharedMemory> {
+        Some(&self.memory)
+    }
+}
+
+impl fmt::Debug for ShmObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShmObject").field("size", &self.stat.read().size).finish()
+    }
+}
@@ -6,38 +6,123 @@
    ║ Author: Michael Schoettner, 30.12.2024, HHU                             ║
    ╚═════════════════════════════════════════════════════════════════════════╝
 */
+use spin::Once;
+use naming::shared_types::{FileStat, FileType};
+
+use crate::syscall::sys_time::sys_get_date;
+use crate::timer;
 
 pub const MODE_FILE: u32 = 0x1;
 pub const MODE_DIR: u32  = 0x2;
 pub const MODE_LINK: u32 = 0x3;
+pub const MODE_CHAR_DEVICE: u32 = 0x4;
+pub const MODE_BLOCK_DEVICE: u32 = 0x8;
+pub const MODE_FIFO: u32 = 0x10;
+
+/// Permission bits (read, write and execute for owner, group and others, e.g. 0o755) are stored above the type bits
+const PERMISSIONS_SHIFT: u32 = 16;
+const PERMISSIONS_MASK: u32 = 0o777;
+
+/// Permissions of objects in file systems without support for permissions (see `Stat::new`)
+pub const DEFAULT_PERMISSIONS: u32 = 0o777;
+/// Permissions of new files and directories
+pub const FILE_PERMISSIONS: u32 = 0o644;
+pub const DIR_PERMISSIONS: u32 = 0o755;
+
+/// User id of the superuser (no permission checks)
+pub const ROOT_UID: u32 = 0;
 
+/// Wall clock time at boot in ms (0, if the UEFI clock is not available)
+static BOOT_TIME: Once<u64> = Once::new();
+
+/// Current time for timestamps in ms since 1.1.1970 (UEFI wall clock) or since boot (PIT uptime), if there is no wall clock. \
+/// The UEFI clock is read only once, afterwards the time is derived from the uptime.
+pub fn now() -> u64 {
+    let uptime = timer().systime_ms() as u64;
+    let boot_time = BOOT_TIME.call_once(|| (sys_get_date() as u64).saturating_sub(uptime));
+    boot_time + uptime
+}
 
 #[derive(Debug, Copy, Clone)]
 pub struct Stat {
     pub mode: Mode,
     pub size: usize,
+    pub blocks: usize, // number of 512 byte blocks allocated for the content (0 if not known by the file system)
     pub created_time: u64,
     pub modified_time: u64,
     pub accessed_time: u64,
+    pub uid: u32, // owner
+    pub gid: u32, // group
 }
 
 impl Stat {
+    /// Create meta data for an object owned by root. If `mode` contains no permissions, everything is allowed
+    /// (used by file systems without support for permissions).
     pub fn new(mode: Mode, size: usize) -> Stat {
+        let mode = if mode.permissions() == 0 { mode.with_permissions(DEFAULT_PERMISSIONS) } else { mode };
         Stat {
             mode,
             size,
+            blocks: 0,
             created_time: 0,
             modified_time: 0,
             accessed_time: 0,
+            uid: ROOT_UID,
+            gid: 0,
         }
     }
+
+    /// Create meta data for an object created now, owned by `uid` and `gid`
+    pub fn created(mode: Mode, uid: u32, gid: u32) -> Stat {
+        let now = now();
+        Stat {
+            mode,
+            size: 0,
+            blocks: 0,
+            created_time: now,
+            modified_time: now,
+            accessed_time: now,
+            uid,
+            gid,
+        }
+    }
+
+    /// Set the access and modification times (used by `utimes`), `None` leaves a timestamp unchanged
+    pub fn set_times(&mut self, accessed: Option<u64>, modified: Option<u64>) {
+        if let Some(accessed) = accessed {
+            self.accessed_time = accessed;
+        }
+        if let Some(modified) = modified {
+            self.modified_time = modified;
+        }
+    }
+
+    /// Convert into the representation passed to user space
+    pub fn to_file_stat(&self) -> FileStat {
+        FileStat {
+            file_type: self.mode.file_type(),
+            mode: self.mode.bits(),
+            size: self.size,
+            blocks: self.blocks,
+            created_time: self.created_time,
+            modified_time: self.modified_time,
+            accessed_time: self.accessed_time,
+            permissions: self.mode.permissions(),
+            uid: self.uid,
+            gid: self.gid,
+        }
+    }
+
     pub fn zeroed() -> Stat {
         Stat {
             mode: Mode::new(MODE_FILE),
             size: 0,
+            blocks: 0,
             created_time: 0,
             modified_time: 0,
             accessed_time: 0, 
+            uid: ROOT_UID,
+            gid: 0,
         }
     }
 }
@@ -62,4 +147,35 @@ impl Mode {
     pub fn is_link(self) -> bool {
         (self.0 & MODE_LINK) == MODE_LINK
     }
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Return the permission bits (e.g. 0o755)
+    pub fn permissions(self) -> u32 {
+        (self.0 >> PERMISSIONS_SHIFT) & PERMISSIONS_MASK
+    }
+
+    /// Return this mode with its permission bits replaced by `permissions` (e.g. 0o755)
+    pub fn with_permissions(self, permissions: u32) -> Mode {
+        Mode((self.0 & !(PERMISSIONS_MASK << PERMISSIONS_SHIFT)) | ((permissions & PERMISSIONS_MASK) << PERMISSIONS_SHIFT))
+    }
+
+    /// Return the file type encoded in the mode
+    pub fn file_type(self) -> FileType {
+        if self.0 & MODE_FIFO != 0 {
+            FileType::Fifo
+        } else if self.0 & MODE_BLOCK_DEVICE != 0 {
+            FileType::BlockDevice
+        } else if self.0 & MODE_CHAR_DEVICE != 0 {
+            FileType::CharDevice
+        } else if self.is_link() {
+            FileType::Link
+        } else if self.is_directory() {
+            FileType::Directory
+        } else {
+            FileType::Regular
+        }
+    }
 }
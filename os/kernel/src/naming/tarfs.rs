@@ -0,0 +1,215 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: tarfs                                                           ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Read-only file system for a TAR archive in memory (used for the initial ║
+   ║ ramdisk). The directory tree is built once from the entry names of the  ║
+   ║ archive, file data is not copied but read directly from the archive.    ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt;
+use core::result::Result;
+use log::warn;
+use spin::rwlock::RwLock;
+use tar_no_std::TarArchiveRef;
+
+use super::stat::{Mode, Stat, MODE_DIR, MODE_FILE};
+use super::traits::{DirectoryObject, FileObject, FileSystem, NamedObject};
+use naming::shared_types::{DirEntry, FileType, OpenOptions};
+use syscall::return_vals::Errno;
+
+pub struct TarFs {
+    root_dir: Arc<Dir>,
+}
+
+impl TarFs {
+    /// Create a file system containing all regular files of `archive`.
+    /// Directories are created for all path components of the entry names.
+    pub fn new(archive: &'static TarArchiveRef<'static>) -> TarFs {
+        let root_dir = Arc::new(Dir::new());
+
+        for entry in archive.entries() {
+            let name = match entry.filename().as_str() {
+                Ok(name) => name.to_string(),
+                Err(_) => {
+                    warn!("tarfs: Skipping entry with invalid file name");
+                    continue;
+                }
+            };
+
+            let mut components: Vec<&str> = name
+                .split('/')
+                .filter(|component| !component.is_empty() && *component != ".")
+                .collect();
+            let file_name = match components.pop() {
+                Some(file_name) => file_name,
+                None => continue,
+            };
+
+            let mut dir = root_dir.clone();
+            for component in components {
+                dir = dir.get_or_create_dir(component);
+            }
+            dir.add(file_name, TarFsINode::File(Arc::new(File::new(entry.data()))));
+        }
+
+        TarFs { root_dir }
+    }
+}
+
+impl FileSystem for TarFs {
+    fn root_dir(&self) -> Arc<dyn DirectoryObject> {
+        self.root_dir.clone()
+    }
+}
+
+enum TarFsINode {
+    File(Arc<File>),
+    Directory(Arc<Dir>),
+}
+
+/// Directory of the archive. The entries are only modified while building the file system.
+pub struct Dir {
+    files: RwLock<Vec<(String, TarFsINode)>>,
+    stat: Stat,
+}
+
+impl Dir {
+    fn new() -> Dir {
+        Dir {
+            files: RwLock::new(Vec::new()),
+            stat: Stat::new(Mode::new(MODE_DIR), 0),
+        }
+    }
+
+    /// Return the sub directory `name`, create it if it does not exist yet
+    fn get_or_create_dir(&self, name: &str) -> Arc<Dir> {
+        let mut files = self.files.write();
+        if let Some((_, TarFsINode::Directory(dir))) = files.iter().find(|(file_name, _)| file_name == name) {
+            return dir.clone();
+        }
+
+        let dir = Arc::new(Dir::new());
+        files.push((name.to_string(), TarFsINode::Directory(dir.clone())));
+        dir
+    }
+
+    /// Add `inode` as `name`, an existing entry with the same name is replaced (later archive entries win)
+    fn add(&self, name: &str, inode: TarFsINode) {
+        let mut files = self.files.write();
+        files.retain(|(file_name, _)| file_name != name);
+        files.push((name.to_string(), inode));
+    }
+}
+
+impl DirectoryObject for Dir {
+    fn lookup(&self, name: &str) -> Result<NamedObject, Errno> {
+        let files = self.files.read();
+        match files.iter().find(|(file_name, _)| file_name == name) {
+            Some((_, TarFsINode::File(file))) => Ok((file.clone() as Arc<dyn FileObject>).into()),
+            Some((_, TarFsINode::Directory(dir))) => Ok((dir.clone() as Arc<dyn DirectoryObject>).into()),
+            None => Err(Errno::ENOENT),
+        }
+    }
+
+    fn create_file(&self, _name: &str, _mode: Mode) -> Result<NamedObject, Errno> {
+        Err(Errno::EROFS)
+    }
+
+    fn create_dir(&self, _name: &str, _mode: Mode) -> Result<NamedObject, Errno> {
+        Err(Errno::EROFS)
+    }
+
+    fn stat(&self) -> Result<Stat, Errno> {
+        Ok(self.stat)
+    }
+
+    fn readdir(&self, index: usize) -> Result<Option<DirEntry>, Errno> {
+        let files = self.files.read();
+        Ok(files.get(index).map(|(name, inode)| DirEntry {
+            inode: match inode {
+                TarFsINode::File(file) => Arc::as_ptr(file) as u64,
+                TarFsINode::Directory(dir) => Arc::as_ptr(dir) as u64,
+            },
+            file_type: match inode {
+                TarFsINode::File(_) => FileType::Regular,
+                TarFsINode::Directory(_) => FileType::Directory,
+            },
+            name: name.clone(),
+        }))
+    }
+
+    fn create_symlink(&self, _name: &str, _target: &str) -> Result<NamedObject, Errno> {
+        Err(Errno::EROFS)
+    }
+
+    fn unlink(&self, _name: &str) -> Result<(), Errno> {
+        Err(Errno::EROFS)
+    }
+
+    fn rmdir(&self, _name: &str) -> Result<(), Errno> {
+        Err(Errno::EROFS)
+    }
+
+    fn rename(&self, _old_name: &str, _new_dir: &Arc<dyn DirectoryObject>, _new_name: &str) -> Result<(), Errno> {
+        Err(Errno::EROFS)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Debug for Dir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TarFsDir").finish()
+    }
+}
+
+/// Regular file of the archive, referencing its data within the archive
+struct File {
+    data: &'static [u8],
+    stat: Stat,
+}
+
+impl File {
+    fn new(data: &'static [u8]) -> File {
+        File {
+            data,
+            stat: Stat::new(Mode::new(MODE_FILE), data.len()),
+        }
+    }
+}
+
+impl FileObject for File {
+    fn stat(&self) -> Result<Stat, Errno> {
+        Ok(self.stat)
+    }
+
+    fn read(&self, buf: &mut [u8], offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        if offset >= self.data.len() {
+            return Ok(0);
+        }
+
+        let len = buf.len().min(self.data.len() - offset);
+        buf[..len].copy_from_slice(&self.data[offset..offset + len]);
+        Ok(len)
+    }
+
+    fn write(&self, _buf: &[u8], _offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        Err(Errno::EROFS)
+    }
+
+    fn truncate(&self, _size: usize) -> Result<(), Errno> {
+        Err(Errno::EROFS)
+    }
+}
+
+impl fmt::Debug for File {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TarFsFile").finish()
+    }
+}
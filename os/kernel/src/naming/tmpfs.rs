@@ -2,21 +2,40 @@
    ║ Module: tmpfs                                                           ║
    ╟─────────────────────────────────────────────────────────────────────────╢
    ║ Temporary file system running storing data in main memory.              ║
+   ║ The content of a file is stored in page-sized chunks (page frames from  ║
+   ║ 'memory::frames'), allocated when written. Pages which have never been  ║
+   ║ written (holes, e.g. after seeking behind the end of a file) read as    ║
+   ║ zeros and use no memory. The page frames used by all files of a mount   ║
+   ║ are limited (see 'size_limit'), exceeding the limit fails with ENOSPC.  ║
+   ║ All mounts together use at most half of the page frames, which are free ║
+   ║ when the first tmpfs is created.                                        ║
    ╟─────────────────────────────────────────────────────────────────────────╢
    ║ Author: Michael Schoettner, Univ. Duesseldorf, 30.12.2024               ║
    ╚═════════════════════════════════════════════════════════════════════════╝
 */
+use alloc::collections::BTreeMap;
 use alloc::string::{String, ToString};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
+use spin::Once;
 use spin::rwlock::RwLock;
+use core::any::Any;
 use core::fmt;
+use core::ptr;
 use core::result::Result;
+use core::slice;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use x86_64::structures::paging::frame::PhysFrameRange;
+use x86_64::structures::paging::PhysFrame;
 
-use super::stat::Mode;
-use super::stat::Stat;
+use super::access;
+use super::stat::{Mode, MODE_DIR, MODE_FILE, MODE_LINK, DIR_PERMISSIONS, DEFAULT_PERMISSIONS, FILE_PERMISSIONS, ROOT_UID};
+use super::stat::{self, Stat};
+use super::pipe::Fifo;
+use super::watch;
 use super::traits::{DirectoryObject, FileObject, FileSystem, NamedObject};
-use naming::shared_types::{DirEntry, FileType, OpenOptions};
+use crate::memory::{frames, PAGE_SIZE};
+use naming::shared_types::{DirEntry, FileType, OpenOptions, WatchMask};
 use syscall::return_vals::Errno;
 
 pub struct TmpFs {
@@ -24,13 +43,50 @@ pub struct TmpFs {
 }
 
 impl TmpFs {
-    pub fn new() -> TmpFs {
+    /// Create an empty tmpfs, whose files may use up to `size_limit` bytes (rounded down to pages)
+    pub fn new(size_limit: usize) -> TmpFs {
+        let usage = Arc::new(Usage::new(size_limit / PAGE_SIZE, Some(total_usage())));
         TmpFs {
-            root_dir: Arc::new(Dir::new()),
+            root_dir: Arc::new(Dir::new(DIR_PERMISSIONS, ROOT_UID, 0, usage)),
         }
     }
 }
 
+/// Page frames used by all tmpfs mounts together
+static TOTAL_USAGE: Once<Usage> = Once::new();
+
+/// Return the usage of all mounts, limited to half of the page frames free when it is first accessed
+fn total_usage() -> &'static Usage {
+    TOTAL_USAGE.call_once(|| Usage::new(frames::free_frames() / 2, None))
+}
+
+/// Default size limit: the memory available to all mounts together
+pub fn default_size_limit() -> usize {
+    total_usage().limit * PAGE_SIZE
+}
+
+/// Return the size limit given by the mount options `options` ("size=<bytes>", optionally followed by 'k', 'm' or 'g'),
+/// at most the default size limit, which is used without a size option.
+pub fn size_limit(options: &str) -> Result<usize, Errno> {
+    let size = match options.strip_prefix("size=") {
+        Some(size) => size.to_lowercase(),
+        None => return Ok(default_size_limit()),
+    };
+
+    let (number, unit) = match size.strip_suffix('k') {
+        Some(number) => (number, 1 << 10),
+        None => match size.strip_suffix('m') {
+            Some(number) => (number, 1 << 20),
+            None => match size.strip_suffix('g') {
+                Some(number) => (number, 1 << 30),
+                None => (size.as_str(), 1),
+            },
+        },
+    };
+    let size = number.parse::<usize>().ok().and_then(|number| number.checked_mul(unit)).ok_or(Errno::EINVAL)?;
+    Ok(size.min(default_size_limit()))
+}
+
 impl FileSystem for TmpFs {
     fn root_dir(&self) -> Arc<dyn DirectoryObject> {
         self.root_dir.clone()
@@ -42,23 +98,104 @@ enum TmpFsINode {
     Directory(Arc<Dir>),
 }
 
+/// Page frames used by the files of a tmpfs (or by all mounts, see `total_usage`)
+struct Usage {
+    pages: AtomicUsize,
+    limit: usize,                  // max. number of pages
+    total: Option<&'static Usage>, // usage of all mounts (also charged for the pages of this one)
+}
+
+impl Usage {
+    fn new(limit: usize, total: Option<&'static Usage>) -> Usage {
+        Usage { pages: AtomicUsize::new(0), limit, total }
+    }
+
+    /// Account for `count` additional pages, fails with `ENOSPC` if this would exceed the limit
+    fn reserve(&self, count: usize) -> Result<(), Errno> {
+        self.pages
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |pages| pages.checked_add(count).filter(|pages| *pages <= self.limit))
+            .map_err(|_| Errno::ENOSPC)?;
+
+        if let Some(total) = self.total {
+            total.reserve(count).inspect_err(|_| {
+                self.pages.fetch_sub(count, Ordering::SeqCst);
+            })?;
+        }
+        Ok(())
+    }
+
+    fn release(&self, count: usize) {
+        self.pages.fetch_sub(count, Ordering::SeqCst);
+        if let Some(total) = self.total {
+            total.release(count);
+        }
+    }
+}
+
 struct DirInner {
     files: Vec<(String, TmpFsINode)>,
     stat: Stat,
+    usage: Arc<Usage>, // shared by all directories and files of the file system
+}
+
+impl DirInner {
+    /// Add the entry `name` and update the modification time
+    fn add(&mut self, name: &str, inode: TmpFsINode) {
+        self.files.push((name.to_string(), inode));
+        self.modified();
+    }
+
+    /// Remove the entry at `index` and update the modification time
+    fn remove(&mut self, index: usize) -> (String, TmpFsINode) {
+        self.modified();
+        self.files.remove(index)
+    }
+
+    fn modified(&mut self) {
+        self.stat.modified_time = stat::now();
+    }
 }
 
 pub struct Dir(RwLock<DirInner>);
 
 impl Dir {
-    pub fn new() -> Dir {
+    fn new(permissions: u32, uid: u32, gid: u32, usage: Arc<Usage>) -> Dir {
         Dir(RwLock::new(DirInner {
             files: Vec::new(),
-            stat: Stat {
-                mode: Mode::new(0),
-                ..Stat::zeroed()
-            },
+            stat: Stat::created(Mode::new(MODE_DIR).with_permissions(permissions), uid, gid),
+            usage,
         }))
     }
+
+    /// Return the index of the entry `name` in `files`
+    fn position(files: &[(String, TmpFsINode)], name: &str) -> Result<usize, Errno> {
+        files
+            .iter()
+            .position(|(file_name, _)| file_name == name)
+            .ok_or(Errno::ENOENT)
+    }
+
+    /// Check if an existing entry `name` in `files` may be replaced by `inode` (used by `rename`). \
+    /// Returns the index of the entry to be replaced, if there is one.
+    fn check_replace(files: &[(String, TmpFsINode)], name: &str, inode: &TmpFsINode) -> Result<Option<usize>, Errno> {
+        let index = match Dir::position(files, name) {
+            Ok(index) => index,
+            Err(_) => return Ok(None),
+        };
+
+        match (inode, &files[index].1) {
+            (TmpFsINode::File(_), TmpFsINode::File(_)) => Ok(Some(index)),
+            (TmpFsINode::File(_), TmpFsINode::Directory(_)) => Err(Errno::EISDIR),
+            (TmpFsINode::Directory(_), TmpFsINode::File(_)) => Err(Errno::ENOTDIR),
+            (TmpFsINode::Directory(_), TmpFsINode::Directory(dir)) => {
+                if dir.0.read().files.is_empty() {
+                    Ok(Some(index))
+                } else {
+                    Err(Errno::ENOTEMPTY)
+                }
+            }
+        }
+    }
 }
 
 impl DirectoryObject for Dir {
@@ -76,7 +213,8 @@ impl DirectoryObject for Dir {
         }
     }
 
-    fn create_file(&self, name: &str, _mode: Mode) -> Result<NamedObject, Errno> {
+    /// Create the file `name` with the permissions of `mode`, owned by the current process
+    fn create_file(&self, name: &str, mode: Mode) -> Result<NamedObject, Errno> {
         let mut dir_lock = self.0.write();
 
         // Check if the file already exists in the directory
@@ -89,16 +227,17 @@ impl DirectoryObject for Dir {
         }
 
         // Create a new file and add it to the directory
-        let inode = Arc::new(File::new());
-        dir_lock
-            .files
-            .push((name.to_string(), TmpFsINode::File(inode.clone())));
+        let (uid, gid) = access::credentials();
+        let inode = Arc::new(File::new(mode.permissions(), uid, gid, dir_lock.usage.clone()));
+        dir_lock.add(name, TmpFsINode::File(inode.clone()));
+        watch::notify(self, WatchMask::CREATE, name, "");
 
         // Return the created file as a NamedObject
         Ok((inode as Arc<dyn FileObject>).into())
     }
 
-    fn create_dir(&self, name: &str, _mode: Mode) -> Result<NamedObject, Errno> {
+    /// Create the directory `name` with the permissions of `mode`, owned by the current process
+    fn create_dir(&self, name: &str, mode: Mode) -> Result<NamedObject, Errno> {
         let mut dir_lock = self.0.write();
     
         // Check if a file or directory with the same name already exists
@@ -107,21 +246,48 @@ impl DirectoryObject for Dir {
         }
     
         // Create a new directory and add it to the directory's entries
-        let inode = Arc::new(Dir::new());
-        dir_lock
-            .files
-            .push((name.to_string(), TmpFsINode::Directory(inode.clone())));
+        let (uid, gid) = access::credentials();
+        let inode = Arc::new(Dir::new(mode.permissions(), uid, gid, dir_lock.usage.clone()));
+        dir_lock.add(name, TmpFsINode::Directory(inode.clone()));
+        watch::notify(self, WatchMask::CREATE, name, "");
     
         // Return the created directory as a NamedObject
         Ok((inode as Arc<dyn DirectoryObject>).into())
     }
     
+    fn create_symlink(&self, name: &str, target: &str) -> Result<NamedObject, Errno> {
+        let mut dir_lock = self.0.write();
+        if dir_lock.files.iter().any(|(file_name, _)| file_name == name) {
+            return Err(Errno::EEXIST);
+        }
+
+        let (uid, gid) = access::credentials();
+        let inode = Arc::new(Link::new(target, uid, gid));
+        dir_lock.add(name, TmpFsINode::File(inode.clone()));
+        watch::notify(self, WatchMask::CREATE, name, "");
+        Ok((inode as Arc<dyn FileObject>).into())
+    }
+
+    fn create_fifo(&self, name: &str) -> Result<NamedObject, Errno> {
+        let mut dir_lock = self.0.write();
+        if dir_lock.files.iter().any(|(file_name, _)| file_name == name) {
+            return Err(Errno::EEXIST);
+        }
+
+        let (uid, gid) = access::credentials();
+        let inode = Arc::new(Fifo::new(FILE_PERMISSIONS, uid, gid));
+        dir_lock.add(name, TmpFsINode::File(inode.clone()));
+        watch::notify(self, WatchMask::CREATE, name, "");
+        Ok((inode as Arc<dyn FileObject>).into())
+    }
+
     fn stat(&self) -> Result<Stat, Errno> {
         Ok(self.0.read().stat)
     }
 
     fn readdir(&self, index: usize) -> Result<Option<DirEntry>, Errno> {
-        let dir_lock = self.0.read();
+        let mut dir_lock = self.0.write();
+        dir_lock.stat.accessed_time = stat::now();
         let (name, inode) = match dir_lock.files.iter().nth(index) {
             Some(entry) => entry,
             None => {
@@ -130,17 +296,118 @@ impl DirectoryObject for Dir {
         };
 
         let entry = match inode {
-            TmpFsINode::Directory(_dir) => DirEntry {
+            TmpFsINode::Directory(dir) => DirEntry {
+                inode: Arc::as_ptr(dir) as u64,
                 file_type: FileType::Directory,
                 name: name.clone(),
             },
-            TmpFsINode::File(_file) => DirEntry {
-                file_type: FileType::Regular,
+            TmpFsINode::File(file) => DirEntry {
+                inode: Arc::as_ptr(file) as *const () as u64,
+                file_type: file.stat()?.mode.file_type(), // regular file, link or FIFO
                 name: name.clone(),
             },
         };
         Ok(Some(entry))
     }
+
+    fn unlink(&self, name: &str) -> Result<(), Errno> {
+        let mut dir_lock = self.0.write();
+        let index = Dir::position(&dir_lock.files, name)?;
+        if let TmpFsINode::Directory(_) = dir_lock.files[index].1 {
+            return Err(Errno::EISDIR);
+        }
+
+        // The file data is freed, when the last handle referencing the file is closed
+        dir_lock.remove(index);
+        watch::notify(self, WatchMask::DELETE, name, "");
+        Ok(())
+    }
+
+    fn rmdir(&self, name: &str) -> Result<(), Errno> {
+        let mut dir_lock = self.0.write();
+        let index = Dir::position(&dir_lock.files, name)?;
+        match &dir_lock.files[index].1 {
+            TmpFsINode::File(_) => return Err(Errno::ENOTDIR),
+            TmpFsINode::Directory(dir) => {
+                if !dir.0.read().files.is_empty() {
+                    return Err(Errno::ENOTEMPTY);
+                }
+            }
+        }
+
+        dir_lock.remove(index);
+        watch::notify(self, WatchMask::DELETE, name, "");
+        Ok(())
+    }
+
+    fn rename(&self, old_name: &str, new_dir: &Arc<dyn DirectoryObject>, new_name: &str) -> Result<(), Errno> {
+        // Both directories must belong to the same tmpfs (identified by its usage, which is charged for the files)
+        let new_dir = new_dir.as_any().downcast_ref::<Dir>().ok_or(Errno::EXDEV)?;
+        let usage = self.0.read().usage.clone();
+        if !Arc::ptr_eq(&usage, &new_dir.0.read().usage) {
+            return Err(Errno::EXDEV);
+        }
+
+        if ptr::eq(self, new_dir) {
+            let mut dir_lock = self.0.write();
+            let index = Dir::position(&dir_lock.files, old_name)?;
+            if old_name == new_name {
+                return Ok(());
+            }
+
+            if let Some(replaced) = Dir::check_replace(&dir_lock.files, new_name, &dir_lock.files[index].1)? {
+                watch::notify(self, WatchMask::DELETE, new_name, "");
+                dir_lock.files.remove(replaced);
+            }
+            let index = Dir::position(&dir_lock.files, old_name)?;
+            dir_lock.files[index].0 = new_name.to_string();
+            dir_lock.modified();
+            watch::notify(self, WatchMask::RENAME, old_name, new_name);
+            return Ok(());
+        }
+
+        // Always lock the directory with the lower address first to avoid deadlocks
+        let (mut old_lock, mut new_lock) = if ptr::from_ref(self) < ptr::from_ref(new_dir) {
+            let old_lock = self.0.write();
+            (old_lock, new_dir.0.write())
+        } else {
+            let new_lock = new_dir.0.write();
+            (self.0.write(), new_lock)
+        };
+
+        let index = Dir::position(&old_lock.files, old_name)?;
+        if let Some(replaced) = Dir::check_replace(&new_lock.files, new_name, &old_lock.files[index].1)? {
+            watch::notify(new_dir, WatchMask::DELETE, new_name, "");
+            new_lock.files.remove(replaced);
+        }
+        let (_, inode) = old_lock.remove(index);
+        new_lock.add(new_name, inode);
+        watch::notify(self, WatchMask::RENAME, old_name, new_name);
+        watch::notify(new_dir, WatchMask::RENAME, old_name, new_name);
+        Ok(())
+    }
+
+    fn chmod(&self, permissions: u32) -> Result<(), Errno> {
+        let mut dir_lock = self.0.write();
+        dir_lock.stat.mode = dir_lock.stat.mode.with_permissions(permissions);
+        Ok(())
+    }
+
+    fn chown(&self, uid: u32, gid: u32) -> Result<(), Errno> {
+        let mut dir_lock = self.0.write();
+        dir_lock.stat.uid = uid;
+        dir_lock.stat.gid = gid;
+        Ok(())
+    }
+
+    fn utimes(&self, accessed: Option<u64>, modified: Option<u64>) -> Result<(), Errno> {
+        self.0.write().stat.set_times(accessed, modified);
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
 }
 
 impl fmt::Debug for Dir {
@@ -149,54 +416,171 @@ impl fmt::Debug for Dir {
     }
 }
 
+/// A page of file content, stored in a page frame (freed when the chunk is dropped)
+struct Chunk(PhysFrame);
+
+impl Chunk {
+    /// Allocate a chunk filled with zeros (`None` if the memory is exhausted)
+    fn new() -> Option<Chunk> {
+        let mut chunk = Chunk(frames::try_alloc(1)?.start);
+        chunk.data_mut().fill(0);
+        Some(chunk)
+    }
+
+    /// Content of the chunk (all page frames are identity mapped in kernel space)
+    fn data(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.0.start_address().as_u64() as *const u8, PAGE_SIZE) }
+    }
+
+    fn data_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.0.start_address().as_u64() as *mut u8, PAGE_SIZE) }
+    }
+}
+
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        unsafe { frames::free(PhysFrameRange { start: self.0, end: self.0 + 1 }); }
+    }
+}
+
+/// Content of a file: chunks by page number (missing pages are holes) and the size in bytes.
+/// Bytes of a chunk behind the end of the file are always zero.
+struct Content {
+    chunks: BTreeMap<usize, Chunk>,
+    size: usize,
+}
+
 struct File {
-    data: RwLock<Vec<u8>>,
-    stat: Stat,
+    content: RwLock<Content>,
+    stat: RwLock<Stat>,
+    usage: Arc<Usage>,
 }
 
 impl File {
-    pub fn new() -> File {
+    fn new(permissions: u32, uid: u32, gid: u32, usage: Arc<Usage>) -> File {
         File {
-            data: RwLock::new(Vec::new()),
-            stat: Stat {
-                mode: Mode::new(0),
-                ..Stat::zeroed()
-            },
+            content: RwLock::new(Content { chunks: BTreeMap::new(), size: 0 }),
+            stat: RwLock::new(Stat::created(Mode::new(MODE_FILE).with_permissions(permissions), uid, gid)),
+            usage,
         }
     }
 }
 
+impl Drop for File {
+    fn drop(&mut self) {
+        self.usage.release(self.content.get_mut().chunks.len());
+    }
+}
+
 impl FileObject for File {
     fn stat(&self) -> Result<Stat, Errno> {
-        Ok(self.stat)
+        let content = self.content.read();
+        Ok(Stat {
+            size: content.size,
+            blocks: content.chunks.len() * PAGE_SIZE / 512,
+            ..*self.stat.read()
+        })
     }
 
     fn read(&self, buf: &mut [u8], offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
-        let data= self.data.write();
-        if offset > data.len() {
+        let content = self.content.read();
+        if offset >= content.size {
             return Ok(0);
         }
 
-        let len;
-        if data.len() - offset < buf.len() {
-            len = data.len() - offset;
-        } else {
-            len = buf.len();
+        let len = buf.len().min(content.size - offset);
+        let mut done = 0;
+        while done < len {
+            let pos = offset + done;
+            let page_offset = pos % PAGE_SIZE;
+            let count = (PAGE_SIZE - page_offset).min(len - done);
+            match content.chunks.get(&(pos / PAGE_SIZE)) {
+                Some(chunk) => buf[done..done + count].copy_from_slice(&chunk.data()[page_offset..page_offset + count]),
+                None => buf[done..done + count].fill(0),
+            }
+            done += count;
         }
-        buf[0..len].clone_from_slice(&data[offset..offset + len]);
+
+        self.stat.write().accessed_time = stat::now();
         Ok(len)
     }
 
+    /// Write `buf` at `offset`, allocating chunks for all pages not written before (the gap between
+    /// the end of the file and `offset` stays a hole). Nothing is written, if the size limit would be exceeded.
     fn write(&self, buf: &[u8], offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
-        let mut data = self.data.write();
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let end = offset.checked_add(buf.len()).ok_or(Errno::EINVAL)?;
+
+        let mut content = self.content.write();
+        let missing: Vec<usize> = (offset / PAGE_SIZE..end.div_ceil(PAGE_SIZE))
+            .filter(|page| !content.chunks.contains_key(page))
+            .collect();
+        self.usage.reserve(missing.len())?;
+
+        // Allocate all chunks first, those already allocated are freed again on failure
+        let mut chunks = Vec::with_capacity(missing.len());
+        for &page in &missing {
+            match Chunk::new() {
+                Some(chunk) => chunks.push((page, chunk)),
+                None => {
+                    drop(chunks);
+                    self.usage.release(missing.len());
+                    return Err(Errno::ENOSPC);
+                }
+            }
+        }
+        content.chunks.extend(chunks);
 
-        if offset + buf.len() > data.len() {
-            data.resize(offset + buf.len(), 0);
+        let mut done = 0;
+        while done < buf.len() {
+            let pos = offset + done;
+            let page_offset = pos % PAGE_SIZE;
+            let count = (PAGE_SIZE - page_offset).min(buf.len() - done);
+            let chunk = content.chunks.get_mut(&(pos / PAGE_SIZE)).unwrap();
+            chunk.data_mut()[page_offset..page_offset + count].copy_from_slice(&buf[done..done + count]);
+            done += count;
         }
+        content.size = content.size.max(end);
 
-        data[offset..offset + buf.len()].clone_from_slice(buf);
+        self.stat.write().modified_time = stat::now();
+        watch::notify(self, WatchMask::MODIFY, "", "");
         Ok(buf.len())
     }
+
+    /// Set the size to `size` bytes. Chunks behind the new end are freed, growing the file only creates a hole.
+    fn truncate(&self, size: usize) -> Result<(), Errno> {
+        let mut content = self.content.write();
+        let removed = content.chunks.split_off(&size.div_ceil(PAGE_SIZE));
+        self.usage.release(removed.len());
+        if let Some(chunk) = content.chunks.get_mut(&(size / PAGE_SIZE)) {
+            chunk.data_mut()[size % PAGE_SIZE..].fill(0);
+        }
+        content.size = size;
+
+        self.stat.write().modified_time = stat::now();
+        watch::notify(self, WatchMask::MODIFY, "", "");
+        Ok(())
+    }
+
+    fn chmod(&self, permissions: u32) -> Result<(), Errno> {
+        let mut stat = self.stat.write();
+        stat.mode = stat.mode.with_permissions(permissions);
+        Ok(())
+    }
+
+    fn chown(&self, uid: u32, gid: u32) -> Result<(), Errno> {
+        let mut stat = self.stat.write();
+        stat.uid = uid;
+        stat.gid = gid;
+        Ok(())
+    }
+
+    fn utimes(&self, accessed: Option<u64>, modified: Option<u64>) -> Result<(), Errno> {
+        self.stat.write().set_times(accessed, modified);
+        Ok(())
+    }
 }
 
 impl fmt::Debug for File {
@@ -204,3 +588,37 @@ impl fmt::Debug for File {
         f.debug_struct("TmpFsFile").finish()
     }
 }
+
+/// Symbolic link, storing the path of its target
+struct Link {
+    target: String,
+    stat: Stat,
+}
+
+impl Link {
+    pub fn new(target: &str, uid: u32, gid: u32) -> Link {
+        Link {
+            target: target.to_string(),
+            stat: Stat {
+                size: target.len(),
+                ..Stat::created(Mode::new(MODE_LINK).with_permissions(DEFAULT_PERMISSIONS), uid, gid)
+            },
+        }
+    }
+}
+
+impl FileObject for Link {
+    fn stat(&self) -> Result<Stat, Errno> {
+        Ok(self.stat)
+    }
+
+    fn readlink(&self) -> Result<String, Errno> {
+        Ok(self.target.clone())
+    }
+}
+
+impl fmt::Debug for Link {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TmpFsLink").field("target", &self.target).finish()
+    }
+}
@@ -12,12 +12,15 @@
 */
 
 
+use alloc::string::String;
 use alloc::sync::Arc;
+use core::any::Any;
 use core::fmt::{self, Debug};
 use core::result::Result;
 
 use super::stat::{Mode, Stat};
-use naming::shared_types::{OpenOptions, DirEntry};
+use crate::memory::shm::SharedMemory;
+use naming::shared_types::{OpenOptions, DirEntry, PollFlags};
 use syscall::return_vals::Errno;
 
 /// FileSystem operations
@@ -38,6 +41,47 @@ pub trait FileObject: Debug + Send + Sync {
     fn write(&self, _buf: &[u8], _offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
         Err(Errno::EBADF)
     }
+
+    /// Set the size of the file to `size` bytes (cutting off data or appending zeros)
+    fn truncate(&self, _size: usize) -> Result<(), Errno> {
+        Err(Errno::EINVAL)
+    }
+
+    /// Return the target path of a symbolic link (the mode of a link is `MODE_LINK`)
+    fn readlink(&self) -> Result<String, Errno> {
+        Err(Errno::EINVAL)
+    }
+
+    /// Return the events currently ready (see `poll`). Objects which never block are always readable and writable.
+    fn poll(&self) -> PollFlags {
+        PollFlags::READABLE | PollFlags::WRITABLE
+    }
+
+    /// Called when the object is opened with `options`. Returns the object to be used for the new handle,
+    /// if it differs from this object (e.g. one end of a FIFO), or `None`.
+    fn open(&self, _options: OpenOptions) -> Result<Option<Arc<dyn FileObject>>, Errno> {
+        Ok(None)
+    }
+
+    /// Set the permission bits (e.g. 0o755)
+    fn chmod(&self, _permissions: u32) -> Result<(), Errno> {
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Set the owner and the group
+    fn chown(&self, _uid: u32, _gid: u32) -> Result<(), Errno> {
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Set the time of the last access and the last modification (in ms), `None` leaves a timestamp unchanged
+    fn utimes(&self, _accessed: Option<u64>, _modified: Option<u64>) -> Result<(), Errno> {
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Return the memory of a shared memory object, whose frames are mapped directly by shared mappings
+    fn shared_memory(&self) -> Option<&SharedMemory> {
+        None
+    }
 }
 
 
@@ -48,6 +92,50 @@ pub trait DirectoryObject: Debug + Send + Sync {
     fn create_dir(&self, _name: &str, _mode: Mode) -> Result<NamedObject, Errno>;
     fn stat(&self) -> Result<Stat, Errno>;
     fn readdir(&self, index: usize) -> Result<Option<DirEntry>, Errno>;
+
+    /// Create the symbolic link `name` pointing to `target`
+    fn create_symlink(&self, _name: &str, _target: &str) -> Result<NamedObject, Errno> {
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Create the named pipe (FIFO) `name`
+    fn create_fifo(&self, _name: &str) -> Result<NamedObject, Errno> {
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Remove the entry `name` (must not be a directory)
+    fn unlink(&self, _name: &str) -> Result<(), Errno> {
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Remove the empty directory `name`
+    fn rmdir(&self, _name: &str) -> Result<(), Errno> {
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Move the entry `old_name` to `new_dir` (of the same file system) as `new_name`. \
+    /// An existing entry `new_name` is replaced, if it is a file or an empty directory.
+    fn rename(&self, _old_name: &str, _new_dir: &Arc<dyn DirectoryObject>, _new_name: &str) -> Result<(), Errno> {
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Set the permission bits (e.g. 0o755)
+    fn chmod(&self, _permissions: u32) -> Result<(), Errno> {
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Set the owner and the group
+    fn chown(&self, _uid: u32, _gid: u32) -> Result<(), Errno> {
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Set the time of the last access and the last modification (in ms), `None` leaves a timestamp unchanged
+    fn utimes(&self, _accessed: Option<u64>, _modified: Option<u64>) -> Result<(), Errno> {
+        Err(Errno::ENOTSUP)
+    }
+
+    /// Access to the implementing type (used for operations involving two directories, e.g. `rename`)
+    fn as_any(&self) -> &dyn Any;
 }
 
 /// A named object.
@@ -74,6 +162,46 @@ impl NamedObject {
         }
     }
 
+    /// Returns the meta data of the file or directory.
+    pub fn stat(&self) -> Result<Stat, Errno> {
+        match self {
+            NamedObject::FileObject(file) => file.stat(),
+            NamedObject::DirectoryObject(dir) => dir.stat(),
+        }
+    }
+
+    /// Set the permission bits of the file or directory.
+    pub fn chmod(&self, permissions: u32) -> Result<(), Errno> {
+        match self {
+            NamedObject::FileObject(file) => file.chmod(permissions),
+            NamedObject::DirectoryObject(dir) => dir.chmod(permissions),
+        }
+    }
+
+    /// Set the owner and the group of the file or directory.
+    pub fn chown(&self, uid: u32, gid: u32) -> Result<(), Errno> {
+        match self {
+            NamedObject::FileObject(file) => file.chown(uid, gid),
+            NamedObject::DirectoryObject(dir) => dir.chown(uid, gid),
+        }
+    }
+
+    /// Set the access and modification times of the file or directory.
+    pub fn utimes(&self, accessed: Option<u64>, modified: Option<u64>) -> Result<(), Errno> {
+        match self {
+            NamedObject::FileObject(file) => file.utimes(accessed, modified),
+            NamedObject::DirectoryObject(dir) => dir.utimes(accessed, modified),
+        }
+    }
+
+    /// Returns `true` if it's a symbolic link.
+    pub fn is_link(&self) -> bool {
+        match self {
+            NamedObject::FileObject(file) => file.stat().is_ok_and(|stat| stat.mode.is_link()),
+            NamedObject::DirectoryObject(_) => false,
+        }
+    }
+
     /// Returns `true` if it's a file.
     pub fn is_file(&self) -> bool {
         matches!(self, NamedObject::FileObject(_))
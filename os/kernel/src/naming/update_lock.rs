@@ -0,0 +1,40 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: update_lock                                                     ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Lock serializing the modifying operations of a disk file system (e.g.   ║
+   ║ creating an entry in a directory), which consist of several disk        ║
+   ║ accesses. The device driver may sleep during I/O, so waiting threads    ║
+   ║ are blocked in a wait queue instead of spinning.                        ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::process::scheduler::WaitQueue;
+use crate::scheduler;
+
+pub(super) struct UpdateLock {
+    locked: AtomicBool,
+    queue: WaitQueue,
+}
+
+/// Releases the lock when dropped
+pub(super) struct UpdateGuard<'a>(&'a UpdateLock);
+
+impl UpdateLock {
+    pub(super) fn new() -> UpdateLock {
+        UpdateLock { locked: AtomicBool::new(false), queue: WaitQueue::new() }
+    }
+
+    /// Block until the lock is free and take it
+    pub(super) fn lock(&self) -> UpdateGuard<'_> {
+        scheduler().wait(&self.queue, None, || self.locked.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_ok());
+        UpdateGuard(self)
+    }
+}
+
+impl Drop for UpdateGuard<'_> {
+    fn drop(&mut self) {
+        self.0.locked.store(false, Ordering::Release);
+        scheduler().wake_up(&self.0.queue);
+    }
+}
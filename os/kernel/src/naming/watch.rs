@@ -0,0 +1,166 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: watch                                                           ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Change notifications of the naming service. A process registers a watch ║
+   ║ for a directory or a file and reads the events (see 'WatchMask') from   ║
+   ║ the returned handle, which blocks until an event is available:          ║
+   ║   - directory: entries created, removed and renamed (a move between two ║
+   ║     directories is reported to the watches of both)                     ║
+   ║   - file: content modified                                              ║
+   ║ File systems report changes by calling 'notify' with the object which   ║
+   ║ has been changed. The watch keeps the watched object alive, so its      ║
+   ║ address identifies it. Events not read in time are dropped and reported ║
+   ║ by an 'OVERFLOW' event. A watch is removed when its handle is closed.   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::collections::VecDeque;
+use alloc::string::{String, ToString};
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::fmt;
+use core::result::Result;
+use spin::{Mutex, RwLock};
+
+use super::stat::{Mode, Stat, MODE_FIFO};
+use super::traits::{FileObject, NamedObject};
+use crate::process::scheduler::WaitQueue;
+use crate::scheduler;
+use naming::shared_types::{OpenOptions, PollFlags, RawWatchEventHeader, WatchMask};
+use syscall::return_vals::Errno;
+
+/// Max. number of events queued per watch (further events are dropped)
+const MAX_EVENTS: usize = 256;
+
+/// All registered watches (removed lazily after their handle has been closed)
+static WATCHES: RwLock<Vec<Weak<Watch>>> = RwLock::new(Vec::new());
+
+/// Register a watch for the events `mask` of `object` and return it (to be opened as handle)
+pub(super) fn create(object: NamedObject, mask: WatchMask) -> Arc<dyn FileObject> {
+    let watch = Arc::new(Watch {
+        id: object_id(&object),
+        mask: mask | WatchMask::OVERFLOW,
+        events: Mutex::new(VecDeque::new()),
+        queue: WaitQueue::new(),
+        _object: object,
+    });
+
+    let mut watches = WATCHES.write();
+    watches.retain(|watch| watch.strong_count() > 0);
+    watches.push(Arc::downgrade(&watch));
+    watch
+}
+
+/// Report the event `mask` of the directory or file `object` (e.g. `self` of a `DirectoryObject`) to all its watches.
+/// `name` is the affected entry of a directory, `new_name` the new name of a renamed entry (empty if unused).
+pub fn notify<T: ?Sized>(object: &T, mask: WatchMask, name: &str, new_name: &str) {
+    let id = object as *const T as *const () as usize;
+    for watch in WATCHES.read().iter().filter_map(Weak::upgrade) {
+        if watch.id == id && watch.mask.contains(mask) {
+            watch.push(Event { mask, name: name.to_string(), new_name: new_name.to_string() });
+        }
+    }
+}
+
+/// Address of the watched object, as passed to `notify` by the file system
+fn object_id(object: &NamedObject) -> usize {
+    match object {
+        NamedObject::FileObject(file) => Arc::as_ptr(file) as *const () as usize,
+        NamedObject::DirectoryObject(dir) => Arc::as_ptr(dir) as *const () as usize,
+    }
+}
+
+struct Event {
+    mask: WatchMask,
+    name: String,
+    new_name: String,
+}
+
+impl Event {
+    fn record_len(&self) -> usize {
+        RawWatchEventHeader::record_len(self.name.len(), self.new_name.len())
+    }
+}
+
+/// A registered watch, readable through its handle
+struct Watch {
+    id: usize,
+    mask: WatchMask,
+    events: Mutex<VecDeque<Event>>,
+    queue: WaitQueue, // threads waiting for an event
+    _object: NamedObject, // keeps the watched object (and thus its address) alive
+}
+
+impl Watch {
+    /// Queue `event` (the last free slot is reserved for reporting an overflow) and wake up waiting readers
+    fn push(&self, event: Event) {
+        {
+            let mut events = self.events.lock();
+            if events.len() < MAX_EVENTS - 1 {
+                events.push_back(event);
+            } else if events.back().is_none_or(|last| last.mask != WatchMask::OVERFLOW) {
+                events.push_back(Event { mask: WatchMask::OVERFLOW, name: String::new(), new_name: String::new() });
+            }
+        }
+        scheduler().wake_up(&self.queue);
+    }
+}
+
+impl FileObject for Watch {
+    fn stat(&self) -> Result<Stat, Errno> {
+        let pending = self.events.lock().iter().map(Event::record_len).sum();
+        Ok(Stat::new(Mode::new(MODE_FIFO), pending))
+    }
+
+    /// Copy as many complete events as fit into `buf` (see `RawWatchEventHeader`), blocking until there is one. \
+    /// Fails with `EINVAL`, if `buf` is too small for the next event.
+    fn read(&self, buf: &mut [u8], _offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        loop {
+            {
+                let mut events = self.events.lock();
+                if let Some(first) = events.front() {
+                    if first.record_len() > buf.len() {
+                        return Err(Errno::EINVAL);
+                    }
+
+                    let mut pos = 0;
+                    while let Some(event) = events.front() {
+                        let record_len = event.record_len();
+                        if pos + record_len > buf.len() {
+                            break;
+                        }
+
+                        let header = RawWatchEventHeader {
+                            mask: event.mask.bits() as u32,
+                            record_len: record_len as u16,
+                            name_len: event.name.len() as u16,
+                            new_name_len: event.new_name.len() as u16,
+                        };
+                        let record = &mut buf[pos..pos + record_len];
+                        unsafe { (record.as_mut_ptr() as *mut RawWatchEventHeader).write_unaligned(header) };
+                        let names = &mut record[RawWatchEventHeader::SIZE..];
+                        names[..event.name.len()].copy_from_slice(event.name.as_bytes());
+                        names[event.name.len()..event.name.len() + event.new_name.len()].copy_from_slice(event.new_name.as_bytes());
+                        names[event.name.len() + event.new_name.len()..].fill(0);
+
+                        pos += record_len;
+                        events.pop_front();
+                    }
+                    return Ok(pos);
+                }
+            }
+
+            scheduler().wait(&self.queue, None, || !self.events.lock().is_empty());
+        }
+    }
+
+    /// Readable if an event is available
+    fn poll(&self) -> PollFlags {
+        if self.events.lock().is_empty() { PollFlags::empty() } else { PollFlags::READABLE }
+    }
+}
+
+impl fmt::Debug for Watch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watch").field("mask", &self.mask).finish()
+    }
+}
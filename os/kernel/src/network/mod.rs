@@ -4,20 +4,26 @@ use alloc::vec::Vec;
 use core::ops::Deref;
 use core::ptr;
 use log::info;
-use smoltcp::iface::{Interface, SocketHandle, SocketSet};
+use smoltcp::iface::{Interface, PollResult, SocketHandle, SocketSet};
 use smoltcp::socket::udp;
 use smoltcp::time::Instant;
 use smoltcp::wire::Ipv4Address;
 use spin::{Once, RwLock};
 use crate::device::rtl8139::Rtl8139;
 use crate::{pci_bus, scheduler, timer};
+use crate::process::scheduler::WaitQueue;
 use crate::process::thread::Thread;
 
+pub mod udp_socket;
+
 static RTL8139: Once<Arc<Rtl8139>> = Once::new();
 
 static INTERFACES: RwLock<Vec<Interface>> = RwLock::new(Vec::new());
 static SOCKETS: Once<RwLock<SocketSet>> = Once::new();
 
+/// Threads waiting for a socket to receive or send (woken up after packets have been processed)
+static SOCKET_QUEUE: WaitQueue = WaitQueue::new();
+
 pub enum SocketType {
     Udp
 }
@@ -93,17 +99,43 @@ pub fn send_datagram(handle: SocketHandle, destination: Ipv4Address, port: u16,
     socket.send_slice(data, (destination, port))
 }
 
+/// Receive a datagram into `buf` without waiting. Returns `Ok(None)`, if no datagram has been received.
+pub fn receive_datagram(handle: SocketHandle, buf: &mut [u8]) -> Result<Option<usize>, udp::RecvError> {
+    let mut sockets = SOCKETS.get().expect("Socket set not initialized!").write();
+    let socket = sockets.get_mut::<udp::Socket>(handle);
+    if !socket.can_recv() {
+        return Ok(None);
+    }
+
+    socket.recv_slice(buf).map(|(len, _)| Some(len))
+}
+
+/// Return whether the UDP socket `handle` has received datagrams and whether it can send datagrams
+pub fn udp_state(handle: SocketHandle) -> (bool, bool) {
+    let mut sockets = SOCKETS.get().expect("Socket set not initialized!").write();
+    let socket = sockets.get_mut::<udp::Socket>(handle);
+    (socket.can_recv(), socket.can_send())
+}
+
 fn poll_sockets() {
     let rtl8139 = RTL8139.get().expect("RTL8139 not initialized");
-    let mut interfaces = INTERFACES.write();
-    let mut sockets = SOCKETS.get().expect("Socket set not initialized!").write();
-    let time = Instant::from_millis(timer().systime_ms() as i64);
+    let mut changed = false;
+
+    {
+        let mut interfaces = INTERFACES.write();
+        let mut sockets = SOCKETS.get().expect("Socket set not initialized!").write();
+        let time = Instant::from_millis(timer().systime_ms() as i64);
 
-    // Smoltcp expects a mutable reference to the device, but the RTL8139 driver is built
-    // to work with a shared reference. We can safely cast the shared reference to a mutable.
-    let device = unsafe { ptr::from_ref(rtl8139.deref()).cast_mut().as_mut().unwrap() };
+        // Smoltcp expects a mutable reference to the device, but the RTL8139 driver is built
+        // to work with a shared reference. We can safely cast the shared reference to a mutable.
+        let device = unsafe { ptr::from_ref(rtl8139.deref()).cast_mut().as_mut().unwrap() };
+
+        for interface in interfaces.iter_mut() {
+            changed |= interface.poll(time, device, &mut sockets) == PollResult::SocketStateChanged;
+        }
+    }
 
-    for interface in interfaces.iter_mut() {
-        interface.poll(time, device, &mut sockets);
+    if changed {
+        scheduler().wake_up(&SOCKET_QUEUE);
     }
 }
\ No newline at end of file
@@ -0,0 +1,93 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: udp_socket                                                      ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ UDP sockets used through the handles of the naming service. A socket is ║
+   ║ bound to a local port and sends its datagrams to a fixed remote         ║
+   ║ endpoint. Reading blocks until a datagram is received (one datagram per ║
+   ║ read), writing sends the buffer as one datagram. Sockets can be waited  ║
+   ║ for with 'poll' together with pipes and the terminal.                   ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use alloc::sync::Arc;
+use core::fmt;
+use smoltcp::iface::SocketHandle;
+use smoltcp::socket::udp;
+use smoltcp::wire::Ipv4Address;
+
+use crate::naming::traits::FileObject;
+use crate::scheduler;
+use naming::shared_types::{OpenOptions, PollFlags};
+use syscall::return_vals::Errno;
+
+/// UDP socket, closed when the last handle referencing it is closed
+pub struct UdpSocket {
+    handle: SocketHandle,
+    remote: Option<(Ipv4Address, u16)>, // destination of written datagrams
+}
+
+impl UdpSocket {
+    /// Open a socket bound to `port`, sending to `remote` (if given). \
+    /// Returns `Ok(socket)` or `Err(Errno::EADDRINUSE)` if the port cannot be used
+    pub fn open(port: u16, remote: Option<(Ipv4Address, u16)>) -> Result<Arc<UdpSocket>, Errno> {
+        let handle = super::open_socket(super::SocketType::Udp);
+        if super::bind_udp(handle, port).is_err() {
+            super::close_socket(handle);
+            return Err(Errno::EADDRINUSE);
+        }
+        Ok(Arc::new(UdpSocket { handle, remote }))
+    }
+}
+
+impl FileObject for UdpSocket {
+    /// Receive the next datagram (truncated to the size of `buf`)
+    fn read(&self, buf: &mut [u8], _offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        loop {
+            match super::receive_datagram(self.handle, buf) {
+                Ok(Some(len)) => return Ok(len),
+                Ok(None) => {
+                    scheduler().wait(&super::SOCKET_QUEUE, None, || super::udp_state(self.handle).0);
+                }
+                Err(udp::RecvError::Truncated) => return Ok(buf.len()),
+                Err(_) => return Err(Errno::EIO),
+            }
+        }
+    }
+
+    /// Send `buf` as one datagram to the remote endpoint
+    fn write(&self, buf: &[u8], _offset: usize, _options: OpenOptions) -> Result<usize, Errno> {
+        let (address, port) = self.remote.ok_or(Errno::ENOTCONN)?;
+        loop {
+            match super::send_datagram(self.handle, address, port, buf) {
+                Ok(()) => return Ok(buf.len()),
+                Err(udp::SendError::BufferFull) => {
+                    scheduler().wait(&super::SOCKET_QUEUE, None, || super::udp_state(self.handle).1);
+                }
+                Err(_) => return Err(Errno::EINVAL), // unaddressable
+            }
+        }
+    }
+
+    fn poll(&self) -> PollFlags {
+        let (can_receive, can_send) = super::udp_state(self.handle);
+        let mut events = PollFlags::empty();
+        if can_receive {
+            events |= PollFlags::READABLE;
+        }
+        if can_send && self.remote.is_some() {
+            events |= PollFlags::WRITABLE;
+        }
+        events
+    }
+}
+
+impl Drop for UdpSocket {
+    fn drop(&mut self) {
+        super::close_socket(self.handle);
+    }
+}
+
+impl fmt::Debug for UdpSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UdpSocket").field("remote", &self.remote).finish()
+    }
+}
@@ -6,13 +6,16 @@
    ║ Author: Fabian Ruhland, HHU                                             ║
    ╚═════════════════════════════════════════════════════════════════════════╝
 */
+use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
-use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::{AtomicU32, AtomicUsize};
 use core::sync::atomic::Ordering::Relaxed;
+use spin::Mutex;
 use crate::{ process_manager, scheduler};
 use crate::memory::pages::Paging;
 use crate::memory::vmm::VirtualAddressSpace;
+use crate::naming::open_objects::OpenObjectTable;
 
 static PROCESS_ID_COUNTER: AtomicUsize = AtomicUsize::new(1);
 
@@ -24,12 +27,25 @@ fn next_process_id() -> usize {
 pub struct Process {
     pub id: usize,
     pub virtual_address_space: VirtualAddressSpace,
+    pub open_objects: Mutex<OpenObjectTable>, // handles of the naming service
+    pub cwd: Mutex<String>,                   // current working directory (absolute path)
+    pub cmdline: Mutex<String>,               // name and arguments of the application (empty for the kernel)
+    uid: AtomicU32,                           // user id (0 = root)
+    gid: AtomicU32,                           // group id
 }
 
 
 impl Process {
-    pub fn new(page_tables: Arc<Paging>) -> Self {
-        Self { id: next_process_id(), virtual_address_space: VirtualAddressSpace::new(page_tables) }
+    pub fn new(page_tables: Arc<Paging>, cwd: String, uid: u32, gid: u32) -> Self {
+        Self {
+            id: next_process_id(),
+            virtual_address_space: VirtualAddressSpace::new(page_tables),
+            open_objects: Mutex::new(OpenObjectTable::new()),
+            cwd: Mutex::new(cwd),
+            cmdline: Mutex::new(String::new()),
+            uid: AtomicU32::new(uid),
+            gid: AtomicU32::new(gid),
+        }
     }
 
     /// Return the id of the process
@@ -37,6 +53,24 @@ impl Process {
         self.id
     }
 
+    /// Return the user id of the process
+    pub fn uid(&self) -> u32 {
+        self.uid.load(Relaxed)
+    }
+
+    /// Return the group id of the process
+    pub fn gid(&self) -> u32 {
+        self.gid.load(Relaxed)
+    }
+
+    pub fn set_uid(&self, uid: u32) {
+        self.uid.store(uid, Relaxed);
+    }
+
+    pub fn set_gid(&self, gid: u32) {
+        self.gid.store(gid, Relaxed);
+    }
+
     pub fn exit(&self) {
         process_manager().write().exit(self.id);
     }
@@ -49,6 +83,11 @@ impl Process {
             }).copied().collect()
     }
 
+    /// Close all handles of the naming service opened by the process
+    pub fn close_all_handles(&self) {
+        self.open_objects.lock().close_all();
+    }
+
     pub fn kill_all_threads_but_current(&self) {
         self.thread_ids().iter()
             .filter(|&&thread_id| thread_id != scheduler().current_thread().id())
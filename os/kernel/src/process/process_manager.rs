@@ -6,11 +6,13 @@
    ║ Author: Fabian Ruhland, Univ. Duesseldorf, 02.03.2025                   ║
    ╚═════════════════════════════════════════════════════════════════════════╝
 */
+use alloc::string::ToString;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use log::info;
 
 use crate::memory::vmm;
+use crate::naming::stat::ROOT_UID;
 use crate::process::process::Process;
 use crate::scheduler;
 
@@ -27,17 +29,20 @@ impl ProcessManager {
         }
     }
 
-    /// Create a new process
+    /// Create a new process (inheriting the current working directory, user and group id of the current process)
     pub fn create_process(&mut self) -> Arc<Process> {
-        let paging = match self.kernel_process() {
+        let (paging, cwd, uid, gid) = match self.kernel_process() {
             Some(kernel_process) => {
                 // Create user address space
-                vmm::clone_address_space(&(kernel_process.virtual_address_space))
+                let paging = vmm::clone_address_space(&(kernel_process.virtual_address_space));
+                let current = self.current_process();
+                let cwd = current.cwd.lock().clone();
+                (paging, cwd, current.uid(), current.gid())
             }
-            None => vmm::create_kernel_address_space(),
+            None => (vmm::create_kernel_address_space(), "/".to_string(), ROOT_UID, 0),
         };
 
-        let process = Arc::new(Process::new(paging));
+        let process = Arc::new(Process::new(paging, cwd, uid, gid));
         self.active_processes.push(Arc::clone(&process));
 
         info!("Process [{}]: created", process.id());
@@ -52,6 +57,14 @@ impl ProcessManager {
             .collect()
     }
 
+    /// Return the active process with the id `process_id`
+    pub fn process(&self, process_id: usize) -> Option<Arc<Process>> {
+        self.active_processes
+            .iter()
+            .find(|process| process.id() == process_id)
+            .cloned()
+    }
+
     pub fn kernel_process(&self) -> Option<Arc<Process>> {
         match self.active_processes.get(0) {
             Some(kernel_process) => Some(Arc::clone(kernel_process)),
@@ -76,6 +89,7 @@ impl ProcessManager {
 
         let process = Arc::clone(&self.active_processes[index]);
         process.kill_all_threads_but_current();
+        process.close_all_handles();
 
         self.active_processes.swap_remove(index);
         self.exited_processes.push(process);
@@ -92,6 +106,7 @@ impl ProcessManager {
         for thread_id in process.thread_ids() {
             scheduler().kill(thread_id);
         }
+        process.close_all_handles();
 
         self.active_processes.swap_remove(index);
         self.exited_processes.push(process);
@@ -12,7 +12,7 @@ use alloc::collections::VecDeque;
 use alloc::rc::Rc;
 use alloc::vec::Vec;
 use core::ptr;
-use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::{AtomicBool, AtomicUsize};
 use core::sync::atomic::Ordering::Relaxed;
 use smallmap::Map;
 use spin::{Mutex, MutexGuard};
@@ -41,11 +41,32 @@ impl ReadyState {
     }
 }
 
+/// Threads blocked until an event occurs, e.g. data arriving in a pipe (see `Scheduler::wait` and `Scheduler::wake_up`).
+/// The list of threads is only locked while holding the lock of the ready state.
+pub struct WaitQueue {
+    threads: Mutex<Vec<Rc<Thread>>>,
+    pending: AtomicBool, // wakeup from an interrupt handler, which has not been delivered yet
+}
+
+unsafe impl Send for WaitQueue {}
+unsafe impl Sync for WaitQueue {}
+
+impl WaitQueue {
+    pub const fn new() -> Self {
+        Self {
+            threads: Mutex::new(Vec::new()),
+            pending: AtomicBool::new(false),
+        }
+    }
+}
+
 /// Main struct of the scheduler
 pub struct Scheduler {
     ready_state: Mutex<ReadyState>,
     sleep_list: Mutex<Vec<(Rc<Thread>, usize)>>,
     join_map: Mutex<Map<usize, Vec<Rc<Thread>>>>, // manage which threads are waiting for a thread-id to terminate
+    any_event: WaitQueue, // threads woken up by a wakeup of any wait queue (see `wait_any`)
+    interrupt_queues: Mutex<Vec<&'static WaitQueue>>, // wait queues woken up by interrupt handlers
 }
 
 unsafe impl Send for Scheduler {}
@@ -65,6 +86,8 @@ impl Scheduler {
             ready_state: Mutex::new(ReadyState::new()),
             sleep_list: Mutex::new(Vec::new()),
             join_map: Mutex::new(Map::new()),
+            any_event: WaitQueue::new(),
+            interrupt_queues: Mutex::new(Vec::new()),
         }
     }
 
@@ -159,6 +182,98 @@ impl Scheduler {
         }
     }
 
+    ///
+    /// Description: Block the calling thread in `queue` until `done` returns true or the system time reaches
+    ///              `deadline` (in ms). `done` is called before blocking and after each wakeup without holding
+    ///              a lock of the scheduler, so it may lock the object waited for (e.g. the buffer of a pipe).
+    ///
+    /// Return: `true` if `done` returned true, `false` if the deadline has been reached
+    ///
+    pub fn wait(&self, queue: &WaitQueue, deadline: Option<usize>, mut done: impl FnMut() -> bool) -> bool {
+        loop {
+            if done() {
+                return true;
+            }
+            if deadline.is_some_and(|deadline| timer().systime_ms() >= deadline) {
+                return false;
+            }
+
+            let thread_id;
+            {
+                let state = self.get_ready_state();
+                if !state.initialized {
+                    continue;
+                }
+
+                let thread = Scheduler::current(&state);
+                thread_id = thread.id();
+                queue.threads.lock().push(thread);
+            }
+
+            // Check again, because the event may have occurred before the thread has been enqueued
+            if !done() {
+                let mut state = self.get_ready_state();
+                let enqueued = queue.threads.lock().iter().any(|thread| thread.id() == thread_id);
+
+                // Not enqueued anymore -> woken up in the meantime
+                if enqueued && !queue.pending.load(Relaxed) {
+                    if let Some(deadline) = deadline {
+                        // Execute in own block, so that the lock is released automatically (block() does not return)
+                        let mut sleep_list = self.sleep_list.lock();
+                        sleep_list.push((Scheduler::current(&state), deadline));
+                    }
+
+                    self.block(&mut state);
+                }
+            }
+
+            // Leave the queue and the sleep list, if the thread has been woken up by the other one
+            let _state = self.get_ready_state();
+            queue.threads.lock().retain(|thread| thread.id() != thread_id);
+            self.sleep_list.lock().retain(|entry| entry.0.id() != thread_id);
+        }
+    }
+
+    /// Description: Like `wait`, but woken up by the wakeup of any wait queue (used for waiting on several objects)
+    pub fn wait_any(&self, deadline: Option<usize>, done: impl FnMut() -> bool) -> bool {
+        self.wait(&self.any_event, deadline, done)
+    }
+
+    /// Description: Wake up all threads waiting in `queue` (and those waiting for any event)
+    pub fn wake_up(&self, queue: &WaitQueue) {
+        let mut state = self.get_ready_state();
+        let mut sleep_list = self.sleep_list.lock();
+
+        Scheduler::wake_up_queue(&mut state, &mut sleep_list, queue, false);
+        Scheduler::wake_up_queue(&mut state, &mut sleep_list, &self.any_event, false);
+    }
+
+    ///
+    /// Description: Like `wake_up`, but called from an interrupt handler and thus never waiting for a lock.
+    ///              If the scheduler is locked, the wakeup is delivered on the next thread switch.
+    ///
+    /// Parameters: `queue` wait queue registered with `register_interrupt_queue`
+    ///
+    pub fn wake_up_from_interrupt(&self, queue: &WaitQueue) {
+        queue.pending.store(true, Relaxed);
+        self.any_event.pending.store(true, Relaxed);
+        if allocator().is_locked() {
+            return;
+        }
+
+        if let Some(mut state) = self.ready_state.try_lock() {
+            if let Some(mut sleep_list) = self.sleep_list.try_lock() {
+                self.deliver_interrupt_wakeups(&mut state, &mut sleep_list, false);
+            }
+        }
+    }
+
+    /// Description: Register `queue` to be woken up by an interrupt handler (see `wake_up_from_interrupt`)
+    pub fn register_interrupt_queue(&self, queue: &'static WaitQueue) {
+        let _state = self.get_ready_state();
+        self.interrupt_queues.lock().push(queue);
+    }
+
     /// 
     /// Description: Switch from current to next thread (from ready queue)
     /// 
@@ -173,6 +288,7 @@ impl Scheduler {
 
             if let Some(mut sleep_list) = self.sleep_list.try_lock() {
                 Scheduler::check_sleep_list(&mut state, &mut sleep_list);
+                self.deliver_interrupt_wakeups(&mut state, &mut sleep_list, false);
             }
 
             let current = Scheduler::current(&state);
@@ -305,6 +421,7 @@ impl Scheduler {
             let mut sleep_list = self.sleep_list.lock();
             while next_thread.is_none() {
                 Scheduler::check_sleep_list(state, &mut sleep_list);
+                self.deliver_interrupt_wakeups(state, &mut sleep_list, true);
                 next_thread = state.ready_queue.pop_back();
             }
         }
@@ -346,6 +463,34 @@ impl Scheduler {
         });
     }
 
+    ///
+    /// Description: Move all threads waiting in `queue` to the ready queue and remove their deadlines
+    ///
+    /// Parameters: `current_blocked` true = the current thread is blocking and may be woken up as well
+    ///                               false = the current thread is running and checks its condition itself
+    ///
+    fn wake_up_queue(state: &mut ReadyState, sleep_list: &mut Vec<(Rc<Thread>, usize)>, queue: &WaitQueue, current_blocked: bool) {
+        for thread in queue.threads.lock().drain(..) {
+            sleep_list.retain(|entry| entry.0.id() != thread.id());
+
+            // A thread woken up by its deadline may still be in the queue
+            let running = state.current_thread.as_ref().is_some_and(|current| current.id() == thread.id());
+            if (current_blocked || !running) && !state.ready_queue.iter().any(|ready| ready.id() == thread.id()) {
+                state.ready_queue.push_front(thread);
+            }
+        }
+    }
+
+    /// Description: Deliver wakeups of interrupt handlers, which could not lock the scheduler
+    fn deliver_interrupt_wakeups(&self, state: &mut ReadyState, sleep_list: &mut Vec<(Rc<Thread>, usize)>, current_blocked: bool) {
+        let interrupt_queues = self.interrupt_queues.lock();
+        for queue in interrupt_queues.iter().copied().chain([&self.any_event]) {
+            if queue.pending.swap(false, Relaxed) {
+                Scheduler::wake_up_queue(state, sleep_list, queue, current_blocked);
+            }
+        }
+    }
+
     /// Description: Helper function returning `ReadyState` of scheduler in a MutexGuard
     fn get_ready_state(&self) -> MutexGuard<ReadyState> {
         let state;
@@ -34,7 +34,7 @@
 
 use crate::consts::MAIN_USER_STACK_START;
 use crate::consts::MAX_USER_STACK_SIZE;
-use crate::consts::{KERNEL_STACK_PAGES, USER_SPACE_ENV_START};
+use crate::consts::{KERNEL_STACK_PAGES, USER_SPACE_CODE_START, USER_SPACE_ENV_START};
 use crate::memory::kstack::StackAllocator;
 use crate::memory::vmm::VmaType;
 use crate::memory::{MemorySpace, PAGE_SIZE};
@@ -43,6 +43,7 @@ use crate::process::scheduler;
 use crate::syscall::syscall_dispatcher::CORE_LOCAL_STORAGE_TSS_RSP0_PTR_INDEX;
 use crate::{memory, process_manager, scheduler, tss};
 use alloc::rc::Rc;
+use alloc::string::ToString;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::arch::naked_asm;
@@ -51,6 +52,7 @@ use goblin::elf::Elf;
 use goblin::elf64;
 use log::info;
 use spin::Mutex;
+use syscall::return_vals::Errno;
 use x86_64::PrivilegeLevel::Ring3;
 use x86_64::VirtAddr;
 use x86_64::structures::gdt::SegmentSelector;
@@ -86,6 +88,33 @@ impl Stacks {
     }
 }
 
+/// Parse the application `elf_buffer` and check that its loadable segments can be mapped: each segment
+/// has to start at a page boundary within the code area of user space, must not overlap another one
+/// and its file content has to lie within `elf_buffer`. \
+/// Returns the parsed ELF file or `Err(Errno::ENOEXEC)`.
+fn parse_application(elf_buffer: &[u8]) -> Result<Elf<'_>, Errno> {
+    let elf = Elf::parse(elf_buffer).map_err(|_| Errno::ENOEXEC)?;
+
+    let mut areas = Vec::new();
+    for header in elf.program_headers.iter().filter(|header| header.p_type == elf64::program_header::PT_LOAD && header.p_memsz > 0) {
+        let start = header.p_vaddr as usize;
+        let end = start.checked_add(header.p_memsz as usize).ok_or(Errno::ENOEXEC)?;
+        let file_end = (header.p_offset as usize).checked_add(header.p_filesz as usize).ok_or(Errno::ENOEXEC)?;
+        if start % PAGE_SIZE != 0 || start < USER_SPACE_CODE_START || end > USER_SPACE_ENV_START
+            || header.p_filesz > header.p_memsz || file_end > elf_buffer.len() {
+            return Err(Errno::ENOEXEC);
+        }
+
+        let end = end.next_multiple_of(PAGE_SIZE);
+        if areas.iter().any(|&(other_start, other_end)| start < other_end && other_start < end) {
+            return Err(Errno::ENOEXEC);
+        }
+        areas.push((start, end));
+    }
+
+    Ok(elf)
+}
+
 impl Thread {
     /// Create a kernel thread. Not started yet, nor registered in the scheduler. \
     /// `entry` is the thread entry function.
@@ -131,16 +160,24 @@ impl Thread {
 
     /// Load application code from `elf_buffer`, create a process with a main thread. \
     /// `name` is the name of the application, `args` are the arguments passed to the application. \
-    /// Returns the main thread of the application which is not yet registered in the scheduler.
-    pub fn load_application(elf_buffer: &[u8], name: &str, args: &Vec<&str>) -> Rc<Thread> {
+    /// Returns the main thread of the application which is not yet registered in the scheduler
+    /// or `Err(Errno::ENOEXEC)` if `elf_buffer` is not a loadable ELF file (see `parse_application`).
+    pub fn load_application(elf_buffer: &[u8], name: &str, args: &Vec<&str>) -> Result<Rc<Thread>, Errno> {
+        let elf = parse_application(elf_buffer)?;
         let process = process_manager().write().create_process();
         //let address_space = process.address_space();
 
-        // Parse elf file headers and map code vma if successful
-        let elf = Elf::parse(elf_buffer).expect("Failed to parse application");
+        let mut cmdline = name.to_string();
+        for arg in args {
+            cmdline.push(' ');
+            cmdline.push_str(arg);
+        }
+        *process.cmdline.lock() = cmdline;
+
+        // Map code vma (segments have been validated above)
         elf.program_headers
             .iter()
-            .filter(|header| header.p_type == elf64::program_header::PT_LOAD)
+            .filter(|header| header.p_type == elf64::program_header::PT_LOAD && header.p_memsz > 0)
             .for_each(|header| {
                 let page_count = if header.p_memsz as usize % PAGE_SIZE == 0 {
                     header.p_memsz as usize / PAGE_SIZE
@@ -284,7 +321,7 @@ impl Thread {
         info!("***ms thread");
 
         thread.prepare_kernel_stack();
-        Rc::new(thread)
+        Ok(Rc::new(thread))
     }
 
     /// Create user thread. Not started yet, nor registered in the scheduler. \
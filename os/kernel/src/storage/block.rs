@@ -72,8 +72,8 @@ impl BlockDevice for Partition {
             return 0;
         }
 
-        let sector = sector + self.start_sector;
         let count = count.min((self.sector_count - sector) as usize);
+        let sector = sector + self.start_sector;
         self.device.read(sector, count, buffer)
     }
 
@@ -82,8 +82,8 @@ impl BlockDevice for Partition {
             return 0;
         }
 
-        let sector = sector + self.start_sector;
         let count = count.min((self.sector_count - sector) as usize);
+        let sector = sector + self.start_sector;
         self.device.write(sector, count, buffer)
     }
 
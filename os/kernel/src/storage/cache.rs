@@ -0,0 +1,261 @@
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use spin::Mutex;
+use crate::storage::block::BlockDevice;
+use syscall::return_vals::Errno;
+
+/// Maximum number of sectors held by a cache
+const CACHE_SECTORS: usize = 2048;
+
+/// Maximum number of sectors transferred from/to the device with one request
+const MAX_TRANSFER_SECTORS: usize = 64;
+
+/// A cached sector
+struct CacheEntry {
+    data: Vec<u8>,
+    dirty: bool,
+    last_used: u64,
+    last_written: u64, // time of the last modification (a write-back only cleans the version it has written)
+}
+
+/// Cached sectors, indexed by sector number and by time of last use (for LRU eviction)
+struct CacheState {
+    entries: BTreeMap<u64, CacheEntry>,
+    lru: BTreeMap<u64, u64>, // time of last use -> sector
+    time: u64,
+}
+
+/// Sector cache wrapping any block device.
+/// Read sectors are kept in memory and written sectors are only marked as dirty.
+/// Dirty sectors are written back, when they are evicted (least recently used first) or when `flush` is called.
+/// The lock of the cache is never held during device I/O, so a sector may change while it is written back.
+pub struct BlockCache {
+    device: Arc<dyn BlockDevice + Send + Sync>,
+    state: Mutex<CacheState>,
+}
+
+impl BlockCache {
+    pub fn new(device: Arc<dyn BlockDevice + Send + Sync>) -> Self {
+        BlockCache {
+            device,
+            state: Mutex::new(CacheState {
+                entries: BTreeMap::new(),
+                lru: BTreeMap::new(),
+                time: 0,
+            }),
+        }
+    }
+
+    /// Write all dirty sectors back to the device (consecutive sectors with one request).
+    /// Returns `Err(Errno::EIO)`, if the device failed to write a sector (it stays dirty).
+    pub fn flush(&self) -> Result<(), Errno> {
+        let sector_size = self.sector_size() as usize;
+        let dirty: Vec<u64> = self.state.lock().entries.iter()
+            .filter(|(_, entry)| entry.dirty)
+            .map(|(&sector, _)| sector)
+            .collect();
+
+        let mut success = true;
+        let mut i = 0;
+        while i < dirty.len() {
+            let start = dirty[i];
+            let mut count = 1;
+            while i + count < dirty.len() && count < MAX_TRANSFER_SECTORS && dirty[i + count] == start + count as u64 {
+                count += 1;
+            }
+
+            // Copy the current data (sectors evicted in the meantime have been written back already)
+            let mut buffer = vec![0u8; count * sector_size];
+            let mut versions = Vec::with_capacity(count);
+            {
+                let state = self.state.lock();
+                for (index, chunk) in buffer.chunks_mut(sector_size).enumerate() {
+                    let entry = state.entries.get(&(start + index as u64));
+                    if let Some(entry) = entry {
+                        chunk.copy_from_slice(&entry.data);
+                    }
+                    versions.push(entry.map(|entry| entry.last_written));
+                }
+            }
+
+            let count = match versions.iter().position(Option::is_none) {
+                Some(0) => {
+                    i += 1;
+                    continue;
+                }
+                Some(present) => present,
+                None => count,
+            };
+
+            let written = self.device.write(start, count, &buffer[..count * sector_size]);
+            let mut state = self.state.lock();
+            for (index, version) in versions.iter().enumerate().take(written.min(count)) {
+                BlockCache::mark_clean(&mut state, start + index as u64, version.unwrap());
+            }
+            success &= written == count;
+
+            i += count;
+        }
+
+        if success { Ok(()) } else { Err(Errno::EIO) }
+    }
+
+    /// Mark `sector` as used right now
+    fn touch(state: &mut CacheState, sector: u64) {
+        state.time += 1;
+        let time = state.time;
+        if let Some(entry) = state.entries.get_mut(&sector) {
+            state.lru.remove(&entry.last_used);
+            entry.last_used = time;
+            state.lru.insert(time, sector);
+        }
+    }
+
+    /// Mark `sector` as clean, if it has not been modified since the version `last_written` has been written back
+    fn mark_clean(state: &mut CacheState, sector: u64, last_written: u64) {
+        if let Some(entry) = state.entries.get_mut(&sector) {
+            if entry.last_written == last_written {
+                entry.dirty = false;
+            }
+        }
+    }
+
+    /// Insert `data` for `sector` (replacing a cached version). The cache may exceed its size until `evict` is called.
+    fn insert(state: &mut CacheState, sector: u64, data: Vec<u8>, dirty: bool) {
+        state.time += 1;
+        let time = state.time;
+        if let Some(entry) = state.entries.get_mut(&sector) {
+            entry.data = data;
+            if dirty {
+                entry.dirty = true;
+                entry.last_written = time;
+            }
+            BlockCache::touch(state, sector);
+            return;
+        }
+
+        state.entries.insert(sector, CacheEntry { data, dirty, last_used: time, last_written: time });
+        state.lru.insert(time, sector);
+    }
+
+    /// Evict the least recently used sectors until the cache has its max. size, writing dirty sectors back first
+    fn evict(&self) {
+        loop {
+            let (sector, data, last_written) = {
+                let mut state = self.state.lock();
+                if state.entries.len() <= CACHE_SECTORS {
+                    return;
+                }
+                let Some((&time, &victim)) = state.lru.first_key_value() else {
+                    return;
+                };
+
+                let entry = &state.entries[&victim];
+                if !entry.dirty {
+                    state.lru.remove(&time);
+                    state.entries.remove(&victim);
+                    continue;
+                }
+                (victim, entry.data.clone(), entry.last_written)
+            };
+
+            let written = self.device.write(sector, 1, &data) == 1;
+            let mut state = self.state.lock();
+            if !written {
+                // Keep sector, since its data would be lost (try again when it becomes the oldest entry again)
+                BlockCache::touch(&mut state, sector);
+                return;
+            }
+            BlockCache::mark_clean(&mut state, sector, last_written);
+        }
+    }
+}
+
+impl BlockDevice for BlockCache {
+    fn read(&self, sector: u64, count: usize, buffer: &mut [u8]) -> usize {
+        let sector_count = self.sector_count();
+        if sector >= sector_count {
+            return 0;
+        }
+
+        let sector_size = self.sector_size() as usize;
+        let count = count.min((sector_count - sector) as usize).min(buffer.len() / sector_size);
+
+        let mut i = 0;
+        while i < count {
+            // Copy cached sectors and find the following sectors missing in the cache
+            let (current, missing) = {
+                let mut state = self.state.lock();
+                while i < count {
+                    let Some(entry) = state.entries.get(&(sector + i as u64)) else {
+                        break;
+                    };
+                    buffer[i * sector_size..(i + 1) * sector_size].copy_from_slice(&entry.data);
+                    BlockCache::touch(&mut state, sector + i as u64);
+                    i += 1;
+                }
+                if i == count {
+                    break;
+                }
+
+                let current = sector + i as u64;
+                let mut missing = 1;
+                while i + missing < count && missing < MAX_TRANSFER_SECTORS && !state.entries.contains_key(&(current + missing as u64)) {
+                    missing += 1;
+                }
+                (current, missing)
+            };
+
+            // Read them with one request
+            let target = &mut buffer[i * sector_size..(i + missing) * sector_size];
+            let read = self.device.read(current, missing, target);
+            {
+                let mut state = self.state.lock();
+                for (index, data) in target.chunks_exact_mut(sector_size).take(read.min(missing)).enumerate() {
+                    match state.entries.get(&(current + index as u64)) {
+                        // Written while reading, so the cached data is newer
+                        Some(entry) => data.copy_from_slice(&entry.data),
+                        None => BlockCache::insert(&mut state, current + index as u64, data.to_vec(), false),
+                    }
+                }
+            }
+            self.evict();
+
+            if read < missing {
+                return i + read;
+            }
+            i += missing;
+        }
+
+        count
+    }
+
+    fn write(&self, sector: u64, count: usize, buffer: &[u8]) -> usize {
+        let sector_count = self.sector_count();
+        if sector >= sector_count {
+            return 0;
+        }
+
+        let sector_size = self.sector_size() as usize;
+        let count = count.min((sector_count - sector) as usize).min(buffer.len() / sector_size);
+        {
+            let mut state = self.state.lock();
+            for (index, data) in buffer.chunks_exact(sector_size).take(count).enumerate() {
+                BlockCache::insert(&mut state, sector + index as u64, data.to_vec(), true);
+            }
+        }
+        self.evict();
+
+        count
+    }
+
+    fn sector_count(&self) -> u64 {
+        self.device.sector_count()
+    }
+
+    fn sector_size(&self) -> u16 {
+        self.device.sector_size()
+    }
+}
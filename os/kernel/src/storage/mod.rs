@@ -1,14 +1,22 @@
 use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use log::info;
 use smallmap::Map;
 use spin::{Mutex, Once, RwLock};
 use crate::device::ide;
 use crate::storage::block::BlockDevice;
+use crate::storage::cache::BlockCache;
+use syscall::return_vals::Errno;
 
 pub mod block;
+pub mod cache;
 
+/// Interval for writing dirty sectors of all block caches back (see `sync`)
+pub const WRITE_BACK_INTERVAL_MS: usize = 5000;
+
+static BLOCK_CACHES: Mutex<Vec<(String, Arc<BlockCache>)>> = Mutex::new(Vec::new());
 static BLOCK_DEVICES: Once<RwLock<Map<String, Arc<dyn BlockDevice + Send + Sync>>>> = Once::new();
 static DEVICE_TYPES: Once<Mutex<Map<String, usize>>> = Once::new();
 
@@ -26,6 +34,11 @@ pub fn add_block_device(typ: &str, drive: Arc<dyn BlockDevice + Send + Sync>) {
     let name = format!("{}{}", typ, index);
     types.insert(typ, index + 1);
 
+    // All accesses (including partitions) go through the cache
+    let cache = Arc::new(BlockCache::new(drive));
+    BLOCK_CACHES.lock().push((name.clone(), Arc::clone(&cache)));
+    let drive: Arc<dyn BlockDevice + Send + Sync> = cache;
+
     let partitions = block::scan_partitions(&drive);
 
     let mut drives = BLOCK_DEVICES.call_once(|| RwLock::new(Map::new())).write();
@@ -48,4 +61,26 @@ pub fn block_device(name: &str) -> Option<Arc<dyn BlockDevice + Send + Sync>> {
         None => None,
         Some(device) => Some(Arc::clone(device))
     }
-}
\ No newline at end of file
+}
+/// Get the names of all registered block devices (sorted by name)
+pub fn block_device_names() -> Vec<String> {
+    let mut names: Vec<String> = BLOCK_DEVICES.call_once(|| RwLock::new(Map::new())).read().keys().cloned().collect();
+    names.sort();
+    names
+}
+
+/// Write all dirty sectors of all block caches back to their devices. \
+/// Returns `Err(Errno::EIO)` if a device failed to write a sector (the others are written nevertheless).
+pub fn sync() -> Result<(), Errno> {
+    let caches: Vec<Arc<BlockCache>> = BLOCK_CACHES.lock().iter().map(|(_, cache)| Arc::clone(cache)).collect();
+    caches.iter().fold(Ok(()), |result, cache| result.and(cache.flush()))
+}
+
+/// Write all dirty sectors of the block device `name` back (for a partition those of its drive). \
+/// Returns `Err(Errno::EIO)` if the device failed to write a sector.
+pub fn sync_device(name: &str) -> Result<(), Errno> {
+    let cache = BLOCK_CACHES.lock().iter()
+        .find(|(drive, _)| name.strip_prefix(drive.as_str()).is_some_and(|rest| rest.is_empty() || rest.starts_with('p')))
+        .map(|(_, cache)| Arc::clone(cache));
+    cache.map_or(Ok(()), |cache| cache.flush())
+}
@@ -12,5 +12,6 @@ pub mod sys_terminal;
 pub mod sys_concurrent;
 pub mod sys_time;
 pub mod sys_vmem;
+pub mod sys_network;
 
 pub mod syscall_dispatcher;
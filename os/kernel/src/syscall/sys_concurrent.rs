@@ -6,13 +6,16 @@
    ║ Author: Fabian Ruhland, 30.8.2024, HHU                                  ║
    ╚═════════════════════════════════════════════════════════════════════════╝
 */
+use alloc::format;
+use alloc::string::ToString;
 use alloc::vec::Vec;
 use alloc::rc::Rc;
 use core::ptr::slice_from_raw_parts;
 use core::str::from_utf8;
 use x86_64::VirtAddr;
 use syscall::return_vals::Errno;
-use crate::{initrd, process_manager, scheduler};
+use crate::{naming, process_manager, scheduler};
+use crate::naming::stat::ROOT_UID;
 use crate::process::thread::Thread;
 
 
@@ -20,6 +23,34 @@ pub fn sys_process_id() -> isize {
     process_manager().read().current_process().id() as isize
 }
 
+pub fn sys_get_uid() -> isize {
+    process_manager().read().current_process().uid() as isize
+}
+
+/// Set the user id of the current process to `uid` (only root may switch to another user)
+pub fn sys_set_uid(uid: u32) -> isize {
+    let process = process_manager().read().current_process();
+    if process.uid() != ROOT_UID && process.uid() != uid {
+        return Errno::EACCES.into();
+    }
+    process.set_uid(uid);
+    0
+}
+
+pub fn sys_get_gid() -> isize {
+    process_manager().read().current_process().gid() as isize
+}
+
+/// Set the group id of the current process to `gid` (only root may switch to another group)
+pub fn sys_set_gid(gid: u32) -> isize {
+    let process = process_manager().read().current_process();
+    if process.uid() != ROOT_UID && process.gid() != gid {
+        return Errno::EACCES.into();
+    }
+    process.set_gid(gid);
+    0
+}
+
 pub fn sys_process_exit() -> isize {
     scheduler().current_thread().process().exit();
     scheduler().exit();
@@ -58,14 +89,39 @@ pub fn sys_thread_exit() -> isize {
     0
 }
 
-pub fn sys_process_execute_binary(name_buffer: *const u8, name_length: usize, args: *const Vec<&str>) -> isize {
+/// Load and start the application `name`. A name without slashes refers to an application in `/bin`,
+/// otherwise `name` is an absolute path or a path relative to the current working directory. \
+/// `handles` points to `handles_count` pairs `(handle in child, handle in caller)` of opened objects passed to the child.
+pub fn sys_process_execute_binary(name_buffer: *const u8, name_length: usize, args: *const Vec<&str>, handles: *const (usize, usize), handles_count: usize) -> isize {
     let app_name = from_utf8(unsafe { slice_from_raw_parts(name_buffer, name_length).as_ref().unwrap() }).unwrap();
-    match initrd().entries().find(|entry| entry.filename().as_str().unwrap() == app_name) {
-        Some(app) => {
-            let thread = Thread::load_application(app.data(), app_name, unsafe { args.as_ref().unwrap() });
-            scheduler().ready(Rc::clone(&thread));
-            thread.id() as isize
-        }
-        None => Errno::ENOENT.into(),
-    }
+    let path = if app_name.contains('/') {
+        app_name.to_string()
+    } else {
+        format!("/bin/{}", app_name)
+    };
+
+    let handles = if handles_count == 0 {
+        &[]
+    } else {
+        unsafe { slice_from_raw_parts(handles, handles_count).as_ref().unwrap() }
+    };
+    let open_objects = match process_manager().read().current_process().open_objects.lock().clone_handles(handles) {
+        Ok(open_objects) => open_objects,
+        Err(errno) => return errno.into(),
+    };
+
+    let elf_buffer = match naming::api::load_file(&path) {
+        Ok(elf_buffer) => elf_buffer,
+        Err(errno) => return errno.into(),
+    };
+
+    let name = app_name.rsplit('/').next().unwrap_or(app_name);
+    let thread = match Thread::load_application(&elf_buffer, name, unsafe { args.as_ref().unwrap() }) {
+        Ok(thread) => thread,
+        Err(errno) => return errno.into(),
+    };
+    *thread.process().open_objects.lock() = open_objects;
+
+    scheduler().ready(Rc::clone(&thread));
+    thread.id() as isize
 }
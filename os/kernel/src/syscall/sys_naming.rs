@@ -8,19 +8,26 @@
 */
 use alloc::slice;
 use alloc::string::{String, ToString};
+use alloc::vec;
 use core::ptr::slice_from_raw_parts;
 use core::str::from_utf8;
 use core::mem;
-use naming::shared_types::{OpenOptions, SeekOrigin, RawDirent};
+use naming::shared_types::{FileStat, OpenOptions, PollHandle, SeekOrigin, RawDirent, WatchMask};
 use syscall::return_vals::{self, Errno};
 use num_enum::FromPrimitive;
 
 use crate::naming::api;
+use crate::storage;
 
 pub fn sys_open(path: *const u8, flags: OpenOptions) -> isize {
     return_vals::convert_syscall_result_to_ret_code(api::open(&ptr_to_string(path).unwrap(), flags))
 }
 
+/// Max. number of bytes passed to a file object per call. User buffers are copied through a kernel buffer,
+/// so that no page fault (e.g. of a file mapped into the buffer) occurs while a file system holds a lock.
+const MAX_TRANSFER_SIZE: usize = 0x10000;
+
+/// Read up to `MAX_TRANSFER_SIZE` bytes (returning less than requested is allowed)
 pub fn sys_read(fh: usize, buffer: *mut u8, buffer_length: usize) -> isize {
     if buffer.is_null() || buffer_length == 0 {
         return Errno::EINVAL as isize;
@@ -29,9 +36,16 @@ pub fn sys_read(fh: usize, buffer: *mut u8, buffer_length: usize) -> isize {
     unsafe {
         buf = slice::from_raw_parts_mut(buffer, buffer_length);
     }
-    return_vals::convert_syscall_result_to_ret_code(api::read(fh, buf))
+
+    let mut kernel_buf = vec![0u8; buffer_length.min(MAX_TRANSFER_SIZE)];
+    let result = api::read(fh, &mut kernel_buf).map(|len| {
+        buf[..len].copy_from_slice(&kernel_buf[..len]);
+        len
+    });
+    return_vals::convert_syscall_result_to_ret_code(result)
 }
 
+/// Write the buffer in parts of `MAX_TRANSFER_SIZE` bytes (stopping after a short write or an error)
 pub fn sys_write(fh: usize, buffer: *const u8, buffer_length: usize) -> isize {
     if buffer.is_null() || buffer_length == 0 {
         return Errno::EINVAL as isize;
@@ -40,7 +54,22 @@ pub fn sys_write(fh: usize, buffer: *const u8, buffer_length: usize) -> isize {
     unsafe {
         buf = slice::from_raw_parts(buffer, buffer_length);
     }
-    return_vals::convert_syscall_result_to_ret_code(api::write(fh, buf))
+
+    let mut written = 0;
+    for chunk in buf.chunks(MAX_TRANSFER_SIZE) {
+        let kernel_buf = chunk.to_vec();
+        match api::write(fh, &kernel_buf) {
+            Ok(len) => {
+                written += len;
+                if len < chunk.len() {
+                    break;
+                }
+            }
+            Err(error) if written == 0 => return error as isize,
+            Err(_) => break,
+        }
+    }
+    written as isize
 }
 
 pub fn sys_seek(fh: usize, offset: usize, origin: usize) -> isize {
@@ -51,6 +80,14 @@ pub fn sys_close(fh: usize) -> isize {
     return_vals::convert_syscall_result_to_ret_code(api::close(fh))
 }
 
+pub fn sys_dup(fh: usize) -> isize {
+    return_vals::convert_syscall_result_to_ret_code(api::dup(fh))
+}
+
+pub fn sys_dup2(fh: usize, new_fh: usize) -> isize {
+    return_vals::convert_syscall_result_to_ret_code(api::dup2(fh, new_fh))
+}
+
 pub fn sys_mkdir(path: *const u8) -> isize {
     return_vals::convert_syscall_result_to_ret_code(api::mkdir(&ptr_to_string(path).unwrap()))
 }
@@ -88,6 +125,22 @@ pub fn sys_readdir(fh: usize, buffer: *mut u8, buffer_length: usize) -> isize {
     return_vals::convert_syscall_result_to_ret_code(api::readdir(fh, dentry))
 }
 
+pub fn sys_poll(handles: *mut PollHandle, count: usize, timeout_ms: usize) -> isize {
+    if handles.is_null() && count > 0 {
+        return Errno::EINVAL as isize;
+    }
+    let handles = if count == 0 { &mut [] } else { unsafe { slice::from_raw_parts_mut(handles, count) } };
+    return_vals::convert_syscall_result_to_ret_code(api::poll(handles, timeout_ms))
+}
+
+pub fn sys_getdents(fh: usize, buffer: *mut u8, buffer_length: usize) -> isize {
+    if buffer.is_null() {
+        return Errno::EINVAL as isize;
+    }
+    let buf = unsafe { slice::from_raw_parts_mut(buffer, buffer_length) };
+    return_vals::convert_syscall_result_to_ret_code(api::getdents(fh, buf))
+}
+
 
 pub fn sys_cwd(buffer: *mut u8, buffer_length: usize) -> isize {
     if buffer.is_null() || buffer_length == 0 {
@@ -103,3 +156,126 @@ pub fn sys_cwd(buffer: *mut u8, buffer_length: usize) -> isize {
 pub fn sys_cd(path: *const u8) -> isize {
     return_vals::convert_syscall_result_to_ret_code(api::cd(&ptr_to_string(path).unwrap()))
 }
+
+pub fn sys_mount(device: *const u8, target: *const u8, fs_type: *const u8) -> isize {
+    let result = ptr_to_string(device).and_then(|device| {
+        ptr_to_string(target).and_then(|target| {
+            ptr_to_string(fs_type).and_then(|fs_type| api::mount(&device, &target, &fs_type))
+        })
+    });
+    return_vals::convert_syscall_result_to_ret_code(result)
+}
+
+pub fn sys_umount(target: *const u8) -> isize {
+    return_vals::convert_syscall_result_to_ret_code(ptr_to_string(target).and_then(|target| api::umount(&target)))
+}
+
+pub fn sys_unlink(path: *const u8) -> isize {
+    return_vals::convert_syscall_result_to_ret_code(ptr_to_string(path).and_then(|path| api::unlink(&path)))
+}
+
+pub fn sys_rmdir(path: *const u8) -> isize {
+    return_vals::convert_syscall_result_to_ret_code(ptr_to_string(path).and_then(|path| api::rmdir(&path)))
+}
+
+pub fn sys_rename(old_path: *const u8, new_path: *const u8) -> isize {
+    let result = ptr_to_string(old_path).and_then(|old_path| {
+        ptr_to_string(new_path).and_then(|new_path| api::rename(&old_path, &new_path))
+    });
+    return_vals::convert_syscall_result_to_ret_code(result)
+}
+
+pub fn sys_stat(path: *const u8, buffer: *mut u8, buffer_length: usize) -> isize {
+    if buffer.is_null() || buffer_length < mem::size_of::<FileStat>() {
+        return Errno::EINVAL as isize;
+    }
+    let result = ptr_to_string(path).and_then(|path| api::stat(&path)).map(|stat| {
+        unsafe { (buffer as *mut FileStat).write(stat) };
+        0
+    });
+    return_vals::convert_syscall_result_to_ret_code(result)
+}
+
+pub fn sys_fstat(fh: usize, buffer: *mut u8, buffer_length: usize) -> isize {
+    if buffer.is_null() || buffer_length < mem::size_of::<FileStat>() {
+        return Errno::EINVAL as isize;
+    }
+    let result = api::fstat(fh).map(|stat| {
+        unsafe { (buffer as *mut FileStat).write(stat) };
+        0
+    });
+    return_vals::convert_syscall_result_to_ret_code(result)
+}
+
+pub fn sys_symlink(target: *const u8, link_path: *const u8) -> isize {
+    let result = ptr_to_string(target).and_then(|target| {
+        ptr_to_string(link_path).and_then(|link_path| api::symlink(&target, &link_path))
+    });
+    return_vals::convert_syscall_result_to_ret_code(result)
+}
+
+/// Copy the target of the link `path` as null terminated string into `buffer` (truncated if too small). \
+/// Returns the number of bytes copied (without null terminator)
+pub fn sys_readlink(path: *const u8, buffer: *mut u8, buffer_length: usize) -> isize {
+    if buffer.is_null() || buffer_length == 0 {
+        return Errno::EINVAL as isize;
+    }
+    let buf: &mut[u8];
+    unsafe {
+        buf = slice::from_raw_parts_mut(buffer, buffer_length);
+    }
+
+    let result = ptr_to_string(path).and_then(|path| api::readlink(&path)).map(|target| {
+        let len = target.len().min(buffer_length - 1);
+        buf[..len].copy_from_slice(&target.as_bytes()[..len]);
+        buf[len] = 0;
+        len
+    });
+    return_vals::convert_syscall_result_to_ret_code(result)
+}
+
+/// Create an anonymous pipe and store the read handle and the write handle in `handles` (array of two handles)
+pub fn sys_pipe(handles: *mut usize) -> isize {
+    if handles.is_null() {
+        return Errno::EINVAL as isize;
+    }
+    let result = api::pipe().map(|(read_handle, write_handle)| {
+        unsafe {
+            handles.write(read_handle);
+            handles.add(1).write(write_handle);
+        }
+        0
+    });
+    return_vals::convert_syscall_result_to_ret_code(result)
+}
+
+/// Register a watch for the events `mask` (see `WatchMask`) of the directory or file `path`. \
+/// Returns the handle for reading the events
+pub fn sys_watch(path: *const u8, mask: usize) -> isize {
+    return_vals::convert_syscall_result_to_ret_code(ptr_to_string(path).and_then(|path| api::watch(&path, WatchMask::from_bits_truncate(mask))))
+}
+
+pub fn sys_mkfifo(path: *const u8) -> isize {
+    return_vals::convert_syscall_result_to_ret_code(ptr_to_string(path).and_then(|path| api::mkfifo(&path)))
+}
+
+pub fn sys_truncate(fh: usize, size: usize) -> isize {
+    return_vals::convert_syscall_result_to_ret_code(api::truncate(fh, size))
+}
+
+pub fn sys_chmod(path: *const u8, permissions: u32) -> isize {
+    return_vals::convert_syscall_result_to_ret_code(ptr_to_string(path).and_then(|path| api::chmod(&path, permissions)))
+}
+
+pub fn sys_chown(path: *const u8, uid: u32, gid: u32) -> isize {
+    return_vals::convert_syscall_result_to_ret_code(ptr_to_string(path).and_then(|path| api::chown(&path, uid, gid)))
+}
+
+pub fn sys_utimes(path: *const u8, accessed: u64, modified: u64) -> isize {
+    return_vals::convert_syscall_result_to_ret_code(ptr_to_string(path).and_then(|path| api::utimes(&path, accessed, modified)))
+}
+
+/// Write all cached data of block devices back
+pub fn sys_sync() -> isize {
+    return_vals::convert_syscall_result_to_ret_code(storage::sync().map(|_| 0))
+}
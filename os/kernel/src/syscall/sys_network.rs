@@ -0,0 +1,20 @@
+/* ╔═════════════════════════════════════════════════════════════════════════╗
+   ║ Module: sys_network                                                     ║
+   ╟─────────────────────────────────────────────────────────────────────────╢
+   ║ Descr.: All system calls related to networking.                         ║
+   ╚═════════════════════════════════════════════════════════════════════════╝
+*/
+use smoltcp::wire::Ipv4Address;
+
+use crate::naming::api;
+use crate::network::udp_socket::UdpSocket;
+use naming::shared_types::OpenOptions;
+use syscall::return_vals;
+
+/// Open a UDP socket bound to `port`, sending datagrams to `remote_address` (IPv4 address as number) and `remote_port`
+/// (no remote endpoint, if `remote_port` is 0). Returns the handle of the socket for reading, writing and `poll`.
+pub fn sys_udp_open(port: u16, remote_address: u32, remote_port: u16) -> isize {
+    let remote = if remote_port == 0 { None } else { Some((Ipv4Address::from(remote_address), remote_port)) };
+    let res = UdpSocket::open(port, remote).and_then(|socket| api::open_file_object(socket, OpenOptions::READWRITE));
+    return_vals::convert_syscall_result_to_ret_code(res)
+}
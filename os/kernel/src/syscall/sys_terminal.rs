@@ -8,10 +8,12 @@
 */
 use core::ptr::slice_from_raw_parts;
 use core::str::from_utf8;
-use crate::terminal;
+use crate::device::ps2::INPUT_QUEUE;
+use crate::{scheduler, terminal};
 
 pub fn sys_terminal_read() -> isize {
     let terminal = terminal();
+    scheduler().wait(&INPUT_QUEUE, None, || terminal.has_input());
     match terminal.read_byte() {
         -1 => panic!("Input stream closed!"),
         c => c as isize
@@ -7,9 +7,14 @@
    ╚═════════════════════════════════════════════════════════════════════════╝
 */
 
+use crate::memory::mmap;
 use crate::memory::vmm::{VirtualMemoryArea, VmaType};
 use crate::memory::{MemorySpace, PAGE_SIZE};
+use crate::naming::api;
 use crate::process_manager;
+use naming::shared_types::MapFlags;
+use syscall::return_vals::{self, Errno};
+use x86_64::VirtAddr;
 use x86_64::structures::paging::PageTableFlags;
 
 
@@ -26,3 +31,28 @@ pub fn sys_map_user_heap(size: usize) -> isize {
     heap_start.as_u64() as isize
 }
 
+/// Map `length` bytes of the file `fh` starting at `offset` (page aligned) into the address space of the current process. \
+/// Returns the start address of the mapping
+pub fn sys_mmap(fh: usize, offset: usize, length: usize, flags: usize) -> isize {
+    let process = process_manager().read().current_process();
+    let result = api::file(fh).and_then(|(file, options)| {
+        mmap::map_file(&process.virtual_address_space, file, options, offset, length, MapFlags::from_bits_truncate(flags))
+    });
+    return_vals::convert_syscall_result_to_ret_code(result.map(|addr| addr.as_u64() as usize))
+}
+
+/// Remove the mapping at `addr` with `length` bytes (created by `sys_mmap`)
+pub fn sys_munmap(addr: usize, length: usize) -> isize {
+    let process = process_manager().read().current_process();
+    let result = VirtAddr::try_new(addr as u64).map_err(|_| Errno::EINVAL)
+        .and_then(|addr| mmap::unmap_file(&process.virtual_address_space, addr, length));
+    return_vals::convert_syscall_result_to_ret_code(result)
+}
+
+/// Write dirty pages of shared mappings in the range `addr` with `length` bytes back to their files
+pub fn sys_msync(addr: usize, length: usize) -> isize {
+    let process = process_manager().read().current_process();
+    let result = VirtAddr::try_new(addr as u64).map_err(|_| Errno::EINVAL)
+        .and_then(|addr| mmap::sync_file(&process.virtual_address_space, addr, length));
+    return_vals::convert_syscall_result_to_ret_code(result)
+}
@@ -15,12 +15,13 @@ use x86_64::registers::control::{Efer, EferFlags};
 use x86_64::registers::model_specific::{KernelGsBase, LStar, Star};
 use x86_64::structures::gdt::SegmentSelector;
 use x86_64::{PrivilegeLevel, VirtAddr};
-use crate::syscall::sys_vmem::sys_map_user_heap;
+use crate::syscall::sys_vmem::{sys_map_user_heap, sys_mmap, sys_msync, sys_munmap};
 use crate::syscall::sys_time::{sys_get_date, sys_get_system_time, sys_set_date, };
 use crate::syscall::sys_concurrent::{sys_process_execute_binary, sys_process_exit, sys_process_id, sys_thread_create, sys_thread_exit,
-    sys_thread_id, sys_thread_join, sys_thread_sleep, sys_thread_switch};
+    sys_thread_id, sys_thread_join, sys_thread_sleep, sys_thread_switch, sys_get_uid, sys_set_uid, sys_get_gid, sys_set_gid};
 use crate::syscall::sys_terminal::{sys_terminal_read, sys_terminal_write};
 use crate::syscall::sys_naming::*;
+use crate::syscall::sys_network::sys_udp_open;
 
 use crate::{core_local_storage, tss};
 
@@ -107,7 +108,36 @@ impl SyscallTable {
                 sys_touch as *const _,
                 sys_readdir as *const _,
                 sys_cwd as *const _,
-                sys_cd as *const _,                
+                sys_cd as *const _,
+                sys_mount as *const _,
+                sys_umount as *const _,
+                sys_unlink as *const _,
+                sys_rmdir as *const _,
+                sys_rename as *const _,
+                sys_stat as *const _,
+                sys_fstat as *const _,
+                sys_symlink as *const _,
+                sys_readlink as *const _,
+                sys_pipe as *const _,
+                sys_mkfifo as *const _,
+                sys_truncate as *const _,
+                sys_sync as *const _,
+                sys_mmap as *const _,
+                sys_munmap as *const _,
+                sys_msync as *const _,
+                sys_chmod as *const _,
+                sys_chown as *const _,
+                sys_get_uid as *const _,
+                sys_set_uid as *const _,
+                sys_get_gid as *const _,
+                sys_set_gid as *const _,
+                sys_utimes as *const _,
+                sys_dup as *const _,
+                sys_dup2 as *const _,
+                sys_getdents as *const _,
+                sys_poll as *const _,
+                sys_udp_open as *const _,
+                sys_watch as *const _,
             ],
         }
     }
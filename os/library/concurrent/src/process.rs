@@ -6,7 +6,7 @@
    ║ Author: Fabian Ruhland, Michael Schoettner, 31.8.2024, HHU              ║
    ╚═════════════════════════════════════════════════════════════════════════╝
 */
-use syscall::{syscall, SystemCall};
+use syscall::{syscall, return_vals::Errno, SystemCall};
 
 pub struct Process {
     id: usize,
@@ -33,3 +33,23 @@ pub fn current() -> Option<Process> {
 pub fn exit() {
     syscall(SystemCall::ProcessExit, &[]).expect("Failed to exit process");
 }
+
+/// Return the user id of the current process (0 = root)
+pub fn uid() -> u32 {
+    syscall(SystemCall::GetUid, &[]).expect("Failed to get user id") as u32
+}
+
+/// Return the group id of the current process
+pub fn gid() -> u32 {
+    syscall(SystemCall::GetGid, &[]).expect("Failed to get group id") as u32
+}
+
+/// Set the user id of the current process (only root may switch to another user)
+pub fn set_uid(uid: u32) -> Result<usize, Errno> {
+    syscall(SystemCall::SetUid, &[uid as usize])
+}
+
+/// Set the group id of the current process (only root may switch to another group)
+pub fn set_gid(gid: u32) -> Result<usize, Errno> {
+    syscall(SystemCall::SetGid, &[gid as usize])
+}
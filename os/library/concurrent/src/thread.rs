@@ -66,9 +66,17 @@ pub fn exit() -> ! {
 }
 
 pub fn start_application(name: &str, args: Vec<&str>) -> Option<Thread> {
+    start_application_with_handles(name, args, &[])
+}
+
+/// Start application `name` and pass the opened objects in `handles` to it. \
+/// Each entry is a pair `(handle in child, handle in caller)`, e.g. `(0, pipe_read_handle)` for stdin.
+pub fn start_application_with_handles(name: &str, args: Vec<&str>, handles: &[(usize, usize)]) -> Option<Thread> {
     let res = syscall(SystemCall::ProcessExecuteBinary, &[name.as_bytes().as_ptr() as usize,
     name.len(),
-    ptr::from_ref(&args) as usize,]);
+    ptr::from_ref(&args) as usize,
+    handles.as_ptr() as usize,
+    handles.len(),]);
     match res {
         Ok(id) => Some(Thread::new(id as usize)),
         Err(_) => None,
@@ -15,11 +15,14 @@ extern crate bitflags;
 
 pub mod shared_types;
 
+use alloc::format;
 use alloc::string::String;
 use alloc::ffi::CString;
-use core::mem;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::{mem, ptr};
 
-use shared_types::{DirEntry, FileType, OpenOptions, RawDirent, SeekOrigin};
+use shared_types::{DirEntry, FileStat, FileType, MapFlags, OpenOptions, PollHandle, RawDirent, RawDirentHeader, RawWatchEventHeader, SeekOrigin, WatchEvent, WatchMask};
 use syscall::{SystemCall, return_vals::Errno, syscall};
 
 
@@ -56,6 +59,157 @@ pub fn close(fh: usize) -> Result<usize, Errno> {
     return syscall(SystemCall::Close, &[fh]);
 }
 
+/// Wait until one of `handles` is ready for its requested events (see `PollHandle`) or `timeout_ms`
+/// has elapsed (`POLL_INFINITE` waits forever, 0 returns immediately). \
+/// Returns the number of ready handles (0 after a timeout)
+pub fn poll(handles: &mut [PollHandle], timeout_ms: usize) -> Result<usize, Errno> {
+    syscall(SystemCall::Poll, &[handles.as_mut_ptr() as usize, handles.len(), timeout_ms])
+}
+
+/// Open a UDP socket bound to `port`. Written data is sent as one datagram to `remote` (IPv4 address, port),
+/// reading returns the next received datagram. \
+/// Returns the handle of the socket (to be closed with `close`)
+pub fn udp_open(port: u16, remote: Option<([u8; 4], u16)>) -> Result<usize, Errno> {
+    let (address, remote_port) = remote.unwrap_or(([0; 4], 0));
+    syscall(SystemCall::UdpOpen, &[port as usize, u32::from_be_bytes(address) as usize, remote_port as usize])
+}
+
+/// Duplicate `fh`, both handles share the opened object (including the position). \
+/// Returns the new handle
+pub fn dup(fh: usize) -> Result<usize, Errno> {
+    return syscall(SystemCall::Dup, &[fh]);
+}
+
+/// Let `new_fh` reference the same opened object as `fh` (an object opened with `new_fh` is closed before). \
+/// Returns `new_fh`
+pub fn dup2(fh: usize, new_fh: usize) -> Result<usize, Errno> {
+    return syscall(SystemCall::Dup2, &[fh, new_fh]);
+}
+
+/// Set the size of the file `fh` (opened for writing) to `size` bytes
+pub fn truncate(fh: usize, size: usize) -> Result<usize, Errno> {
+    return syscall(SystemCall::Truncate, &[fh, size]);
+}
+
+/// Set the permission bits of `path` (e.g. 0o755), only allowed for the owner and root
+pub fn chmod(path: &str, permissions: u32) -> Result<usize, Errno> {
+    match CString::new(path) {
+        Ok(c_path) => {
+            return syscall(SystemCall::Chmod, &[c_path.as_bytes().as_ptr() as usize, permissions as usize]);
+        }
+        Err(_) => Err(Errno::EBADSTR),
+    }
+}
+
+/// Set the owner `uid` and the group `gid` of `path`, only allowed for root
+pub fn chown(path: &str, uid: u32, gid: u32) -> Result<usize, Errno> {
+    match CString::new(path) {
+        Ok(c_path) => {
+            return syscall(SystemCall::Chown, &[c_path.as_bytes().as_ptr() as usize, uid as usize, gid as usize]);
+        }
+        Err(_) => Err(Errno::EBADSTR),
+    }
+}
+
+/// Set the time of the last access `accessed` and the last modification `modified` (in ms) of `path`,
+/// only allowed for the owner and root. `UTIME_NOW` sets the current time, `UTIME_OMIT` leaves a timestamp unchanged.
+pub fn utimes(path: &str, accessed: u64, modified: u64) -> Result<usize, Errno> {
+    match CString::new(path) {
+        Ok(c_path) => {
+            return syscall(SystemCall::Utimes, &[c_path.as_bytes().as_ptr() as usize, accessed as usize, modified as usize]);
+        }
+        Err(_) => Err(Errno::EBADSTR),
+    }
+}
+
+/// Map `length` bytes of the file `fh` starting at `offset` (multiple of the page size) into memory. \
+/// Returns the start address of the mapping
+pub fn mmap(fh: usize, offset: usize, length: usize, flags: MapFlags) -> Result<*mut u8, Errno> {
+    syscall(SystemCall::Mmap, &[fh, offset, length, flags.bits()]).map(|addr| addr as *mut u8)
+}
+
+/// Remove the mapping at `addr` with `length` bytes (as returned and used by `mmap`)
+pub fn munmap(addr: *mut u8, length: usize) -> Result<usize, Errno> {
+    return syscall(SystemCall::Munmap, &[addr as usize, length]);
+}
+
+/// Write changed pages of shared mappings in the range `addr` with `length` bytes back to their files
+pub fn msync(addr: *mut u8, length: usize) -> Result<usize, Errno> {
+    return syscall(SystemCall::Msync, &[addr as usize, length]);
+}
+
+/// Directory of the named shared memory objects
+pub const SHM_DIR: &str = "/dev/shm";
+
+/// Open the shared memory object `name` (e.g. "/buffer"), which is created by `OpenOptions::CREATE`. \
+/// Its size is set with `truncate` and it is mapped with `mmap` (using `MapFlags::SHARED`) into several processes.
+pub fn shm_open(name: &str, flags: OpenOptions) -> Result<usize, Errno> {
+    open(&format!("{}/{}", SHM_DIR, name.trim_start_matches('/')), flags)
+}
+
+/// Remove the name of the shared memory object `name`. \
+/// Its memory is freed, when it is no longer opened or mapped by any process.
+pub fn shm_unlink(name: &str) -> Result<usize, Errno> {
+    unlink(&format!("{}/{}", SHM_DIR, name.trim_start_matches('/')))
+}
+
+/// Write all cached data of block devices back
+pub fn sync() -> Result<usize, Errno> {
+    return syscall(SystemCall::Sync, &[]);
+}
+
+/// Create the named pipe (FIFO) `path`
+pub fn mkfifo(path: &str) -> Result<usize, Errno> {
+    match CString::new(path) {
+        Ok(c_path) => {
+            return syscall(SystemCall::Mkfifo, &[c_path.as_bytes().as_ptr() as usize]);
+        }
+        Err(_) => Err(Errno::EBADSTR),
+    }
+}
+
+/// Size of the buffer used by `read_watch_events`
+const WATCH_BUFFER_SIZE: usize = 4096;
+
+/// Watch the directory or file `path` for the events `mask`. \
+/// Returns a handle for `read_watch_events` (can be used with `poll` and is removed by `close`)
+pub fn watch(path: &str, mask: WatchMask) -> Result<usize, Errno> {
+    match CString::new(path) {
+        Ok(c_path) => {
+            return syscall(SystemCall::Watch, &[c_path.as_bytes().as_ptr() as usize, mask.bits()]);
+        }
+        Err(_) => Err(Errno::EBADSTR),
+    }
+}
+
+/// Read the pending events of the watch handle `fh` (blocks until there is at least one)
+pub fn read_watch_events(fh: usize) -> Result<Vec<WatchEvent>, Errno> {
+    let mut buf = vec![0u8; WATCH_BUFFER_SIZE];
+    let len = read(fh, &mut buf)?;
+
+    let mut events = Vec::new();
+    let mut pos = 0;
+    while pos < len {
+        let header = unsafe { ptr::read_unaligned(buf[pos..].as_ptr() as *const RawWatchEventHeader) };
+        let name_start = pos + RawWatchEventHeader::SIZE;
+        let new_name_start = name_start + header.name_len as usize;
+        events.push(WatchEvent {
+            mask: WatchMask::from_bits_truncate(header.mask as usize),
+            name: String::from_utf8_lossy(&buf[name_start..new_name_start]).into_owned(),
+            new_name: String::from_utf8_lossy(&buf[new_name_start..new_name_start + header.new_name_len as usize]).into_owned(),
+        });
+        pos += header.record_len as usize;
+    }
+    Ok(events)
+}
+
+/// Create an anonymous pipe, returns `(read handle, write handle)`
+pub fn pipe() -> Result<(usize, usize), Errno> {
+    let mut handles: [usize; 2] = [0; 2];
+    syscall(SystemCall::Pipe, &[handles.as_mut_ptr() as usize])?;
+    Ok((handles[0], handles[1]))
+}
+
 pub fn mkdir(path: &str) -> Result<usize, Errno> {
     match CString::new(path) {
         Ok(c_path) => {
@@ -74,34 +228,101 @@ pub fn touch(path: &str) -> Result<usize, Errno> {
     }
 }
 
-pub fn readdir(fh: usize) -> Result<Option<DirEntry>, Errno> {
-    let mut raw_dirent = RawDirent::new();
-    let ret = syscall(SystemCall::Readdir, &[
+/// Size of the buffer used by `ReadDir` for fetching entries
+const READDIR_BUFFER_SIZE: usize = 4096;
+
+/// Iterator over the entries of the directory `fh` (opened with `OpenOptions::DIRECTORY`), starting at its current position. \
+/// The entries are fetched in batches by the `getdents` syscall.
+pub fn readdir(fh: usize) -> ReadDir {
+    ReadDir {
         fh,
-        raw_dirent.as_mut_ptr() as usize,
-        mem::size_of::<RawDirent>(),
-    ]);
-    match ret {
-        Ok(rescode) => {
-            if rescode == 0 {
-                return Ok(None);
-            } else {
-                return Ok(DirEntry::from_dirent(&raw_dirent).clone());
+        buf: vec![0; READDIR_BUFFER_SIZE],
+        len: 0,
+        pos: 0,
+        offset: None,
+        done: false,
+    }
+}
+
+/// Fill `buf` with as many entries of the directory `fh` as fit (see `RawDirentHeader`). \
+/// Returns the number of bytes written (0 at the end of the directory)
+pub fn getdents(fh: usize, buf: &mut [u8]) -> Result<usize, Errno> {
+    syscall(SystemCall::Getdents, &[fh, buf.as_mut_ptr() as usize, buf.len()])
+}
+
+/// Iterator returned by `readdir`
+pub struct ReadDir {
+    fh: usize,
+    buf: Vec<u8>,
+    len: usize,          // number of valid bytes in `buf`
+    pos: usize,          // position of the next entry in `buf`
+    offset: Option<u64>, // offset of the next entry in the directory (known after the first entry)
+    done: bool,
+}
+
+impl ReadDir {
+    /// Offset of the next entry in the directory, which can be passed to `seek` (with `SeekOrigin::Start`)
+    /// for resuming the listing later. Returns `None`, if no entry has been returned yet.
+    pub fn offset(&self) -> Option<u64> {
+        self.offset
+    }
+}
+
+impl Iterator for ReadDir {
+    type Item = Result<DirEntry, Errno>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.done {
+                return None;
+            }
+
+            if self.pos < self.len {
+                let header = unsafe { ptr::read_unaligned(self.buf[self.pos..].as_ptr() as *const RawDirentHeader) };
+                let name_start = self.pos + RawDirentHeader::SIZE;
+                let name = String::from_utf8_lossy(&self.buf[name_start..name_start + header.name_len as usize]).into_owned();
+                self.pos += header.record_len as usize;
+                self.offset = Some(header.next_offset);
+
+                // skip entries with unknown types
+                if let Some(file_type) = file_type(header.d_type as usize) {
+                    return Some(Ok(DirEntry { inode: header.inode, file_type, name }));
+                }
+                continue;
+            }
+
+            match getdents(self.fh, &mut self.buf) {
+                Ok(0) => self.done = true,
+                Ok(len) => {
+                    self.len = len;
+                    self.pos = 0;
+                }
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(error));
+                }
             }
         }
-        Err(e) => Err(e),
+    }
+}
+
+/// Convert the type of a raw directory entry into a `FileType`
+fn file_type(d_type: usize) -> Option<FileType> {
+    match d_type {
+        1 => Some(FileType::Fifo),
+        2 => Some(FileType::CharDevice),
+        4 => Some(FileType::Directory),
+        6 => Some(FileType::BlockDevice),
+        8 => Some(FileType::Regular),
+        10 => Some(FileType::Link),
+        _ => None, // unsupported file types
     }
 }
 
 impl DirEntry {
     pub fn from_dirent(dirent: &RawDirent) -> Option<Self> {
         // Convert d_type to a FileType enum
-        let file_type = match dirent.d_type {
-            4 => FileType::Directory,
-            8 => FileType::Regular,
-            10 => FileType::Link,
-            _ => return None, // Return None for unsupported file types
-        };
+        let file_type = file_type(dirent.d_type)?;
 
         // Convert d_name (null-terminated) to a Rust String
         let name = dirent
@@ -116,7 +337,7 @@ impl DirEntry {
             return None;
         }
 
-        Some(DirEntry { file_type, name })
+        Some(DirEntry { inode: 0, file_type, name })
     }
 }
 
@@ -144,4 +365,108 @@ pub fn cd(path: &str) -> Result<usize, Errno> {
         }
         Err(_) => Err(Errno::EBADSTR),
     }
-}
\ No newline at end of file
+}
+
+pub fn mount(device: &str, target: &str, fs_type: &str) -> Result<usize, Errno> {
+    match (CString::new(device), CString::new(target), CString::new(fs_type)) {
+        (Ok(c_device), Ok(c_target), Ok(c_fs_type)) => {
+            return syscall(SystemCall::Mount, &[
+                c_device.as_bytes().as_ptr() as usize,
+                c_target.as_bytes().as_ptr() as usize,
+                c_fs_type.as_bytes().as_ptr() as usize,
+            ]);
+        }
+        _ => Err(Errno::EBADSTR),
+    }
+}
+
+pub fn umount(target: &str) -> Result<usize, Errno> {
+    match CString::new(target) {
+        Ok(c_target) => {
+            return syscall(SystemCall::Umount, &[c_target.as_bytes().as_ptr() as usize]);
+        }
+        Err(_) => Err(Errno::EBADSTR),
+    }
+}
+
+pub fn unlink(path: &str) -> Result<usize, Errno> {
+    match CString::new(path) {
+        Ok(c_path) => {
+            return syscall(SystemCall::Unlink, &[c_path.as_bytes().as_ptr() as usize]);
+        }
+        Err(_) => Err(Errno::EBADSTR),
+    }
+}
+
+pub fn rmdir(path: &str) -> Result<usize, Errno> {
+    match CString::new(path) {
+        Ok(c_path) => {
+            return syscall(SystemCall::Rmdir, &[c_path.as_bytes().as_ptr() as usize]);
+        }
+        Err(_) => Err(Errno::EBADSTR),
+    }
+}
+
+pub fn rename(old_path: &str, new_path: &str) -> Result<usize, Errno> {
+    match (CString::new(old_path), CString::new(new_path)) {
+        (Ok(c_old_path), Ok(c_new_path)) => {
+            return syscall(SystemCall::Rename, &[
+                c_old_path.as_bytes().as_ptr() as usize,
+                c_new_path.as_bytes().as_ptr() as usize,
+            ]);
+        }
+        _ => Err(Errno::EBADSTR),
+    }
+}
+
+pub fn stat(path: &str) -> Result<FileStat, Errno> {
+    let mut file_stat = FileStat::new();
+    match CString::new(path) {
+        Ok(c_path) => {
+            syscall(SystemCall::Stat, &[
+                c_path.as_bytes().as_ptr() as usize,
+                file_stat.as_mut_ptr() as usize,
+                mem::size_of::<FileStat>(),
+            ])?;
+            Ok(file_stat)
+        }
+        Err(_) => Err(Errno::EBADSTR),
+    }
+}
+
+pub fn fstat(fh: usize) -> Result<FileStat, Errno> {
+    let mut file_stat = FileStat::new();
+    syscall(SystemCall::Fstat, &[
+        fh,
+        file_stat.as_mut_ptr() as usize,
+        mem::size_of::<FileStat>(),
+    ])?;
+    Ok(file_stat)
+}
+
+pub fn symlink(target: &str, link_path: &str) -> Result<usize, Errno> {
+    match (CString::new(target), CString::new(link_path)) {
+        (Ok(c_target), Ok(c_link_path)) => {
+            return syscall(SystemCall::Symlink, &[
+                c_target.as_bytes().as_ptr() as usize,
+                c_link_path.as_bytes().as_ptr() as usize,
+            ]);
+        }
+        _ => Err(Errno::EBADSTR),
+    }
+}
+
+pub fn readlink(path: &str) -> Result<String, Errno> {
+    let mut buf: [u8; 512] = [0; 512]; // buffer for the target path
+    match CString::new(path) {
+        Ok(c_path) => {
+            let len = syscall(SystemCall::Readlink, &[
+                c_path.as_bytes().as_ptr() as usize,
+                buf.as_mut_ptr() as usize,
+                buf.len(),
+            ])?;
+            Ok(String::from_utf8_lossy(&buf[..len]).into_owned())
+        }
+        Err(_) => Err(Errno::EBADSTR),
+    }
+}
@@ -7,16 +7,102 @@
    ╚═════════════════════════════════════════════════════════════════════════╝
 */
 use alloc::string::String;
+use core::mem;
 use num_enum::{FromPrimitive, IntoPrimitive};
 
+/// Description: Handle of the standard input of a process (e.g. the read end of a pipe)
+pub const STDIN: usize = 0;
+
+/// Description: Handle of the standard output of a process (e.g. the write end of a pipe)
+pub const STDOUT: usize = 1;
+
+/// Description: Handle of the standard error output of a process
+pub const STDERR: usize = 2;
+
+/// Description: Passed to `utimes` as time to set the timestamp to the current time
+pub const UTIME_NOW: u64 = u64::MAX;
+
+/// Description: Passed to `utimes` as time to leave the timestamp unchanged
+pub const UTIME_OMIT: u64 = u64::MAX - 1;
+
 bitflags! {
-    /// Description: Option flags for opening objects
+    /// Description: Option flags for opening objects (independent bits, may be combined)
     pub struct OpenOptions: usize {
-        const READONLY  = 1;
-        const READWRITE = 2;
-        const CREATE    = 3;
-        const EXCLUSIVE = 4;
-        const DIRECTORY = 5;
+        const READONLY  = 0x001;
+        const WRITEONLY = 0x002;
+        const READWRITE = 0x004;
+        const CREATE    = 0x008; // create a regular file, if it does not exist
+        const EXCLUSIVE = 0x010; // together with CREATE: fail with EEXIST, if the file exists
+        const DIRECTORY = 0x020; // fail with ENOTDIR, if the object is not a directory
+        const NOFOLLOW  = 0x040; // do not follow a symbolic link in the last path component
+        const APPEND    = 0x080; // each write appends at the end of the file
+        const TRUNCATE  = 0x100; // truncate a regular file to length 0 (requires write access)
+    }
+}
+
+impl OpenOptions {
+    /// Reading is allowed unless the object is opened write-only
+    pub fn is_readable(&self) -> bool {
+        self.contains(OpenOptions::READWRITE) || !self.contains(OpenOptions::WRITEONLY)
+    }
+
+    /// Writing is allowed if the object is opened write-only or read-write
+    pub fn is_writable(&self) -> bool {
+        self.intersects(OpenOptions::WRITEONLY | OpenOptions::READWRITE)
+    }
+}
+
+bitflags! {
+    /// Description: Flags for mapping a file into memory (either SHARED or PRIVATE must be set)
+    pub struct MapFlags: usize {
+        const READ    = 0x1; // pages can be read
+        const WRITE   = 0x2; // pages can be written
+        const SHARED  = 0x4; // changes are written back to the file and shared with other shared mappings
+        const PRIVATE = 0x8; // changes are only visible in the mapping and never written back
+    }
+}
+
+bitflags! {
+    /// Description: Events of a handle reported by `poll`
+    pub struct PollFlags: usize {
+        const READABLE = 0x1; // reading does not block (data or end of file available)
+        const WRITABLE = 0x2; // writing does not block
+        const HANGUP   = 0x4; // the other side has been closed (always reported)
+        const INVALID  = 0x8; // the handle is not open (always reported)
+    }
+}
+
+bitflags! {
+    /// Description: Events of a watched directory or file reported by a watch handle (see `watch`)
+    pub struct WatchMask: usize {
+        const CREATE   = 0x1;  // an entry has been created in the watched directory
+        const DELETE   = 0x2;  // an entry has been removed from the watched directory
+        const MODIFY   = 0x4;  // the content of the watched file has been changed
+        const RENAME   = 0x8;  // an entry of the watched directory has been renamed or moved
+        const OVERFLOW = 0x10; // events have been lost, because they were not read in time (always reported)
+    }
+}
+
+/// Description: Timeout for `poll`, waiting until a handle becomes ready
+pub const POLL_INFINITE: usize = usize::MAX;
+
+/// Description: An entry passed to the `poll` syscall. The kernel sets `revents` to the
+/// ready events of `handle` (restricted to `events` plus `HANGUP` and `INVALID`).
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub struct PollHandle {
+    pub handle: usize,
+    pub events: usize,  // requested `PollFlags`
+    pub revents: usize, // returned `PollFlags`
+}
+
+impl PollHandle {
+    pub fn new(handle: usize, events: PollFlags) -> Self {
+        PollHandle { handle, events: events.bits(), revents: 0 }
+    }
+
+    pub fn revents(&self) -> PollFlags {
+        PollFlags::from_bits_truncate(self.revents)
     }
 }
 
@@ -35,7 +121,10 @@ pub enum SeekOrigin {
 #[repr(u8)]
 #[non_exhaustive]
 pub enum FileType {
+    Fifo = 1,
+    CharDevice = 2,
     Directory = 4,
+    BlockDevice = 6,
     Regular = 8,
     Link = 10,
 }
@@ -43,6 +132,7 @@ pub enum FileType {
 /// A directory entry 
 #[derive(Debug, Clone)]
 pub struct DirEntry {
+    pub inode: u64, // number of the named object, unique within its file system (0 if unknown)
     pub file_type: FileType,
     pub name: String,
 }
@@ -70,3 +160,91 @@ impl RawDirent {
     }
 }
 
+/// Description: header of a directory entry written by the `getdents` syscall into the caller's buffer. \
+/// Each entry consists of this header followed by the name (not null terminated) and is padded to a multiple of 8 bytes.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub struct RawDirentHeader {
+    pub inode: u64,       // number of the named object, unique within its file system
+    pub next_offset: u64, // offset of the next entry in the directory (for resuming with `seek`)
+    pub record_len: u16,  // length of the entry in bytes (header, name and padding)
+    pub name_len: u16,    // length of the name in bytes
+    pub d_type: u8,       // type of file
+}
+
+impl RawDirentHeader {
+    pub const SIZE: usize = mem::size_of::<RawDirentHeader>();
+
+    /// Length of an entry with a name of `name_len` bytes
+    pub const fn record_len(name_len: usize) -> usize {
+        (RawDirentHeader::SIZE + name_len).next_multiple_of(8)
+    }
+}
+
+
+/// Description: header of an event read from a watch handle. Each event consists of this header followed by the
+/// name of the affected entry and the new name of a renamed entry (both not null terminated, empty if unused)
+/// and is padded to a multiple of 8 bytes.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub struct RawWatchEventHeader {
+    pub mask: u32,         // kind of event (one bit of `WatchMask`)
+    pub record_len: u16,   // length of the event in bytes (header, names and padding)
+    pub name_len: u16,     // length of the name in bytes
+    pub new_name_len: u16, // length of the new name in bytes (only for `RENAME`)
+}
+
+impl RawWatchEventHeader {
+    pub const SIZE: usize = mem::size_of::<RawWatchEventHeader>();
+
+    /// Length of an event with names of `name_len` and `new_name_len` bytes
+    pub const fn record_len(name_len: usize, new_name_len: usize) -> usize {
+        (RawWatchEventHeader::SIZE + name_len + new_name_len).next_multiple_of(8)
+    }
+}
+
+/// An event of a watched directory or file
+#[derive(Debug, Clone)]
+pub struct WatchEvent {
+    pub mask: WatchMask,
+    pub name: String,     // entry in the watched directory (empty for events of a watched file)
+    pub new_name: String, // new name of a renamed entry
+}
+
+
+/// Description: meta data of a named object, returned by the `stat` and `fstat` syscalls
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub struct FileStat {
+    pub file_type: FileType,  // type of the named object
+    pub mode: u32,            // mode bits of the named object
+    pub size: usize,          // size in bytes
+    pub blocks: usize,        // number of 512 byte blocks allocated for the content (holes of sparse files need none)
+    pub created_time: u64,    // time of creation (ms since 1.1.1970 or since boot without wall clock)
+    pub modified_time: u64,   // time of last modification (ms)
+    pub accessed_time: u64,   // time of last access (ms)
+    pub permissions: u32,     // permission bits (read, write, execute for owner, group and others, e.g. 0o755)
+    pub uid: u32,             // user id of the owner
+    pub gid: u32,             // group id
+}
+
+impl FileStat {
+    pub fn new() -> Self {
+        FileStat {
+            file_type: FileType::Regular,
+            mode: 0,
+            size: 0,
+            blocks: 0,
+            created_time: 0,
+            modified_time: 0,
+            accessed_time: 0,
+            permissions: 0,
+            uid: 0,
+            gid: 0,
+        }
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut u8 {
+        self as *mut FileStat as *mut u8
+    }
+}
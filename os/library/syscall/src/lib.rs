@@ -42,6 +42,35 @@ pub enum SystemCall {
     Readdir,
     Cwd,
     Cd,
+    Mount,
+    Umount,
+    Unlink,
+    Rmdir,
+    Rename,
+    Stat,
+    Fstat,
+    Symlink,
+    Readlink,
+    Pipe,
+    Mkfifo,
+    Truncate,
+    Sync,
+    Mmap,
+    Munmap,
+    Msync,
+    Chmod,
+    Chown,
+    GetUid,
+    SetUid,
+    GetGid,
+    SetGid,
+    Utimes,
+    Dup,
+    Dup2,
+    Getdents,
+    Poll,
+    UdpOpen,
+    Watch,
     // no syscall, just marking last number, see NUM_SYSCALLS
     // insert any new system calls before this marker
     LastEntryMarker,
@@ -25,6 +25,21 @@ pub enum Errno {
     EINVALH    = -9,  // Invalid handle
     ENOTEMPTY  = -10, // Directory not empty
     EBADSTR    = -11, // Bad string
+    EIO        = -12, // Input/output error
+    ENOSPC     = -13, // No space left on device
+    EBUSY      = -14, // Device or resource busy
+    EROFS      = -15, // Read-only file system
+    ENOEXEC    = -16, // Exec format error
+    EISDIR     = -17, // Is a directory
+    ENOTSUP    = -18, // Operation not supported
+    EXDEV      = -19, // Cross-device link
+    ELOOP      = -20, // Too many levels of symbolic links
+    EPIPE      = -21, // Broken pipe (no reader)
+    ENODEV     = -22, // Operation not supported by the device (e.g. mapping a pipe)
+    EADDRINUSE = -23, // Address (port) already in use
+    ENOTCONN   = -24, // Socket has no remote endpoint
+    EPERM      = -25, // Operation not permitted (requires root)
+    ENOMEM     = -26, // Out of memory
 }
 
 
@@ -1,17 +1,44 @@
 /* ╔═════════════════════════════════════════════════════════════════════════╗
    ║ Module: read                                                            ║
    ╟─────────────────────────────────────────────────────────────────────────╢
-   ║ Descr.: Read a input char from terminal.                                ║
+   ║ Descr.: Read a input char from the standard input (terminal).           ║
    ╟─────────────────────────────────────────────────────────────────────────╢
    ║ Author: Fabian Ruhland, 31.8.2024, HHU                                  ║
    ╚═════════════════════════════════════════════════════════════════════════╝
 */
-use syscall::{syscall, SystemCall};
+use syscall::{return_vals::Errno, syscall, SystemCall};
 
+/// Handle of the standard input (see `naming::shared_types::STDIN`)
+const STDIN: usize = 0;
+
+/// Read the next char from the standard input (the terminal, unless redirected by the shell). \
+/// Returns `None` at the end of the input or if the input is not valid UTF-8.
 pub fn read() -> Option<char> {
-    let res = syscall(SystemCall::TerminalRead, &[]);
-    match res {
-        Ok(ch) => Some(char::from_u32(ch as u32).unwrap()),
-        Err(_) => None,
-    }    
-}
\ No newline at end of file
+    let mut buf = [0u8; 4];
+    match read_byte(&mut buf[0..1]) {
+        Ok(1) => {}
+        // The process has closed its standard input, read from the terminal directly
+        Err(Errno::EINVALH) => {
+            return syscall(SystemCall::TerminalRead, &[]).ok().and_then(|ch| char::from_u32(ch as u32));
+        }
+        _ => return None,
+    }
+
+    // Read the remaining bytes of a multi-byte UTF-8 char
+    let len = match buf[0] {
+        0x00..=0x7f => 1,
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        _ => 4,
+    };
+    for i in 1..len {
+        if read_byte(&mut buf[i..i + 1]) != Ok(1) {
+            return None;
+        }
+    }
+    core::str::from_utf8(&buf[..len]).ok().and_then(|string| string.chars().next())
+}
+
+fn read_byte(buf: &mut [u8]) -> Result<usize, Errno> {
+    syscall(SystemCall::Read, &[STDIN, buf.as_mut_ptr() as usize, buf.len()])
+}
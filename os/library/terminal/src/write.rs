@@ -9,7 +9,11 @@
 use core::fmt;
 use core::fmt::Write;
 use spin::Mutex;
-use syscall::{syscall, SystemCall};
+use syscall::{return_vals::Errno, syscall, SystemCall};
+
+/// Handles of the standard output and the standard error output (see `naming::shared_types`)
+const STDOUT: usize = 1;
+const STDERR: usize = 2;
 
 #[macro_export]
 macro_rules! print {
@@ -24,26 +28,58 @@ macro_rules! println {
     ($fmt:expr, $($arg:tt)*) => (print!(concat!($fmt, "\n"), $($arg)*));
 }
 
-static WRITER: Mutex<Writer> = Mutex::new(Writer::new());
+#[macro_export]
+macro_rules! eprint {
+    ($($arg:tt)*) => ({
+        $crate::write::eprint(format_args!($($arg)*));
+    });
+}
+
+#[macro_export]
+macro_rules! eprintln {
+    ($fmt:expr) => (eprint!(concat!($fmt, "\n")));
+    ($fmt:expr, $($arg:tt)*) => (eprint!(concat!($fmt, "\n"), $($arg)*));
+}
+
+static WRITER: Mutex<Writer> = Mutex::new(Writer::new(STDOUT));
+static ERROR_WRITER: Mutex<Writer> = Mutex::new(Writer::new(STDERR));
 
+/// Write to standard output (write errors, e.g. of a redirection to a full disk, are ignored)
 pub fn print(args: fmt::Arguments) {
-    WRITER.lock().write_fmt(args).unwrap();
+    let _ = WRITER.lock().write_fmt(args);
 }
 
-struct Writer {}
+/// Write to standard error (errors are ignored)
+pub fn eprint(args: fmt::Arguments) {
+    let _ = ERROR_WRITER.lock().write_fmt(args);
+}
+
+/// Writes to a standard handle of the process (the terminal, unless redirected)
+struct Writer {
+    handle: usize,
+}
 
 impl Writer {
-    const fn new() -> Self {
-        Self {}
+    const fn new(handle: usize) -> Self {
+        Self { handle }
     }
 }
 
 impl Write for Writer {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        let res = syscall(
-            SystemCall::TerminalWrite,
-            &[s.as_bytes().as_ptr() as usize, s.len()],
-        );
+        if s.is_empty() {
+            return Ok(());
+        }
+
+        // Write to the standard handle, if the process still has it (e.g. a pipe or file set up by the shell)
+        let res = match syscall(SystemCall::Write, &[self.handle, s.as_bytes().as_ptr() as usize, s.len()]) {
+            Err(Errno::EINVALH) => syscall(
+                SystemCall::TerminalWrite,
+                &[s.as_bytes().as_ptr() as usize, s.len()],
+            ),
+            Err(Errno::EPIPE) => Ok(0), // reader has terminated, discard the output
+            res => res,
+        };
         match res {
             Ok(_) => Ok(()),
             Err(_) => Err(fmt::Error),